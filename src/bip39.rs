@@ -0,0 +1,132 @@
+//! BIP-39 mnemonics: random entropy written as English words, ending in a checksum.
+
+use std::io::Write;
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use crate::generator::{Error, SecureRng};
+use crate::output::{self, OutputOptions};
+use crate::passphrase::BIP39_WORDLIST;
+
+/// The mnemonic lengths BIP-39 defines, in words.
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// The bits each word encodes.
+const BITS_PER_WORD: usize = 11;
+
+/// Encode `entropy` as a mnemonic. BIP-39 allows 16, 20, 24, 28, or 32 bytes, which give
+/// 12 to 24 words; the checksum is the first `entropy.len() / 4` bits of its SHA-256 hash.
+pub fn mnemonic(entropy: &[u8]) -> Result<String, Error> {
+    if !(16..=32).contains(&entropy.len()) || !entropy.len().is_multiple_of(4) {
+        return Err(Error::InvalidConfig(format!(
+            "BIP-39 entropy must be 16, 20, 24, 28, or 32 bytes, not {}.",
+            entropy.len()
+        )));
+    }
+
+    let hash = Sha256::digest(entropy);
+    let checksum_bits = entropy.len() / 4;
+    let mut bits: Vec<bool> = entropy
+        .iter()
+        .chain(hash.iter().take(1))
+        .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+        .take(entropy.len() * 8 + checksum_bits)
+        .collect();
+
+    let words: Vec<&str> = BIP39_WORDLIST.lines().collect();
+    let mnemonic = bits
+        .chunks(BITS_PER_WORD)
+        .map(|chunk| {
+            let index = chunk
+                .iter()
+                .fold(0, |index, bit| index << 1 | usize::from(*bit));
+            words[index]
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    bits.zeroize();
+    Ok(mnemonic)
+}
+
+// The entropy behind a mnemonic of `words` words. Every bit but the checksum is random.
+fn entropy_bytes(words: usize) -> usize {
+    words * BITS_PER_WORD * 32 / 33 / 8
+}
+
+/// Generate `count` mnemonics of `words` words with the given random number generator and
+/// write them to `output` in the format described by `options`.
+pub fn generate_mnemonics<R: SecureRng + ?Sized>(
+    words: usize,
+    count: usize,
+    rng: &mut R,
+    options: &OutputOptions,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    if !WORD_COUNTS.contains(&words) {
+        return Err(Error::InvalidConfig(format!(
+            "A BIP-39 mnemonic has 12, 15, 18, 21, or 24 words, not {}.",
+            words
+        )));
+    }
+
+    let entropy_bytes = entropy_bytes(words);
+    let batch = output::Batch {
+        categories: vec!["words".to_string()],
+        count,
+        entropy: output::Entropy::Fixed((entropy_bytes * 8) as f64),
+    };
+    let mut formatter = output::formatter(options, output, &batch, rng);
+
+    let mnemonics = (0..count).map(|_| {
+        let mut entropy = vec![0u8; entropy_bytes];
+        rng.fill(entropy.as_mut_slice());
+        let mnemonic = mnemonic(&entropy);
+        entropy.zeroize();
+        mnemonic
+    });
+    output::write_all(mnemonics, &mut *formatter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vectors from the BIP-39 reference implementation.
+    #[test]
+    fn mnemonic_matches_the_reference_vectors() {
+        assert_eq!(
+            mnemonic(&[0x00; 16]).unwrap(),
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+        );
+        assert_eq!(
+            mnemonic(&[0x7f; 16]).unwrap(),
+            "legal winner thank year wave sausage worth useful legal winner thank yellow"
+        );
+        assert_eq!(
+            mnemonic(&[0xff; 32]).unwrap(),
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote"
+        );
+    }
+
+    #[test]
+    fn entropy_sizes_match_the_word_counts() {
+        let sizes: Vec<usize> = WORD_COUNTS
+            .iter()
+            .map(|&words| entropy_bytes(words))
+            .collect();
+        assert_eq!(sizes, [16, 20, 24, 28, 32]);
+        for words in WORD_COUNTS {
+            let mnemonic = mnemonic(&vec![0x5a; entropy_bytes(words)]).unwrap();
+            assert_eq!(mnemonic.split(' ').count(), words);
+        }
+    }
+
+    #[test]
+    fn mnemonic_rejects_other_entropy_sizes() {
+        for len in [0, 12, 15, 17, 33, 36] {
+            assert!(mnemonic(&vec![0; len]).is_err(), "{len}");
+        }
+    }
+}
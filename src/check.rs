@@ -0,0 +1,153 @@
+//! Analysis of existing passwords against this tool's character sets and constraints.
+
+use std::collections::BTreeSet;
+
+use crate::generator::{self, AMBIGUOUS, CharClass};
+use crate::strength::{self, Strength};
+
+/// What can be learned about a password from its characters alone.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    /// The number of characters.
+    pub length: usize,
+    /// The built-in categories the password draws from.
+    pub classes: Vec<CharClass>,
+    /// The characters outside every built-in category, without duplicates.
+    pub other_chars: Vec<char>,
+    /// The size of the character pool the password appears to come from: every built-in
+    /// category it uses, plus its other characters.
+    pub pool_size: usize,
+    /// The entropy of a random password of this length from that pool.
+    pub entropy_bits: f64,
+    /// The zxcvbn estimate.
+    pub strength: Strength,
+}
+
+/// Analyze `password`, assuming it was drawn uniformly from the categories it uses.
+pub fn analyze(password: &str) -> Analysis {
+    let classes: BTreeSet<CharClass> = password.chars().filter_map(CharClass::of).collect();
+    let other_chars: BTreeSet<char> = password
+        .chars()
+        .filter(|c| CharClass::of(*c).is_none())
+        .collect();
+
+    let pool_size = classes
+        .iter()
+        .map(|class| class.chars().chars().count())
+        .sum::<usize>()
+        + other_chars.len();
+    let length = password.chars().count();
+
+    Analysis {
+        length,
+        classes: classes.into_iter().collect(),
+        other_chars: other_chars.into_iter().collect(),
+        pool_size,
+        entropy_bits: generator::entropy_bits(pool_size, length),
+        strength: strength::estimate(password),
+    }
+}
+
+/// Requirements a password must meet, mirroring the generation options.
+#[derive(Clone, Debug, Default)]
+pub struct Policy {
+    pub min_length: Option<usize>,
+    /// Categories that must appear at least once.
+    pub required: Vec<CharClass>,
+    pub min_lower: usize,
+    pub min_upper: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+    pub max_lower: Option<usize>,
+    pub max_upper: Option<usize>,
+    pub max_digits: Option<usize>,
+    pub max_symbols: Option<usize>,
+    /// Characters that must not appear.
+    pub exclude_chars: Option<String>,
+    /// Reject the visually ambiguous characters in [`AMBIGUOUS`].
+    pub avoid_ambiguous: bool,
+}
+
+impl Policy {
+    /// Describe every requirement `password` fails to meet. An empty list means it passes.
+    pub fn violations(&self, password: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        let length = password.chars().count();
+        if let Some(min_length) = self.min_length
+            && length < min_length
+        {
+            violations.push(format!(
+                "It is {} characters long, but at least {} are required.",
+                length, min_length
+            ));
+        }
+
+        for class in CharClass::ALL {
+            let count = password
+                .chars()
+                .filter(|c| CharClass::of(*c) == Some(class))
+                .count();
+            let (min, max) = self.limits(class);
+            let min = if self.required.contains(&class) {
+                min.max(1)
+            } else {
+                min
+            };
+
+            if count < min {
+                violations.push(format!(
+                    "It contains {} {}, but at least {} are required.",
+                    count,
+                    class.name(),
+                    min
+                ));
+            }
+            if let Some(max) = max
+                && count > max
+            {
+                violations.push(format!(
+                    "It contains {} {}, but at most {} are allowed.",
+                    count,
+                    class.name(),
+                    max
+                ));
+            }
+        }
+
+        if let Some(exclude_chars) = &self.exclude_chars {
+            let found: String = unique_matches(password, exclude_chars);
+            if !found.is_empty() {
+                violations.push(format!("It contains excluded characters: {}", found));
+            }
+        }
+
+        if self.avoid_ambiguous {
+            let found: String = unique_matches(password, AMBIGUOUS);
+            if !found.is_empty() {
+                violations.push(format!("It contains ambiguous characters: {}", found));
+            }
+        }
+        violations
+    }
+
+    fn limits(&self, class: CharClass) -> (usize, Option<usize>) {
+        match class {
+            CharClass::Lower => (self.min_lower, self.max_lower),
+            CharClass::Upper => (self.min_upper, self.max_upper),
+            CharClass::Digit => (self.min_digits, self.max_digits),
+            CharClass::Symbol => (self.min_symbols, self.max_symbols),
+        }
+    }
+}
+
+// The characters of `password` that appear in `set`, once each in order of appearance.
+fn unique_matches(password: &str, set: &str) -> String {
+    let mut found = String::new();
+    for c in password.chars().filter(|c| set.contains(*c)) {
+        if !found.contains(c) {
+            found.push(c);
+        }
+    }
+    found
+}
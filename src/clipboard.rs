@@ -0,0 +1,151 @@
+//! Access to the system clipboard through the platform's command-line tools.
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use zeroize::Zeroize;
+
+/// The hidden command-line flag that makes the binary clear the clipboard after a delay.
+pub const CLEAR_FLAG: &str = "--clear-clipboard";
+
+// Candidate (program, arguments) pairs for copying, in order of preference.
+fn copy_commands() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", &[])]
+    } else if cfg!(windows) {
+        vec![("clip", &[])]
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        vec![
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    } else {
+        vec![
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ]
+    }
+}
+
+// Candidate (program, arguments) pairs for pasting, in order of preference.
+fn paste_commands() -> Vec<(&'static str, &'static [&'static str])> {
+    if cfg!(target_os = "macos") {
+        vec![("pbpaste", &[])]
+    } else if cfg!(windows) {
+        vec![("powershell", &["-NoProfile", "-Command", "Get-Clipboard"])]
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        vec![
+            ("wl-paste", &["--no-newline"]),
+            ("xclip", &["-selection", "clipboard", "-o"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    } else {
+        vec![
+            ("xclip", &["-selection", "clipboard", "-o"]),
+            ("xsel", &["--clipboard", "--output"]),
+        ]
+    }
+}
+
+// The bytes `program` reads as `text`. Windows' `clip` reads the console code page unless
+// given UTF-16 with a byte order mark, which keeps characters such as emoji intact.
+fn encode(program: &str, text: &str) -> Vec<u8> {
+    if program != "clip" || text.is_ascii() {
+        return text.as_bytes().to_vec();
+    }
+    std::iter::once('\u{FEFF}')
+        .chain(text.chars())
+        .collect::<String>()
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/// Place `text` on the system clipboard.
+///
+/// Fails with [`io::ErrorKind::NotFound`] when no clipboard tool is installed or none of them
+/// can reach a clipboard, e.g. on a headless server.
+pub fn copy(text: &str) -> io::Result<()> {
+    let commands = copy_commands();
+
+    for (program, args) in &commands {
+        // The tools may keep running to serve the selection, so they must not hold our
+        // stdout or stderr open.
+        let child = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let Ok(mut child) = child else {
+            continue;
+        };
+
+        let mut bytes = encode(program, text);
+        let written = child.stdin.take().unwrap().write_all(&bytes);
+        bytes.zeroize();
+        written?;
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+
+    let tried: Vec<&str> = commands.iter().map(|(program, _)| *program).collect();
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no usable clipboard tool, tried {}", tried.join(", ")),
+    ))
+}
+
+/// Read the current text on the system clipboard.
+pub fn paste() -> io::Result<String> {
+    for (program, args) in paste_commands() {
+        if let Ok(output) = Command::new(program)
+            .args(args)
+            .stderr(Stdio::null())
+            .output()
+            && output.status.success()
+        {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no usable clipboard tool",
+    ))
+}
+
+/// Clear the clipboard after `seconds` in a detached copy of the current executable,
+/// provided it still holds `text` by then.
+///
+/// `text` is handed over on stdin so it never appears in the process list.
+pub fn clear_after(text: &str, seconds: u64) -> io::Result<()> {
+    let mut child = Command::new(std::env::current_exe()?)
+        .arg(CLEAR_FLAG)
+        .arg(seconds.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    child.stdin.take().unwrap().write_all(text.as_bytes())
+}
+
+/// The detached side of [`clear_after`]: read the copied text from stdin, wait, and clear
+/// the clipboard unless something else has been copied in the meantime.
+pub fn run_clear(seconds: u64) -> io::Result<()> {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+
+    thread::sleep(Duration::from_secs(seconds));
+
+    // Some tools add a trailing newline when pasting.
+    match paste() {
+        Ok(current) if current.trim_end_matches(['\r', '\n']) != text => Ok(()),
+        _ => copy(""),
+    }
+}
@@ -0,0 +1,58 @@
+//! Shell completion scripts generated from the clap definition by `clap_complete`.
+
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use clap::builder::PossibleValuesParser;
+
+/// The shells scripts can be generated for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+    Elvish,
+}
+
+impl Shell {
+    fn generator(self) -> clap_complete::Shell {
+        match self {
+            Shell::Bash => clap_complete::Shell::Bash,
+            Shell::Zsh => clap_complete::Shell::Zsh,
+            Shell::Fish => clap_complete::Shell::Fish,
+            Shell::Powershell => clap_complete::Shell::PowerShell,
+            Shell::Elvish => clap_complete::Shell::Elvish,
+        }
+    }
+}
+
+/// Write the completion script for `shell` to `out`.
+///
+/// `extra_values` lists candidates for options whose values are only known at run time, by
+/// option id, e.g. the preset names for `preset`.
+pub fn generate(
+    shell: Shell,
+    command: &clap::Command,
+    bin_name: &str,
+    extra_values: &[(&str, Vec<String>)],
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let mut command = command.clone();
+    for (id, extra) in extra_values {
+        command = command.mut_arg(*id, |arg| {
+            let values: Vec<String> = arg
+                .get_possible_values()
+                .iter()
+                .map(|value| value.get_name().to_string())
+                .chain(extra.iter().cloned())
+                .collect();
+            arg.value_parser(PossibleValuesParser::new(values))
+        });
+    }
+
+    // clap_complete panics on write errors, so the script is written out in one piece.
+    let mut script = Vec::new();
+    clap_complete::generate(shell.generator(), &mut command, bin_name, &mut script);
+    out.write_all(&script)
+}
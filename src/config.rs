@@ -0,0 +1,190 @@
+//! Reading the defaults file, which is TOML parsed by the `toml` crate.
+//!
+//! Top-level keys set options, and a `[presets]` table defines presets. Values are strings,
+//! integers, floats, booleans, and arrays of those.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+/// A value in the config file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn from_toml(value: toml::Value) -> Result<Self, String> {
+        match value {
+            toml::Value::String(s) => Ok(Value::String(s)),
+            toml::Value::Integer(n) => Ok(Value::Integer(n)),
+            toml::Value::Float(x) => Ok(Value::Float(x)),
+            toml::Value::Boolean(b) => Ok(Value::Boolean(b)),
+            toml::Value::Array(values) => values
+                .into_iter()
+                .map(Value::from_toml)
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            toml::Value::Datetime(datetime) => Err(format!(
+                "'{}' is a date. Quote it to use it as a string.",
+                datetime
+            )),
+            toml::Value::Table(_) => {
+                Err("Expected a string, number, boolean, or array, not a table.".to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Array(values) => {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+/// A key and its value, with the line it was defined on.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// The full key, including any table name, e.g. `presets.vendor`.
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+}
+
+// The keys of a file. Only the presets are a table.
+type Table = BTreeMap<String, Spanned<toml::Value>>;
+
+#[derive(Deserialize)]
+struct Presets {
+    #[serde(default)]
+    presets: Table,
+}
+
+/// The default location: `$XDG_CONFIG_HOME/pwgen/config.toml`, falling back to
+/// `~/.config/pwgen/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("pwgen").join("config.toml"))
+}
+
+/// Parse the entries of a config file in file order. Errors start with the offending line
+/// number, as in `3: message`.
+pub fn parse(content: &str) -> Result<Vec<Entry>, String> {
+    let line = |offset: usize| content[..offset].matches('\n').count() + 1;
+    let error = |err: toml::de::Error| match err.span() {
+        Some(span) => format!("{}: {}", line(span.start), err.message().trim_end()),
+        None => err.message().trim_end().to_string(),
+    };
+    let options: Table = toml::from_str(content).map_err(error)?;
+    let presets: Presets = toml::from_str(content).map_err(error)?;
+
+    let mut entries = Vec::new();
+    let keys = options
+        .into_iter()
+        .filter(|(key, _)| key != "presets")
+        .chain(
+            presets
+                .presets
+                .into_iter()
+                .map(|(name, value)| (format!("presets.{}", name), value)),
+        );
+    for (key, value) in keys {
+        let line = line(value.span().start);
+        let value =
+            Value::from_toml(value.into_inner()).map_err(|err| format!("{}: {}", line, err))?;
+        entries.push(Entry { key, value, line });
+    }
+    entries.sort_by_key(|entry| entry.line);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_options_and_presets_in_file_order() {
+        let entries = parse(
+            "length = 20\n\
+             use_symbols = true\n\
+             exclude = \"\\\"'\"\n\
+             weight = ['digits=2', \"symbols=0.5\"]\n\
+             \n\
+             [presets]\n\
+             \"vowels only\" = 'aeiou'\n",
+        )
+        .unwrap();
+        let found: Vec<(&str, &Value, usize)> = entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), &entry.value, entry.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("length", &Value::Integer(20), 1),
+                ("use_symbols", &Value::Boolean(true), 2),
+                ("exclude", &Value::String("\"'".to_string()), 3),
+                (
+                    "weight",
+                    &Value::Array(vec![
+                        Value::String("digits=2".to_string()),
+                        Value::String("symbols=0.5".to_string())
+                    ]),
+                    4
+                ),
+                (
+                    "presets.vowels only",
+                    &Value::String("aeiou".to_string()),
+                    7
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_accepts_multi_line_strings_and_inline_tables() {
+        let entries = parse("chars = \"\"\"\nabc\"\"\"\npresets = { hex = 'abcdef' }\n").unwrap();
+        assert_eq!(entries[0].value, Value::String("abc".to_string()));
+        assert_eq!(entries[1].key, "presets.hex");
+    }
+
+    #[test]
+    fn parse_reports_the_line_of_errors() {
+        assert!(
+            parse("length = 20\nlength = 21\n")
+                .unwrap_err()
+                .starts_with("2: ")
+        );
+        assert!(
+            parse("length = 20\nchars = \"abc\n")
+                .unwrap_err()
+                .starts_with("2: ")
+        );
+        assert!(
+            parse("\n\nwhen = 2024-01-01\n")
+                .unwrap_err()
+                .starts_with("3: ")
+        );
+        assert!(parse("[generate]\nlength = 8\n").is_err());
+    }
+}
@@ -1,127 +1,3584 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, RandomState};
 use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
+use rand::rngs::{OsRng, StdRng};
 use rand::seq::SliceRandom;
-use rand::{Rng, thread_rng};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use zeroize::Zeroize;
 
-const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
-const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const DIGITS: &str = "0123456789";
-const SYMBOLS: &str = "!@#$%^&*()_-+=[]{}|;:,.<>?";
+use crate::output::{self, OutputOptions};
+#[cfg(feature = "common-words")]
+use crate::words;
+use crate::{pwned, sequence, strength, validation};
+
+pub const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+pub const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+pub const DIGITS: &str = "0123456789";
+pub const SYMBOLS: &str = "!@#$%^&*()_-+=[]{}|;:,.<>?";
+
+pub const HEX_LOWER: &str = "0123456789abcdef";
+pub const HEX_UPPER: &str = "0123456789ABCDEF";
+pub const BASE62: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+/// The Bitcoin base58 alphabet: base62 without `0`, `O`, `I`, and `l`.
+pub const BASE58: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// 64 animals, foods, and objects that are easy to tell apart. Each is a single scalar value
+/// shown as emoji by default, so none needs a variation selector, joiner, or skin tone.
+pub const EMOJI: &str = "🐌🐍🐎🐘🐙🐚🐛🐜🐝🐞🐟🐢🐧🐨🐬🐳🐸🐼🐷🐯🐰🐻🐔🍄🍅🍆🍇🍉🍋🍌🍍🍎🍒🍓🍔🍕🍩🍪🍰🍿🌽🌵🌻🍀🍁🎈🎁🎸🎲🎯🏀🎩🎃🔑🔔💎💡📚🚀🚲🚗🌈🌙🔥";
+
+/// Letters used to build pronounceable syllables.
+const CONSONANTS: &str = "bcdfghjklmnprstvwz";
+const VOWELS: &str = "aeiou";
+
+/// The built-in character categories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    Symbol,
+}
+
+impl CharClass {
+    /// Every category, in the order they are listed.
+    pub const ALL: [CharClass; 4] = [
+        CharClass::Lower,
+        CharClass::Upper,
+        CharClass::Digit,
+        CharClass::Symbol,
+    ];
+
+    /// The name used in messages and output, e.g. `lowercase`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CharClass::Lower => "lowercase",
+            CharClass::Upper => "uppercase",
+            CharClass::Digit => "digits",
+            CharClass::Symbol => "symbols",
+        }
+    }
+
+    /// The characters of the category.
+    pub fn chars(self) -> &'static str {
+        match self {
+            CharClass::Lower => LOWERCASE,
+            CharClass::Upper => UPPERCASE,
+            CharClass::Digit => DIGITS,
+            CharClass::Symbol => SYMBOLS,
+        }
+    }
+
+    /// The category `c` belongs to, if any.
+    pub fn of(c: char) -> Option<CharClass> {
+        Self::ALL
+            .into_iter()
+            .find(|class| class.chars().contains(c))
+    }
+}
+
+/// The entropy in bits of `length` characters chosen uniformly from `charset_size` characters.
+pub fn entropy_bits(charset_size: usize, length: usize) -> f64 {
+    if charset_size == 0 {
+        return 0.0;
+    }
+    length as f64 * (charset_size as f64).log2()
+}
+
+/// The shortest length at which characters chosen uniformly from `charset_size` characters
+/// reach `bits` of entropy: `ceil(bits / log2(charset_size))`. `None` if no length can.
+pub fn suggested_length(bits: f64, charset_size: usize) -> Option<usize> {
+    if charset_size < 2 {
+        return None;
+    }
+    length_for_char_bits(bits, (charset_size as f64).log2())
+}
+
+// The shortest length at which characters of `char_bits` each reach `bits`.
+fn length_for_char_bits(bits: f64, char_bits: f64) -> Option<usize> {
+    (char_bits > 0.0).then(|| (bits / char_bits).ceil() as usize)
+}
+
+// The weight of each set from the factors of their categories, or nothing when no factor
+// is given.
+fn set_weights(sets: &[CharSet], weights: &[(CharClass, f64)]) -> Vec<f64> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+    sets.iter()
+        .map(|set| {
+            weights
+                .iter()
+                .find(|(class, _)| class.name() == set.name)
+                .map_or(1.0, |(_, factor)| *factor)
+        })
+        .collect()
+}
+
+/// log2 of the binomial coefficient `n` choose `k`.
+pub(crate) fn log2_binomial(n: usize, k: usize) -> f64 {
+    (0..k.min(n - k))
+        .map(|i| ((n - i) as f64 / (i + 1) as f64).log2())
+        .sum()
+}
+
+// The index of the first set holding `c`, which the weighted fill counts it under.
+fn set_of(sets: &[CharSet], c: char) -> usize {
+    sets.iter()
+        .position(|set| set.chars.contains(&c))
+        .unwrap_or(0)
+}
+
+// The Shannon entropy of one character of `charset`: `log2` of its size, or with weights
+// the bits of picking a set plus those of picking a character within it.
+fn char_bits(sets: &[CharSet], weights: &[f64], charset: &[char]) -> f64 {
+    if weights.is_empty() {
+        return if charset.is_empty() {
+            0.0
+        } else {
+            (charset.len() as f64).log2()
+        };
+    }
+    let mut sizes = vec![0usize; sets.len()];
+    for &c in charset {
+        sizes[set_of(sets, c)] += 1;
+    }
+    let total: f64 = sizes
+        .iter()
+        .zip(weights)
+        .filter(|(size, _)| **size > 0)
+        .map(|(_, weight)| weight)
+        .sum();
+    sizes
+        .iter()
+        .zip(weights)
+        .filter(|(size, _)| **size > 0)
+        .map(|(size, weight)| {
+            let p = weight / total;
+            p * ((*size as f64).log2() - p.log2())
+        })
+        .sum()
+}
+
+/// How many candidates are tried for each password before a constraint is reported as
+/// unsatisfiable, unless the configuration sets its own limit.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 1000;
+
+// The number of passwords a job generates at a time when a batch runs on several threads.
+const PARALLEL_CHUNK: usize = 4096;
+
+// The scratch space of random passwords, kept per thread so that a batch reuses it rather
+// than allocating for every password. It is wiped after each one.
+#[derive(Default)]
+struct Buffers {
+    chars: Vec<char>,
+    pool: Vec<char>,
+    counts: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+thread_local! {
+    static BUFFERS: RefCell<Buffers> = RefCell::new(Buffers::default());
+}
+
+// The name of the set that `--emoji` mixes in.
+const EMOJI_SET: &str = "emoji";
+
+/// Characters that are easily confused with one another when printed or read aloud.
+pub const AMBIGUOUS: &str = "0Oo1lI|5S2Z";
+
+/// Errors that can occur while configuring or running password generation.
+#[derive(Debug)]
+pub enum Error {
+    /// The length cannot fit the characters required from the selected sets.
+    LengthTooShort { length: usize, required: usize },
+    /// No character set was selected.
+    EmptyCharset,
+    /// A selected set lost all of its characters to exclusions or filtering.
+    EmptySet { name: String },
+    /// The options are inconsistent with each other.
+    InvalidConfig(String),
+    /// No candidate satisfied a constraint within the allowed number of attempts.
+    AttemptsExhausted { attempts: usize, constraint: String },
+    /// A password could not be checked against Have I Been Pwned.
+    BreachCheck(io::Error),
+    /// The options cannot reach the required entropy. `suggestion` says how to fix that.
+    InsufficientEntropy {
+        bits: f64,
+        required: f64,
+        suggestion: String,
+    },
+    /// Writing the output failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::LengthTooShort { length, required } => write!(
+                f,
+                "Password length ({}) is too short to include the required characters from each selected set ({}).",
+                length, required
+            ),
+            Error::EmptyCharset => write!(
+                f,
+                "No character sets selected. Please use --all or select at least one category."
+            ),
+            Error::EmptySet { name } => write!(
+                f,
+                "The selected {} set has no characters left after exclusions and filtering.",
+                name
+            ),
+            Error::InvalidConfig(message) => write!(f, "{}", message),
+            Error::AttemptsExhausted {
+                attempts,
+                constraint,
+            } => write!(
+                f,
+                "Could not generate a password satisfying {} after {} attempts. Try relaxing the constraint or increasing the length.",
+                constraint, attempts
+            ),
+            Error::BreachCheck(err) => write!(
+                f,
+                "Could not check the password against Have I Been Pwned: {}. Use the best-effort mode to continue without the check.",
+                err
+            ),
+            Error::InsufficientEntropy {
+                bits,
+                required,
+                suggestion,
+            } => write!(
+                f,
+                "The options give {:.1} bits of entropy, but at least {} are required. {}",
+                bits, required, suggestion
+            ),
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::BreachCheck(err) | Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// A cryptographically secure random number generator usable as a trait object.
+pub trait SecureRng: RngCore + CryptoRng {}
+
+impl<T: RngCore + CryptoRng> SecureRng for T {}
+
+/// A single position of a password pattern.
+#[derive(Clone, Debug)]
+pub enum PatternClass {
+    Lower,
+    Upper,
+    Digit,
+    Symbol,
+    Alnum,
+    Any,
+    Literal(char),
+}
+
+/// A password structure, one class per generated character.
+#[derive(Clone, Debug)]
+pub struct Pattern(pub Vec<PatternClass>);
+
+/// The substitutions of [`LeetMap::default`], in the syntax of `--leet`.
+pub const LEET_MAP: &str = "a=4@,e=3,i=1,o=0,s=5$,t=7";
+
+/// Letters and the look-alike characters that may replace them, e.g. `a` with `4` or `@`.
+/// Letters match in either case.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeetMap(pub Vec<(char, Vec<char>)>);
+
+impl Default for LeetMap {
+    fn default() -> Self {
+        Self(
+            [
+                ('a', "4@"),
+                ('e', "3"),
+                ('i', "1"),
+                ('o', "0"),
+                ('s', "5$"),
+                ('t', "7"),
+            ]
+            .into_iter()
+            .map(|(letter, substitutes)| (letter, substitutes.chars().collect()))
+            .collect(),
+        )
+    }
+}
+
+/// Substitutions applied to random passwords after their characters are drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Leet {
+    pub map: LeetMap,
+    /// The chance, from 0 to 1, that an eligible letter is replaced.
+    pub probability: f64,
+}
+
+impl Default for Leet {
+    fn default() -> Self {
+        Self {
+            map: LeetMap::default(),
+            probability: 0.5,
+        }
+    }
+}
+
+/// Password lengths from `min` to `max`, inclusive. A single length has `min == max`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthRange {
+    pub min: usize,
+    pub max: usize,
+}
+
+/// A complete character set that replaces the category flags.
+///
+/// No category guarantees apply: every character is drawn uniformly from `chars`.
+#[derive(Clone, Debug)]
+pub struct Charset {
+    pub name: String,
+    pub chars: String,
+}
+
+impl Charset {
+    /// Hexadecimal digits, in lowercase unless `uppercase` is set.
+    pub fn hex(uppercase: bool) -> Self {
+        Self {
+            name: "hex".to_string(),
+            chars: if uppercase { HEX_UPPER } else { HEX_LOWER }.to_string(),
+        }
+    }
+
+    /// Letters and digits without the look-alikes `0`, `O`, `I`, and `l`.
+    pub fn base58() -> Self {
+        Self {
+            name: "base58".to_string(),
+            chars: BASE58.to_string(),
+        }
+    }
+
+    /// Letters and digits.
+    pub fn base62() -> Self {
+        Self {
+            name: "base62".to_string(),
+            chars: BASE62.to_string(),
+        }
+    }
+
+    /// The emoji of [`EMOJI`].
+    pub fn emoji() -> Self {
+        Self {
+            name: "emoji".to_string(),
+            chars: EMOJI.to_string(),
+        }
+    }
+}
 
 /// Configure information for password generate
-pub struct GeneratorConfig<'a> {
+///
+/// Prefer [`GeneratorConfig::builder`], which validates the configuration and keeps
+/// working as new options are added. Constructing the struct directly is still supported.
+#[derive(Clone, Debug, Default)]
+pub struct GeneratorConfig {
     pub length: usize,
+    /// Draw each password's length from `length..=length_max` for random and pronounceable
+    /// passwords.
+    pub length_max: Option<usize>,
     pub count: usize,
     pub use_lower: bool,
     pub use_upper: bool,
     pub use_digits: bool,
     pub use_symbols: bool,
     pub custom_chars: Option<String>,
-    pub output: Box<dyn Write + 'a>,
+    /// Characters added to the pool without being required, unlike `custom_chars`.
+    pub optional_chars: Option<String>,
+    /// Characters that replace [`SYMBOLS`] as the symbols category.
+    pub symbols_set: Option<String>,
+    /// Mix exactly this many characters of [`EMOJI`] into random passwords of the categories.
+    /// They count toward `length` like any other character.
+    pub emoji: usize,
+    pub exclude_chars: Option<String>,
+    pub avoid_ambiguous: bool,
+    pub min_lower: usize,
+    pub min_upper: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+    pub max_lower: Option<usize>,
+    pub max_upper: Option<usize>,
+    pub max_digits: Option<usize>,
+    pub max_symbols: Option<usize>,
+    pub pattern: Option<Pattern>,
+    pub pronounceable: bool,
+    /// Generate Safari-style passwords like `hyzvox-2Qojny-mandiq`: three six-letter chunks
+    /// of consonants and vowels with one digit at a chunk edge and one uppercase letter.
+    /// `length`, the categories, and the per-category counts do not apply.
+    pub memorable: bool,
+    pub charset: Option<Charset>,
+    pub uuid: bool,
+    /// Regenerate passwords whose zxcvbn score is below this (0-4).
+    pub min_score: Option<u8>,
+    /// The number of candidates tried per password, [`DEFAULT_MAX_ATTEMPTS`] if unset.
+    pub max_attempts: Option<usize>,
+    /// Regenerate passwords found in Have I Been Pwned. Requires the `pwned` feature.
+    pub check_pwned: Option<pwned::Mode>,
+    /// The range API that `check_pwned` queries, [`pwned::DEFAULT_API_URL`] if unset.
+    pub pwned_api_url: Option<String>,
+    /// Regenerate passwords containing any of these terms, ignoring case.
+    pub blacklist: Vec<String>,
+    /// Reject the configuration if its entropy is below this many bits.
+    pub min_entropy: Option<f64>,
+    /// Derive the length of random passwords from this many bits of entropy, overriding
+    /// `length`.
+    pub bits: Option<f64>,
+    /// The longest length `bits` may choose, [`validation::MAX_LENGTH`] if unset. A target
+    /// that needs a longer one is rejected.
+    pub length_limit: Option<usize>,
+    /// Raise the length of random passwords to what the required characters and
+    /// `min_entropy` need, instead of rejecting a length that is too short.
+    pub auto_length: bool,
+    /// Draw every character of random passwords from the whole charset and regenerate those
+    /// that miss a category count or position rule. Each valid password is then equally
+    /// likely, while the default places the required characters first, which favors counts
+    /// just above the minimums.
+    pub uniform: bool,
+    /// Never repeat a password within one iterator or batch.
+    pub unique: bool,
+    /// Regenerate passwords containing a common English word of at least this many letters.
+    /// Requires the `common-words` feature.
+    pub no_words: Option<usize>,
+    /// Use every character at most once in a random password.
+    pub no_repeat: bool,
+    /// Regenerate passwords with two identical characters next to each other.
+    pub no_consecutive_identical: bool,
+    /// Regenerate passwords containing an alphabetic, numeric, or keyboard-row run of at
+    /// least this many characters, e.g. `abc`, `321`, or `qwe`.
+    pub no_sequential: Option<usize>,
+    /// Begin random passwords with a letter from the enabled lowercase or uppercase set.
+    pub start_with_letter: bool,
+    /// Keep symbols, including custom ones, out of the first and last positions of random
+    /// passwords.
+    pub no_edge_symbols: bool,
+    /// Regenerate passwords containing a keyboard walk of at least this many keys, as
+    /// measured by [`longest_keyboard_walk`].
+    pub no_keyboard_walks: Option<usize>,
+    /// The number of threads that generate a batch. 0 and 1 generate on the calling thread.
+    pub jobs: usize,
+    /// Replace letters of random passwords with look-alikes. Substitutes outside the charset
+    /// are skipped, and candidates that no longer meet the category counts are regenerated.
+    pub leet: Option<Leet>,
+    /// Bias the characters that fill random passwords after the required ones: a set is
+    /// picked in proportion to its category's factor, 1 unless listed, and then a character
+    /// uniformly within it. Factors must be positive.
+    pub weights: Vec<(CharClass, f64)>,
+}
+
+impl GeneratorConfig {
+    /// Start building a configuration with the command-line defaults
+    /// (length 8, one password, no categories selected).
+    pub fn builder() -> GeneratorConfigBuilder {
+        GeneratorConfigBuilder {
+            config: GeneratorConfig {
+                length: 8,
+                count: 1,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// A builder for [`GeneratorConfig`], created by [`GeneratorConfig::builder`].
+#[derive(Clone)]
+pub struct GeneratorConfigBuilder {
+    config: GeneratorConfig,
+}
+
+impl GeneratorConfigBuilder {
+    /// The total length of each password.
+    pub fn length(mut self, length: usize) -> Self {
+        self.config.length = length;
+        self.config.length_max = None;
+        self
+    }
+
+    /// Give each password a random length from `min` to `max`, inclusive.
+    pub fn length_range(mut self, min: usize, max: usize) -> Self {
+        self.config.length = min;
+        self.config.length_max = Some(max);
+        self
+    }
+
+    /// The number of passwords to generate.
+    pub fn count(mut self, count: usize) -> Self {
+        self.config.count = count;
+        self
+    }
+
+    /// Include lowercase letters, uppercase letters, digits, and symbols.
+    pub fn use_all(mut self) -> Self {
+        self.config.use_lower = true;
+        self.config.use_upper = true;
+        self.config.use_digits = true;
+        self.config.use_symbols = true;
+        self
+    }
+
+    /// Include lowercase letters.
+    pub fn use_lower(mut self, enabled: bool) -> Self {
+        self.config.use_lower = enabled;
+        self
+    }
+
+    /// Include uppercase letters.
+    pub fn use_upper(mut self, enabled: bool) -> Self {
+        self.config.use_upper = enabled;
+        self
+    }
+
+    /// Include digits.
+    pub fn use_digits(mut self, enabled: bool) -> Self {
+        self.config.use_digits = enabled;
+        self
+    }
+
+    /// Include symbols.
+    pub fn use_symbols(mut self, enabled: bool) -> Self {
+        self.config.use_symbols = enabled;
+        self
+    }
+
+    /// An additional set of characters, at least one of which appears in every password.
+    ///
+    /// Repeated calls add to the set, and the guarantee applies to the combined set.
+    pub fn custom_chars(mut self, chars: impl Into<String>) -> Self {
+        self.config
+            .custom_chars
+            .get_or_insert_with(String::new)
+            .push_str(&chars.into());
+        self
+    }
+
+    /// Additional characters that may appear, with no guarantee that any of them does.
+    pub fn optional_chars(mut self, chars: impl Into<String>) -> Self {
+        self.config
+            .optional_chars
+            .get_or_insert_with(String::new)
+            .push_str(&chars.into());
+        self
+    }
+
+    /// Replace the built-in symbols with `chars`. Symbol minimums and maximums apply to them.
+    pub fn symbols_set(mut self, chars: impl Into<String>) -> Self {
+        self.config.symbols_set = Some(chars.into());
+        self
+    }
+
+    /// Characters removed from every selected set.
+    pub fn exclude_chars(mut self, chars: impl Into<String>) -> Self {
+        self.config.exclude_chars = Some(chars.into());
+        self
+    }
+
+    /// Remove visually ambiguous characters from the built-in sets.
+    pub fn avoid_ambiguous(mut self, enabled: bool) -> Self {
+        self.config.avoid_ambiguous = enabled;
+        self
+    }
+
+    /// The minimum number of lowercase letters.
+    pub fn min_lower(mut self, n: usize) -> Self {
+        self.config.min_lower = n;
+        self
+    }
+
+    /// The minimum number of uppercase letters.
+    pub fn min_upper(mut self, n: usize) -> Self {
+        self.config.min_upper = n;
+        self
+    }
+
+    /// The minimum number of digits.
+    pub fn min_digits(mut self, n: usize) -> Self {
+        self.config.min_digits = n;
+        self
+    }
+
+    /// The minimum number of symbols.
+    pub fn min_symbols(mut self, n: usize) -> Self {
+        self.config.min_symbols = n;
+        self
+    }
+
+    /// The maximum number of lowercase letters.
+    pub fn max_lower(mut self, n: usize) -> Self {
+        self.config.max_lower = Some(n);
+        self
+    }
+
+    /// The maximum number of uppercase letters.
+    pub fn max_upper(mut self, n: usize) -> Self {
+        self.config.max_upper = Some(n);
+        self
+    }
+
+    /// The maximum number of digits.
+    pub fn max_digits(mut self, n: usize) -> Self {
+        self.config.max_digits = Some(n);
+        self
+    }
+
+    /// The maximum number of symbols.
+    pub fn max_symbols(mut self, n: usize) -> Self {
+        self.config.max_symbols = Some(n);
+        self
+    }
+
+    /// Generate passwords matching a pattern instead of a length and categories.
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.config.pattern = Some(pattern);
+        self
+    }
+
+    /// Generate pronounceable passwords.
+    pub fn pronounceable(mut self, enabled: bool) -> Self {
+        self.config.pronounceable = enabled;
+        self
+    }
+
+    /// Generate Safari-style memorable passwords such as `hyzvox-2Qojny-mandiq`.
+    pub fn memorable(mut self, enabled: bool) -> Self {
+        self.config.memorable = enabled;
+        self
+    }
+
+    /// Draw every character from a complete charset instead of the categories.
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.config.charset = Some(charset);
+        self
+    }
+
+    /// Generate version 4 UUIDs.
+    pub fn uuid(mut self, enabled: bool) -> Self {
+        self.config.uuid = enabled;
+        self
+    }
+
+    /// Regenerate passwords whose zxcvbn score is below `score` (0-4).
+    pub fn min_score(mut self, score: u8) -> Self {
+        self.config.min_score = Some(score);
+        self
+    }
+
+    /// The number of candidates tried per password before giving up.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.config.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Regenerate passwords found in Have I Been Pwned. Requires the `pwned` feature.
+    pub fn check_pwned(mut self, mode: pwned::Mode) -> Self {
+        self.config.check_pwned = Some(mode);
+        self
+    }
+
+    /// Query another server implementing the range API for `check_pwned`, e.g. a local mirror.
+    pub fn pwned_api_url(mut self, api_url: &str) -> Self {
+        self.config.pwned_api_url = Some(api_url.to_string());
+        self
+    }
+
+    /// Regenerate passwords containing any of `terms`, ignoring case.
+    pub fn blacklist(mut self, terms: Vec<String>) -> Self {
+        self.config.blacklist = terms;
+        self
+    }
+
+    /// Reject the configuration if its entropy is below `bits`.
+    pub fn min_entropy(mut self, bits: f64) -> Self {
+        self.config.min_entropy = Some(bits);
+        self
+    }
+
+    /// Use the shortest length that gives at least `bits` of entropy instead of `length`.
+    pub fn bits(mut self, bits: f64) -> Self {
+        self.config.bits = Some(bits);
+        self
+    }
+
+    /// Reject a `bits` target that needs a length above `limit`.
+    pub fn length_limit(mut self, limit: usize) -> Self {
+        self.config.length_limit = Some(limit);
+        self
+    }
+
+    /// Raise a length that is too short for the required characters or `min_entropy`
+    /// instead of rejecting it.
+    pub fn auto_length(mut self, enabled: bool) -> Self {
+        self.config.auto_length = enabled;
+        self
+    }
+
+    /// Sample random passwords uniformly from the valid ones, by regenerating invalid
+    /// candidates.
+    pub fn uniform(mut self, enabled: bool) -> Self {
+        self.config.uniform = enabled;
+        self
+    }
+
+    /// Never repeat a password within one batch.
+    pub fn unique(mut self, enabled: bool) -> Self {
+        self.config.unique = enabled;
+        self
+    }
+
+    /// Regenerate passwords containing a common English word of at least `min_length`
+    /// letters. Requires the `common-words` feature.
+    pub fn no_words(mut self, min_length: usize) -> Self {
+        self.config.no_words = Some(min_length);
+        self
+    }
+
+    /// Use every character at most once in a random password.
+    pub fn no_repeat(mut self, enabled: bool) -> Self {
+        self.config.no_repeat = enabled;
+        self
+    }
+
+    /// Regenerate passwords with two identical characters next to each other.
+    pub fn no_consecutive_identical(mut self, enabled: bool) -> Self {
+        self.config.no_consecutive_identical = enabled;
+        self
+    }
+
+    /// Regenerate passwords containing a run of at least `min_length` characters such as
+    /// `abc`, `321`, or `qwe`.
+    pub fn no_sequential(mut self, min_length: usize) -> Self {
+        self.config.no_sequential = Some(min_length);
+        self
+    }
+
+    /// Begin random passwords with a letter from the enabled lowercase or uppercase set.
+    pub fn start_with_letter(mut self, enabled: bool) -> Self {
+        self.config.start_with_letter = enabled;
+        self
+    }
+
+    /// Keep symbols out of the first and last positions of random passwords.
+    pub fn no_edge_symbols(mut self, enabled: bool) -> Self {
+        self.config.no_edge_symbols = enabled;
+        self
+    }
+
+    /// Regenerate passwords containing a keyboard walk of at least `min_length` keys.
+    pub fn no_keyboard_walks(mut self, min_length: usize) -> Self {
+        self.config.no_keyboard_walks = Some(min_length);
+        self
+    }
+
+    /// Generate batches on `jobs` threads, each with its own random number generator.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.config.jobs = jobs;
+        self
+    }
+
+    /// Replace letters of random passwords with look-alike digits and symbols.
+    pub fn leet(mut self, leet: Leet) -> Self {
+        self.config.leet = Some(leet);
+        self
+    }
+
+    /// Mix `count` emoji of [`EMOJI`] into each password.
+    pub fn emoji(mut self, count: usize) -> Self {
+        self.config.emoji = count;
+        self
+    }
+
+    /// Make the characters of `class` `factor` times as likely to fill a random password as
+    /// those of a category without a weight.
+    pub fn weight(mut self, class: CharClass, factor: f64) -> Self {
+        self.config.weights.push((class, factor));
+        self
+    }
+
+    /// Validate the configuration, reporting invalid combinations before any generation happens.
+    pub fn build(self) -> Result<GeneratorConfig, Error> {
+        PasswordGenerator::new(&self.config)?;
+        Ok(self.config)
+    }
+}
+
+/// A named set of characters, at least `min` and at most `max` of which must appear in every password.
+#[derive(Clone, Debug)]
+pub struct CharSet {
+    name: String,
+    chars: Vec<char>,
+    min: usize,
+    max: Option<usize>,
+}
+
+impl CharSet {
+    /// The category name, e.g. `lowercase` or `custom`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The characters left after exclusions and filtering.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// How many of the characters every password contains at least.
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    /// How many of the characters every password contains at most, if limited.
+    pub fn max(&self) -> Option<usize> {
+        self.max
+    }
+}
+
+/// Letters and extras used by pronounceable password generation.
+struct Syllables {
+    consonants: Vec<char>,
+    vowels: Vec<char>,
+    digits: Option<Vec<char>>,
+    uppercase: Option<Vec<char>>,
+}
+
+/// The consonant (`c`) and vowel (`v`) positions of one chunk of a memorable password.
+const MEMORABLE_CHUNK: &str = "cvccvc";
+
+/// The number of chunks in a memorable password.
+const MEMORABLE_CHUNKS: usize = 3;
+
+/// The characters of memorable passwords.
+struct Chunks {
+    consonants: Vec<char>,
+    vowels: Vec<char>,
+    // The consonants and vowels whose uppercase form survived filtering.
+    upper_consonants: Vec<char>,
+    upper_vowels: Vec<char>,
+    digits: Vec<char>,
+}
+
+impl Chunks {
+    // The letters a position may hold, capitalized or not.
+    fn letters(&self, position: usize, capital: bool) -> &[char] {
+        let vowel = MEMORABLE_CHUNK.as_bytes()[position % MEMORABLE_CHUNK.len()] == b'v';
+        match (vowel, capital) {
+            (false, false) => &self.consonants,
+            (true, false) => &self.vowels,
+            (false, true) => &self.upper_consonants,
+            (true, true) => &self.upper_vowels,
+        }
+    }
+
+    // The positions the digit may take: the first and last of each chunk.
+    fn digit_positions() -> Vec<usize> {
+        (0..MEMORABLE_CHUNKS)
+            .flat_map(|chunk| {
+                let start = chunk * MEMORABLE_CHUNK.len();
+                [start, start + MEMORABLE_CHUNK.len() - 1]
+            })
+            .collect()
+    }
+}
+
+/// The QWERTY keyboard rows, unshifted and shifted. Each row starts half a key to the right
+/// of the one above it.
+const KEYBOARD_ROWS: [(&str, &str); 4] = [
+    ("1234567890-=", "!@#$%^&*()_+"),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    ("asdfghjkl;'", "ASDFGHJKL:\""),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+
+/// The default shortest walk rejected by [`GeneratorConfig::no_keyboard_walks`].
+pub const DEFAULT_KEYBOARD_WALK: usize = 4;
+
+// The row and column of the key that types `c`.
+fn key_position(c: char) -> Option<(usize, usize)> {
+    KEYBOARD_ROWS
+        .iter()
+        .enumerate()
+        .find_map(|(row, (plain, shifted))| {
+            plain
+                .chars()
+                .position(|k| k == c)
+                .or_else(|| shifted.chars().position(|k| k == c))
+                .map(|column| (row, column))
+        })
+}
+
+// Whether two keys touch, along a row, a column, or a diagonal.
+fn keys_adjacent((row_a, col_a): (usize, usize), (row_b, col_b): (usize, usize)) -> bool {
+    if row_a == row_b {
+        col_a.abs_diff(col_b) == 1
+    } else if row_b + 1 == row_a {
+        col_b == col_a || col_b == col_a + 1
+    } else if row_a + 1 == row_b {
+        col_a == col_b || col_a == col_b + 1
+    } else {
+        false
+    }
 }
 
-struct PasswordGenerator<'a> {
-    final_charset: Vec<char>,
-    required_sets: Vec<Vec<char>>,
-    length: usize,
-    count: usize,
-    output: Box<dyn Write + 'a>,
-}
+/// The length of the longest run of characters in `s` typed on touching QWERTY keys, such as
+/// `qwer`, `1qaz`, or `!@#$`, in any direction and ignoring case and shift.
+pub fn longest_keyboard_walk(s: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous = None;
+
+    for c in s.chars() {
+        let position = key_position(c);
+        current = match (previous, position) {
+            (Some(a), Some(b)) if keys_adjacent(a, b) => current + 1,
+            (_, Some(_)) => 1,
+            (_, None) => 0,
+        };
+        longest = longest.max(current);
+        previous = position;
+    }
+    longest
+}
+
+/// Read a blacklist of terms that passwords must not contain, one per line.
+///
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_blacklist(path: &str) -> Result<Vec<String>, Error> {
+    let content = fs::read_to_string(path).map_err(|err| {
+        Error::InvalidConfig(format!("Failed to read blacklist '{}': {}", path, err))
+    })?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|term| !term.is_empty() && !term.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Read a custom character set from a file, or from stdin if `path` is `-`.
+///
+/// Line breaks are removed and each line is read with the syntax of `--chars`. The result has
+/// no repeated characters.
+pub fn read_chars_file(path: &str) -> Result<String, Error> {
+    let content = if path == "-" {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(path)
+    }
+    .map_err(|err| {
+        Error::InvalidConfig(format!("Failed to read character file '{}': {}", path, err))
+    })?;
+    // Errors are located compiler-style, as PATH:LINE or PATH:LINE:COLUMN.
+    let invalid = |location: String, message: String| {
+        Error::InvalidConfig(format!("{}:{}: {}", path, location, message))
+    };
+
+    let mut chars = String::new();
+    for (i, line) in content.lines().enumerate() {
+        if let Some((column, c)) = line
+            .chars()
+            .enumerate()
+            .find(|(_, c)| c.is_whitespace() || c.is_control())
+        {
+            return Err(invalid(
+                format!("{}:{}", i + 1, column + 1),
+                format!(
+                    "U+{:04X} is whitespace or a control character. Use an escape such as \\s instead.",
+                    c as u32
+                ),
+            ));
+        }
+        if !line.is_empty() {
+            let line_chars =
+                validation::parse_chars(line).map_err(|err| invalid((i + 1).to_string(), err))?;
+            chars.push_str(&line_chars);
+        }
+    }
+
+    if chars.is_empty() {
+        return Err(Error::InvalidConfig(format!(
+            "The character file '{}' contains no characters.",
+            path
+        )));
+    }
+    Ok(unique_chars(&chars).into_iter().collect())
+}
+
+/// Checks each candidate password must pass, or else be regenerated.
+struct Filters {
+    no_edge_symbols: bool,
+    no_consecutive_identical: bool,
+    no_sequential: Option<usize>,
+    no_keyboard_walks: Option<usize>,
+    blacklist: Vec<String>,
+    #[cfg(feature = "common-words")]
+    words: Option<words::WordFilter>,
+    min_score: Option<u8>,
+    max_attempts: usize,
+    #[cfg(feature = "pwned")]
+    pwned: Option<pwned::Checker>,
+}
+
+impl Filters {
+    fn new(config: &GeneratorConfig) -> Result<Self, Error> {
+        if let Some(score) = config.min_score
+            && score > strength::MAX_SCORE
+        {
+            return Err(Error::InvalidConfig(format!(
+                "The minimum strength score must be between 0 and {}.",
+                strength::MAX_SCORE
+            )));
+        }
+        if config.max_attempts == Some(0) {
+            return Err(Error::InvalidConfig(
+                "At least one attempt per password is required.".to_string(),
+            ));
+        }
+
+        #[cfg(not(feature = "common-words"))]
+        if config.no_words.is_some() {
+            return Err(Error::InvalidConfig(
+                "Checking passwords for common words requires building with the 'common-words' feature.".to_string(),
+            ));
+        }
+
+        #[cfg(not(feature = "pwned"))]
+        if config.check_pwned.is_some() {
+            return Err(Error::InvalidConfig(
+                "Checking passwords against Have I Been Pwned requires building with the 'pwned' feature.".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            no_edge_symbols: config.no_edge_symbols,
+            no_consecutive_identical: config.no_consecutive_identical,
+            no_sequential: config.no_sequential,
+            no_keyboard_walks: config.no_keyboard_walks,
+            blacklist: config
+                .blacklist
+                .iter()
+                .filter(|term| !term.is_empty())
+                .map(|term| term.to_lowercase())
+                .collect(),
+            #[cfg(feature = "common-words")]
+            words: config.no_words.map(words::WordFilter::new),
+            min_score: config.min_score,
+            max_attempts: config.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+            #[cfg(feature = "pwned")]
+            pwned: config.check_pwned.map(|mode| {
+                let api_url = config
+                    .pwned_api_url
+                    .as_deref()
+                    .unwrap_or(pwned::DEFAULT_API_URL);
+                pwned::Checker::with_api_url(mode, api_url)
+            }),
+        })
+    }
+
+    // Whether any check can reject a candidate.
+    fn is_active(&self) -> bool {
+        #[cfg(feature = "common-words")]
+        if self.words.is_some() {
+            return true;
+        }
+        #[cfg(feature = "pwned")]
+        if self.pwned.is_some() {
+            return true;
+        }
+        self.no_edge_symbols
+            || self.no_consecutive_identical
+            || self.no_sequential.is_some()
+            || self.no_keyboard_walks.is_some()
+            || !self.blacklist.is_empty()
+            || self.min_score.is_some()
+    }
+
+    // The constraint `password` violates, if any.
+    fn rejection(&self, password: &str) -> Result<Option<String>, Error> {
+        // Placement fixes the edges whenever the password has enough non-symbols to do so.
+        if self.no_edge_symbols
+            && (password.chars().next().is_some_and(is_symbol)
+                || password.chars().last().is_some_and(is_symbol))
+        {
+            return Ok(Some("no symbols at either end".to_string()));
+        }
+
+        if self.no_consecutive_identical
+            && password
+                .chars()
+                .zip(password.chars().skip(1))
+                .any(|(a, b)| a == b)
+        {
+            return Ok(Some("no identical adjacent characters".to_string()));
+        }
+
+        if let Some(min_length) = self.no_sequential
+            && let Some(span) = sequence::find_run(password, min_length)
+        {
+            let run: String = password.chars().skip(span.start).take(span.len()).collect();
+            return Ok(Some(format!(
+                "no sequential runs (candidates kept containing '{}')",
+                run
+            )));
+        }
+
+        if let Some(min_length) = self.no_keyboard_walks
+            && longest_keyboard_walk(password) >= min_length
+        {
+            return Ok(Some(format!(
+                "no keyboard walks of {} or more keys",
+                min_length
+            )));
+        }
+
+        if !self.blacklist.is_empty() {
+            let lowercase = password.to_lowercase();
+            if let Some(term) = self.blacklist.iter().find(|term| lowercase.contains(*term)) {
+                return Ok(Some(format!(
+                    "the blacklist (candidates kept containing '{}')",
+                    term
+                )));
+            }
+        }
+
+        #[cfg(feature = "common-words")]
+        if let Some(word) = self.words.as_ref().and_then(|words| words.find(password)) {
+            return Ok(Some(format!(
+                "the absence of common words (candidates kept containing '{}')",
+                word
+            )));
+        }
+
+        if let Some(min_score) = self.min_score
+            && strength::estimate(password).score < min_score
+        {
+            return Ok(Some(format!("a minimum strength score of {}", min_score)));
+        }
+
+        // The breach check goes last so that only otherwise acceptable candidates are sent.
+        #[cfg(feature = "pwned")]
+        if let Some(checker) = &self.pwned
+            && checker.is_pwned(password).map_err(Error::BreachCheck)?
+        {
+            return Ok(Some("absence from known data breaches".to_string()));
+        }
+        Ok(None)
+    }
+}
+
+/// How the characters of each password are chosen.
+enum Strategy {
+    /// One character from each required set, filled from the final charset and shuffled.
+    Random(Placement),
+    /// One character per pattern position.
+    Pattern(Vec<Vec<char>>),
+    /// Alternating consonant/vowel syllables.
+    Pronounceable(Syllables),
+    /// Separated consonant/vowel chunks with one digit and one capital.
+    Memorable(Chunks),
+    /// RFC 4122 version 4 UUIDs.
+    Uuid,
+}
+
+/// Restrictions on where characters of a random password may end up after the shuffle.
+struct Placement {
+    /// The characters allowed in the first position, if restricted.
+    leading: Option<Vec<char>>,
+    /// Keep symbols out of the first and last positions.
+    no_edge_symbols: bool,
+    /// Reject candidates that break the rules instead of arranging their characters.
+    uniform: bool,
+}
+
+// The characters of `s` without repeats, in order of first appearance. Fragments may repeat
+// characters, which would otherwise be drawn more often.
+fn unique_chars(s: &str) -> Vec<char> {
+    let mut chars = Vec::new();
+    for c in s.chars() {
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    chars
+}
+
+// Whether `c` counts as a symbol for placement: anything but a letter or digit.
+fn is_symbol(c: char) -> bool {
+    !c.is_alphanumeric()
+}
+
+/// A validated password generator built from a [`GeneratorConfig`].
+pub struct PasswordGenerator {
+    final_charset: Vec<char>,
+    required_sets: Vec<CharSet>,
+    categories: Vec<String>,
+    strategy: Strategy,
+    filters: Filters,
+    unique: bool,
+    no_repeat: bool,
+    length: usize,
+    // The longest length when lengths vary per password, otherwise `length`.
+    max_length: usize,
+    count: usize,
+    jobs: usize,
+    // The substitutions of '--leet', limited to characters of the final charset.
+    leet: Option<Leet>,
+    // The weight of each required set in the fill, empty when every character is as likely.
+    weights: Vec<f64>,
+    // Candidates discarded by the checks so far, shared by the jobs of a batch.
+    rejected: AtomicUsize,
+}
+
+impl PasswordGenerator {
+    fn build_required_sets(config: &GeneratorConfig) -> Vec<CharSet> {
+        // A complete charset stands alone and requires nothing.
+        if let Some(charset) = &config.charset {
+            return vec![CharSet {
+                name: charset.name.clone(),
+                chars: charset
+                    .chars
+                    .chars()
+                    .filter(|c| !(config.avoid_ambiguous && AMBIGUOUS.contains(*c)))
+                    .collect(),
+                min: 0,
+                max: None,
+            }];
+        }
+
+        let mut required_sets = Self::build_default_sets(config);
+
+        if let Some(custom_chars) = &config.custom_chars
+            && !custom_chars.is_empty()
+        {
+            required_sets.push(CharSet {
+                name: "custom".to_string(),
+                chars: unique_chars(custom_chars),
+                min: 1,
+                max: None,
+            });
+        }
+        // A minimum of zero adds the characters to the pool without requiring them.
+        if let Some(optional_chars) = &config.optional_chars
+            && !optional_chars.is_empty()
+        {
+            required_sets.push(CharSet {
+                name: "optional".to_string(),
+                chars: unique_chars(optional_chars),
+                min: 0,
+                max: None,
+            });
+        }
+        if config.emoji > 0 {
+            required_sets.push(CharSet {
+                name: EMOJI_SET.to_string(),
+                chars: EMOJI.chars().collect(),
+                min: config.emoji,
+                max: Some(config.emoji),
+            });
+        }
+        required_sets
+    }
+
+    fn build_default_sets(config: &GeneratorConfig) -> Vec<CharSet> {
+        CharClass::ALL
+            .into_iter()
+            .filter_map(|class| {
+                let (use_set, min, max) = match class {
+                    CharClass::Lower => (config.use_lower, config.min_lower, config.max_lower),
+                    CharClass::Upper => (config.use_upper, config.min_upper, config.max_upper),
+                    CharClass::Digit => (config.use_digits, config.min_digits, config.max_digits),
+                    CharClass::Symbol => {
+                        (config.use_symbols, config.min_symbols, config.max_symbols)
+                    }
+                };
+                use_set.then(|| CharSet {
+                    name: class.name().to_string(),
+                    chars: unique_chars(Self::class_chars(config, class))
+                        .into_iter()
+                        .filter(|c| !(config.avoid_ambiguous && AMBIGUOUS.contains(*c)))
+                        .collect(),
+                    min: min.max(1),
+                    max,
+                })
+            })
+            .collect()
+    }
+
+    // The characters of a built-in category, after any replacement of the symbols.
+    fn class_chars(config: &GeneratorConfig, class: CharClass) -> &str {
+        match (class, &config.symbols_set) {
+            (CharClass::Symbol, Some(symbols)) => symbols,
+            _ => class.chars(),
+        }
+    }
+
+    // Remove excluded characters from every required set.
+    fn apply_exclusions(required_sets: &mut [CharSet], exclude_chars: &Option<String>) {
+        if let Some(exclude_chars) = exclude_chars {
+            for set in required_sets.iter_mut() {
+                set.chars.retain(|c| !exclude_chars.contains(*c));
+            }
+        }
+    }
+
+    /// Validate the configuration and resolve the character sets.
+    pub fn new(config: &GeneratorConfig) -> Result<Self, Error> {
+        let generator = match config.bits {
+            Some(bits) => {
+                let limit = config.length_limit.unwrap_or(validation::MAX_LENGTH);
+                let mut length = Self::length_for_bits(config, bits)?;
+                loop {
+                    if length > limit {
+                        return Err(Error::InvalidConfig(format!(
+                            "The password length {} is above the maximum of {}. Use --max-length to raise it.",
+                            length, limit
+                        )));
+                    }
+                    let generator = Self::build(&GeneratorConfig {
+                        length,
+                        length_max: None,
+                        ..config.clone()
+                    })?;
+                    // Emoji take fewer bits than the estimate, which may need another character
+                    // or two.
+                    if generator.entropy_bits() >= bits {
+                        break generator;
+                    }
+                    length += 1;
+                }
+            }
+            None if config.auto_length => {
+                let length = Self::length_needed(config)?;
+                Self::build(&GeneratorConfig {
+                    length,
+                    length_max: config.length_max.map(|max| max.max(length)),
+                    ..config.clone()
+                })?
+            }
+            None => Self::build(config)?,
+        };
+
+        // Entropy only depends on the configuration, so a shortfall is reported up front.
+        if let Some(required) = config.min_entropy {
+            let bits = generator.entropy_bits();
+            if bits < required {
+                let suggestion = match generator.strategy {
+                    Strategy::Random(_) => length_for_char_bits(
+                        required,
+                        char_bits(
+                            &generator.required_sets,
+                            &generator.weights,
+                            &generator.final_charset,
+                        ),
+                    )
+                    .map(|length| format!("Use --length {} or more.", length)),
+                    Strategy::Pattern(_) => {
+                        Some("Use a longer pattern or broader classes.".to_string())
+                    }
+                    Strategy::Pronounceable(_) => Some("Use a longer --length.".to_string()),
+                    Strategy::Memorable(_) => None,
+                    Strategy::Uuid => Some("UUIDs always have 122 random bits.".to_string()),
+                };
+                return Err(Error::InsufficientEntropy {
+                    bits,
+                    required,
+                    suggestion: suggestion
+                        .unwrap_or_else(|| "Add more characters or use another mode.".to_string()),
+                });
+            }
+        }
+        // Collisions become frequent long before the space runs out, so insist on twice the room.
+        if config.unique {
+            let space = generator.entropy_bits().exp2().round();
+            if config.count as f64 > space / 2.0 {
+                return Err(Error::InvalidConfig(format!(
+                    "{} unique passwords were requested, but only about {:.0} different passwords are possible. Use a longer length or more characters.",
+                    config.count, space
+                )));
+            }
+        }
+        Ok(generator)
+    }
+
+    // The shortest length at which the charset reaches `bits`, but never shorter than the
+    // required characters or the minimum password length.
+    fn length_for_bits(config: &GeneratorConfig, bits: f64) -> Result<usize, Error> {
+        if config.uuid || config.pattern.is_some() || config.pronounceable || config.memorable {
+            return Err(Error::InvalidConfig(
+                "An entropy target can only set the length of random passwords.".to_string(),
+            ));
+        }
+
+        let (charset_size, char_bits, required_total) = Self::random_charset(config);
+        // Leave an empty charset for `build` to report.
+        if charset_size == 0 {
+            return Ok(config.length);
+        }
+        let Some(length) = length_for_char_bits(bits, char_bits) else {
+            return Err(Error::InvalidConfig(
+                "A single character cannot reach an entropy target.".to_string(),
+            ));
+        };
+        Ok(length.max(required_total).max(validation::MIN_LENGTH))
+    }
+
+    // The configured length, raised to fit the required characters and to reach
+    // `min_entropy`.
+    fn length_needed(config: &GeneratorConfig) -> Result<usize, Error> {
+        if config.uuid || config.pattern.is_some() || config.pronounceable || config.memorable {
+            return Err(Error::InvalidConfig(
+                "Only the length of random passwords can be raised automatically.".to_string(),
+            ));
+        }
+
+        let (_, char_bits, required_total) = Self::random_charset(config);
+        let entropy_length = config
+            .min_entropy
+            .and_then(|bits| length_for_char_bits(bits, char_bits))
+            .unwrap_or(0);
+        Ok(config.length.max(required_total).max(entropy_length))
+    }
+
+    // The number of distinct characters of the required sets of random passwords, the bits
+    // of each character, and the characters they require in total.
+    fn random_charset(config: &GeneratorConfig) -> (usize, f64, usize) {
+        let mut required_sets = Self::build_required_sets(config);
+        Self::apply_exclusions(&mut required_sets, &config.exclude_chars);
+        let charset: Vec<char> = required_sets
+            .iter()
+            .flat_map(|set| set.chars.iter())
+            .cloned()
+            .collect::<BTreeSet<char>>()
+            .into_iter()
+            .collect();
+        let weights = set_weights(&required_sets, &config.weights);
+        let required_total = required_sets.iter().map(|set| set.min).sum();
+        (
+            charset.len(),
+            char_bits(&required_sets, &weights, &charset),
+            required_total,
+        )
+    }
+
+    fn build(config: &GeneratorConfig) -> Result<Self, Error> {
+        let filters = Filters::new(config)?;
+
+        // Whether the strategy lays out its own characters.
+        let structured =
+            config.uuid || config.pattern.is_some() || config.pronounceable || config.memorable;
+        if config.no_repeat && structured {
+            return Err(Error::InvalidConfig(
+                "Distinct characters can only be required for random passwords.".to_string(),
+            ));
+        }
+        if config.uniform && structured {
+            return Err(Error::InvalidConfig(
+                "Uniform sampling only applies to random passwords.".to_string(),
+            ));
+        }
+        if config.uniform && config.no_repeat {
+            return Err(Error::InvalidConfig(
+                "Uniform sampling cannot be combined with distinct characters.".to_string(),
+            ));
+        }
+        if config.emoji > 0 && (structured || config.charset.is_some()) {
+            return Err(Error::InvalidConfig(
+                "Emoji can only be mixed into random passwords of the categories.".to_string(),
+            ));
+        }
+        if !config.weights.is_empty() {
+            if structured {
+                return Err(Error::InvalidConfig(
+                    "Weights only apply to random passwords.".to_string(),
+                ));
+            }
+            if config.uniform || config.no_repeat {
+                return Err(Error::InvalidConfig(
+                    "Weights cannot be combined with uniform sampling or distinct characters."
+                        .to_string(),
+                ));
+            }
+        }
+        if (config.start_with_letter || config.no_edge_symbols) && structured {
+            return Err(Error::InvalidConfig(
+                "Character positions can only be restricted for random passwords.".to_string(),
+            ));
+        }
+        if let Some(leet) = &config.leet {
+            if structured {
+                return Err(Error::InvalidConfig(
+                    "Leet substitutions only apply to random passwords.".to_string(),
+                ));
+            }
+            if !(0.0..=1.0).contains(&leet.probability) {
+                return Err(Error::InvalidConfig(
+                    "The leet probability must be between 0 and 1.".to_string(),
+                ));
+            }
+        }
+
+        let max_length = config.length_max.unwrap_or(config.length);
+        if max_length < config.length {
+            return Err(Error::InvalidConfig(format!(
+                "The shortest length ({}) exceeds the longest ({}).",
+                config.length, max_length
+            )));
+        }
+        if max_length > config.length
+            && (config.uuid || config.pattern.is_some() || config.memorable)
+        {
+            return Err(Error::InvalidConfig(
+                "A length range only applies to random and pronounceable passwords.".to_string(),
+            ));
+        }
+
+        if config.uuid {
+            return Ok(Self {
+                final_charset: Vec::new(),
+                required_sets: Vec::new(),
+                categories: vec!["uuid".to_string()],
+                strategy: Strategy::Uuid,
+                filters,
+                unique: config.unique,
+                no_repeat: config.no_repeat,
+                length: 36,
+                max_length: 36,
+                count: config.count,
+                jobs: config.jobs,
+                leet: None,
+                weights: Vec::new(),
+                rejected: AtomicUsize::new(0),
+            });
+        }
+
+        if config.memorable {
+            let chunks = Self::build_chunks(config)?;
+            let final_charset: BTreeSet<char> = chunks
+                .consonants
+                .iter()
+                .chain(&chunks.vowels)
+                .cloned()
+                .chain(
+                    chunks
+                        .upper_consonants
+                        .iter()
+                        .chain(&chunks.upper_vowels)
+                        .map(char::to_ascii_uppercase),
+                )
+                .chain(chunks.digits.iter().cloned())
+                .collect();
+            let length = MEMORABLE_CHUNKS * (MEMORABLE_CHUNK.len() + 1) - 1;
+
+            return Ok(Self {
+                final_charset: final_charset.into_iter().collect(),
+                required_sets: Vec::new(),
+                categories: [CharClass::Lower, CharClass::Upper, CharClass::Digit]
+                    .iter()
+                    .map(|class| class.name().to_string())
+                    .collect(),
+                strategy: Strategy::Memorable(chunks),
+                filters,
+                unique: config.unique,
+                no_repeat: config.no_repeat,
+                length,
+                max_length: length,
+                count: config.count,
+                jobs: config.jobs,
+                leet: None,
+                weights: Vec::new(),
+                rejected: AtomicUsize::new(0),
+            });
+        }
+
+        let mut required_sets = Self::build_required_sets(config);
+        Self::apply_exclusions(&mut required_sets, &config.exclude_chars);
+        let categories = required_sets.iter().map(|set| set.name.clone()).collect();
+
+        if let Some(set) = required_sets.iter().find(|set| set.chars.is_empty()) {
+            return Err(Error::EmptySet {
+                name: set.name.clone(),
+            });
+        }
+
+        // A sorted set keeps the charset order stable, so a seed reproduces the same output.
+        let final_charset: BTreeSet<char> = required_sets
+            .iter()
+            .flat_map(|set| set.chars.iter())
+            .cloned()
+            .collect();
+
+        if let Some(pattern) = &config.pattern {
+            let final_charset: Vec<char> = final_charset.into_iter().collect();
+            let pattern_sets = Self::build_pattern_sets(config, pattern, &final_charset)?;
+
+            return Ok(Self {
+                final_charset,
+                required_sets: Vec::new(),
+                categories,
+                length: pattern_sets.len(),
+                max_length: pattern_sets.len(),
+                strategy: Strategy::Pattern(pattern_sets),
+                filters,
+                unique: config.unique,
+                no_repeat: config.no_repeat,
+                count: config.count,
+                jobs: config.jobs,
+                leet: None,
+                weights: Vec::new(),
+                rejected: AtomicUsize::new(0),
+            });
+        }
+
+        if config.pronounceable {
+            let syllables = Self::build_syllables(config)?;
+
+            return Ok(Self {
+                final_charset: final_charset.into_iter().collect(),
+                required_sets: Vec::new(),
+                categories,
+                strategy: Strategy::Pronounceable(syllables),
+                filters,
+                unique: config.unique,
+                no_repeat: config.no_repeat,
+                length: config.length,
+                max_length,
+                count: config.count,
+                jobs: config.jobs,
+                leet: None,
+                weights: Vec::new(),
+                rejected: AtomicUsize::new(0),
+            });
+        }
+
+        // Every selected set contributes at least one character, or its minimum count.
+        let required_total: usize = required_sets.iter().map(|set| set.min).sum();
+        if config.length < required_total {
+            return Err(Error::LengthTooShort {
+                length: config.length,
+                required: required_total,
+            });
+        }
+
+        if final_charset.is_empty() {
+            return Err(Error::EmptyCharset);
+        }
+
+        // Every length in the range must be reachable, so the longest one is checked.
+        Self::validate_maximums(&required_sets, &final_charset, max_length)?;
+
+        // The letters of the enabled letter sets; each of them is in every password.
+        let leading: Vec<char> = required_sets
+            .iter()
+            .filter(|set| {
+                set.name == CharClass::Lower.name() || set.name == CharClass::Upper.name()
+            })
+            .flat_map(|set| set.chars.iter().cloned())
+            .collect();
+        if config.start_with_letter && leading.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Starting with a letter requires the lowercase or uppercase set.".to_string(),
+            ));
+        }
+        if config.no_edge_symbols && final_charset.iter().all(|c| is_symbol(*c)) {
+            return Err(Error::InvalidConfig(
+                "Symbols cannot be kept out of the first and last positions when every character is a symbol.".to_string(),
+            ));
+        }
+        if config.no_repeat {
+            Self::validate_distinct(&required_sets, &final_charset, max_length)?;
+        }
+        if config.no_consecutive_identical && final_charset.len() < 2 && max_length > 1 {
+            return Err(Error::InvalidConfig(
+                "Identical adjacent characters cannot be avoided with a single character."
+                    .to_string(),
+            ));
+        }
+
+        // Substitutes must survive the same filtering as every other character.
+        let leet = config.leet.as_ref().map(|leet| Leet {
+            map: LeetMap(
+                leet.map
+                    .0
+                    .iter()
+                    .map(|(letter, substitutes)| {
+                        let substitutes = substitutes
+                            .iter()
+                            .filter(|c| final_charset.contains(c))
+                            .cloned()
+                            .collect();
+                        (letter.to_ascii_lowercase(), substitutes)
+                    })
+                    .filter(|(_, substitutes): &(char, Vec<char>)| !substitutes.is_empty())
+                    .collect(),
+            ),
+            probability: leet.probability,
+        });
+
+        for (i, (class, factor)) in config.weights.iter().enumerate() {
+            if !(factor.is_finite() && *factor > 0.0) {
+                return Err(Error::InvalidConfig(format!(
+                    "The weight of {} must be positive. Leave the category out instead of giving it no weight.",
+                    class.name()
+                )));
+            }
+            if config.weights[..i].iter().any(|(other, _)| other == class) {
+                return Err(Error::InvalidConfig(format!(
+                    "The weight of {} is given more than once.",
+                    class.name()
+                )));
+            }
+            if !required_sets.iter().any(|set| set.name == class.name()) {
+                return Err(Error::InvalidConfig(format!(
+                    "A weight is given for {}, which are not selected.",
+                    class.name()
+                )));
+            }
+        }
+        let weights = set_weights(&required_sets, &config.weights);
+
+        let placement = Placement {
+            leading: config.start_with_letter.then_some(leading),
+            no_edge_symbols: config.no_edge_symbols,
+            uniform: config.uniform,
+        };
+        let generator = Self {
+            final_charset: final_charset.into_iter().collect(),
+            required_sets,
+            categories,
+            strategy: Strategy::Random(placement),
+            filters,
+            unique: config.unique,
+            no_repeat: config.no_repeat,
+            length: config.length,
+            max_length,
+            count: config.count,
+            jobs: config.jobs,
+            leet,
+            weights,
+            rejected: AtomicUsize::new(0),
+        };
+        if config.uniform {
+            generator.validate_acceptance()?;
+        }
+        Ok(generator)
+    }
+
+    // Reject uniform sampling that would rarely draw a valid candidate within the attempts.
+    // The shortest length is hardest for minimums and the longest for maximums.
+    fn validate_acceptance(&self) -> Result<(), Error> {
+        // Expecting this many valid candidates per password makes running out of attempts
+        // negligible, at about e^-20 per password.
+        const MIN_EXPECTED_ACCEPTED: f64 = 20.0;
+
+        let attempts = self.filters.max_attempts;
+        for length in [self.length, self.max_length] {
+            if let Some(acceptance) = self.acceptance(length)
+                && acceptance * (attempts as f64) < MIN_EXPECTED_ACCEPTED
+            {
+                let ratio = 1.0 / acceptance.max(f64::MIN_POSITIVE);
+                let ratio = if ratio < 1e6 {
+                    format!("{:.0}", ratio)
+                } else {
+                    format!("{:.1e}", ratio)
+                };
+                return Err(Error::InvalidConfig(format!(
+                    "Uniform sampling would accept only about 1 in {} candidates of {} characters, too few for {} attempts per password. Use a longer length, fewer required characters, or the default sampling.",
+                    ratio, length, attempts
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The chance that a random password of `length` characters drawn uniformly from the
+    /// charset has every category count in bounds and keeps the position rules, which is the
+    /// share of candidates that uniform sampling accepts.
+    ///
+    /// `None` for passwords that are not random, or when the category counts have too many
+    /// combinations to track.
+    pub fn acceptance(&self, length: usize) -> Option<f64> {
+        const MAX_STATES: usize = 4096;
+
+        let Strategy::Random(placement) = &self.strategy else {
+            return None;
+        };
+        let sets = &self.required_sets;
+        if sets.len() > 64 {
+            return None;
+        }
+
+        // Counts beyond a maximum all fail, and counts beyond a minimum without a maximum all
+        // pass, so each set's count is tracked up to a cap. A state holds every set's count.
+        let caps: Vec<usize> = sets
+            .iter()
+            .map(|set| set.max.map_or(set.min, |max| max + 1))
+            .collect();
+        let mut strides = Vec::with_capacity(caps.len());
+        let mut states = 1usize;
+        for cap in &caps {
+            strides.push(states);
+            states = states.checked_mul(cap + 1).filter(|&n| n <= MAX_STATES)?;
+        }
+        let count = |state: usize, i: usize| state / strides[i] % (caps[i] + 1);
+
+        let edges = placement.no_edge_symbols && length > 2;
+        let allowed = |position: usize, c: char| {
+            let leads = position != 0
+                || placement
+                    .leading
+                    .as_ref()
+                    .is_none_or(|leading| leading.contains(&c));
+            let edge = position == 0 || position + 1 == length;
+            leads && !(edges && edge && is_symbol(c))
+        };
+
+        let total = self.final_charset.len() as f64;
+        let mut chances = vec![0.0; states];
+        chances[0] = 1.0;
+        for position in 0..length {
+            // The allowed characters by the sets they belong to.
+            let mut groups: Vec<(u64, usize)> = Vec::new();
+            for &c in self.final_charset.iter().filter(|&&c| allowed(position, c)) {
+                let mask = sets
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, set)| set.chars.contains(&c))
+                    .fold(0u64, |mask, (i, _)| mask | 1 << i);
+                match groups.iter_mut().find(|(group, _)| *group == mask) {
+                    Some((_, n)) => *n += 1,
+                    None => groups.push((mask, 1)),
+                }
+            }
+
+            let mut next = vec![0.0; states];
+            for (state, &chance) in chances.iter().enumerate().filter(|(_, c)| **c > 0.0) {
+                for &(mask, n) in &groups {
+                    let to = (0..sets.len())
+                        .filter(|i| mask & 1 << i != 0 && count(state, *i) < caps[*i])
+                        .fold(state, |to, i| to + strides[i]);
+                    next[to] += chance * n as f64 / total;
+                }
+            }
+            chances = next;
+        }
+
+        Some(
+            chances
+                .iter()
+                .enumerate()
+                .filter(|(state, _)| {
+                    sets.iter().enumerate().all(|(i, set)| {
+                        let n = count(*state, i);
+                        n >= set.min && set.max.is_none_or(|max| n <= max)
+                    })
+                })
+                .map(|(_, chance)| chance)
+                .sum(),
+        )
+    }
+
+    // Characters of a built-in set after ambiguity and exclusion filtering.
+    fn filtered_chars(config: &GeneratorConfig, set_str: &str) -> Vec<char> {
+        unique_chars(set_str)
+            .into_iter()
+            .filter(|c| !(config.avoid_ambiguous && AMBIGUOUS.contains(*c)))
+            .filter(|c| {
+                !config
+                    .exclude_chars
+                    .as_ref()
+                    .is_some_and(|e| e.contains(*c))
+            })
+            .collect()
+    }
+
+    // Resolve each pattern position to the characters it may produce.
+    fn build_pattern_sets(
+        config: &GeneratorConfig,
+        pattern: &Pattern,
+        final_charset: &[char],
+    ) -> Result<Vec<Vec<char>>, Error> {
+        pattern
+            .0
+            .iter()
+            .map(|class| {
+                let (name, chars) = match class {
+                    PatternClass::Lower => ("lowercase", Self::filtered_chars(config, LOWERCASE)),
+                    PatternClass::Upper => ("uppercase", Self::filtered_chars(config, UPPERCASE)),
+                    PatternClass::Digit => ("digits", Self::filtered_chars(config, DIGITS)),
+                    PatternClass::Symbol => (
+                        "symbols",
+                        Self::filtered_chars(config, Self::class_chars(config, CharClass::Symbol)),
+                    ),
+                    PatternClass::Alnum => {
+                        let alnum = [LOWERCASE, UPPERCASE, DIGITS].concat();
+                        ("alphanumeric", Self::filtered_chars(config, &alnum))
+                    }
+                    PatternClass::Any => ("any", final_charset.to_vec()),
+                    PatternClass::Literal(c) => ("literal", vec![*c]),
+                };
+
+                if chars.is_empty() {
+                    return Err(Error::InvalidConfig(format!(
+                        "The {} class in the pattern has no characters left after filtering.",
+                        name
+                    )));
+                }
+                Ok(chars)
+            })
+            .collect()
+    }
+
+    // Collect the letters for pronounceable passwords, plus a digit and an uppercase
+    // letter to sprinkle in when those categories are enabled.
+    fn build_syllables(config: &GeneratorConfig) -> Result<Syllables, Error> {
+        let consonants = Self::filtered_chars(config, CONSONANTS);
+        let vowels = Self::filtered_chars(config, VOWELS);
+
+        if consonants.is_empty() || vowels.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Pronounceable passwords need at least one consonant and one vowel after filtering."
+                    .to_string(),
+            ));
+        }
+
+        let digits = Some(Self::filtered_chars(config, DIGITS))
+            .filter(|digits| config.use_digits && !digits.is_empty());
+        let uppercase = Some(Self::filtered_chars(config, UPPERCASE))
+            .filter(|upper| config.use_upper && !upper.is_empty());
+
+        // Every password needs one whole syllable, and a digit beside it when digits are on.
+        let shortest = 2 + usize::from(digits.is_some());
+        if config.length < shortest {
+            return Err(Error::InvalidConfig(format!(
+                "Pronounceable passwords need a length of at least {}.",
+                shortest
+            )));
+        }
+
+        Ok(Syllables {
+            consonants,
+            vowels,
+            digits,
+            uppercase,
+        })
+    }
+
+    // Collect the letters and digits of memorable passwords. Each kind must survive filtering,
+    // since every password has all of them.
+    fn build_chunks(config: &GeneratorConfig) -> Result<Chunks, Error> {
+        let consonants = Self::filtered_chars(config, CONSONANTS);
+        let vowels = Self::filtered_chars(config, VOWELS);
+        let uppercase = Self::filtered_chars(config, UPPERCASE);
+        let capitals = |letters: &[char]| -> Vec<char> {
+            letters
+                .iter()
+                .filter(|c| uppercase.contains(&c.to_ascii_uppercase()))
+                .cloned()
+                .collect()
+        };
+        let chunks = Chunks {
+            upper_consonants: capitals(&consonants),
+            upper_vowels: capitals(&vowels),
+            consonants,
+            vowels,
+            digits: Self::filtered_chars(config, DIGITS),
+        };
+
+        if chunks.consonants.is_empty() || chunks.vowels.is_empty() || chunks.digits.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Memorable passwords need at least one consonant, vowel, and digit after filtering."
+                    .to_string(),
+            ));
+        }
+        if chunks.upper_consonants.is_empty() && chunks.upper_vowels.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Memorable passwords need an uppercase letter after filtering.".to_string(),
+            ));
+        }
+        Ok(chunks)
+    }
+
+    // Check that the per-category maximums agree with the minimums and still allow
+    // a password of the requested length to be filled.
+    fn validate_maximums(
+        required_sets: &[CharSet],
+        final_charset: &BTreeSet<char>,
+        length: usize,
+    ) -> Result<(), Error> {
+        for set in required_sets {
+            if let Some(max) = set.max
+                && max < set.min
+            {
+                return Err(Error::InvalidConfig(format!(
+                    "The minimum number of {} ({}) exceeds the maximum ({}).",
+                    set.name, set.min, max
+                )));
+            }
+        }
+
+        let capped: Vec<&CharSet> = required_sets.iter().filter(|s| s.max.is_some()).collect();
+        let has_uncapped_chars = final_charset
+            .iter()
+            .any(|c| !capped.iter().any(|set| set.chars.contains(c)));
+        let capacity: usize = capped.iter().filter_map(|set| set.max).sum();
+
+        if !has_uncapped_chars && capacity < length {
+            return Err(Error::InvalidConfig(format!(
+                "Password length ({}) cannot be reached with the maximum character counts ({}).",
+                length, capacity
+            )));
+        }
+        Ok(())
+    }
+
+    // Check that the password and each set's minimum fit without repeating a character.
+    fn validate_distinct(
+        required_sets: &[CharSet],
+        final_charset: &BTreeSet<char>,
+        length: usize,
+    ) -> Result<(), Error> {
+        if length > final_charset.len() {
+            return Err(Error::InvalidConfig(format!(
+                "Password length ({}) exceeds the {} distinct characters available, so characters would have to repeat.",
+                length,
+                final_charset.len()
+            )));
+        }
+        for set in required_sets {
+            if set.min > set.chars.len() {
+                return Err(Error::InvalidConfig(format!(
+                    "The minimum number of {} ({}) exceeds the {} distinct characters in the set.",
+                    set.name,
+                    set.min,
+                    set.chars.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // Whether adding `c` would exceed the maximum of a set that has already reached it.
+    fn exceeds_max(&self, c: char, counts: &[usize]) -> bool {
+        self.required_sets
+            .iter()
+            .zip(counts)
+            .any(|(set, count)| set.max.is_some_and(|max| *count >= max) && set.chars.contains(&c))
+    }
+
+    /// The number of passwords requested by the configuration.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The length of each password, e.g. as derived from an entropy target. When lengths vary,
+    /// this is the shortest one.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The longest password length, equal to [`length`](Self::length) unless lengths vary.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// The names of the character categories in use, e.g. `lowercase` or `hex`.
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
 
-impl<'a> PasswordGenerator<'a> {
-    fn build_required_sets(config: &GeneratorConfig<'a>) -> Vec<Vec<char>> {
-        let mut required_sets = Self::build_default_sets(config);
+    /// The number of candidates discarded so far by checks such as `min_score` or `unique`.
+    pub fn rejected(&self) -> usize {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Whether any check, including `unique`, can discard candidates.
+    pub fn rejects_candidates(&self) -> bool {
+        self.unique
+            || self.filters.is_active()
+            || self.leet.is_some()
+            || matches!(&self.strategy, Strategy::Random(placement) if placement.uniform)
+    }
+
+    /// Every character a password may contain, sorted and without repeats.
+    pub fn charset(&self) -> &[char] {
+        &self.final_charset
+    }
+
+    /// The sets random passwords take their minimum and maximum counts from. Patterns and
+    /// pronounceable passwords have none.
+    pub fn required_sets(&self) -> &[CharSet] {
+        &self.required_sets
+    }
+
+    /// The theoretical entropy of each password in bits.
+    ///
+    /// Random passwords count `length * log2(charset size)` over the deduplicated charset, so
+    /// minimums and maximums make this an upper bound. With uniform sampling it is exact
+    /// instead, reduced by the share of candidates accepted. Pronounceable passwords are estimated
+    /// conservatively from their consonant/vowel syllables. When lengths vary, this is the
+    /// entropy of the shortest password.
+    pub fn entropy_bits(&self) -> f64 {
+        self.entropy_bits_at(self.length)
+    }
+
+    /// The theoretical entropy of the passwords, by length when lengths vary.
+    pub fn entropy(&self) -> output::Entropy {
+        if self.max_length == self.length {
+            return output::Entropy::Fixed(self.entropy_bits());
+        }
+        output::Entropy::ByLength {
+            min_length: self.length,
+            bits: (self.length..=self.max_length)
+                .map(|length| self.entropy_bits_at(length))
+                .collect(),
+        }
+    }
 
-        if let Some(custom_chars) = &config.custom_chars {
-            if !custom_chars.is_empty() {
-                required_sets.push(custom_chars.chars().collect());
+    fn entropy_bits_at(&self, length: usize) -> f64 {
+        match &self.strategy {
+            // Without repeats, each position has one candidate fewer than the previous.
+            Strategy::Random(_) if self.no_repeat => (0..length)
+                .map(|i| ((self.final_charset.len() - i) as f64).log2())
+                .sum(),
+            // Uniform sampling spreads over the valid passwords, a known share of all strings.
+            Strategy::Random(placement) if placement.uniform => {
+                let bits = entropy_bits(self.final_charset.len(), length);
+                match self.acceptance(length) {
+                    Some(acceptance) if acceptance > 0.0 => bits + acceptance.log2(),
+                    _ => bits,
+                }
+            }
+            Strategy::Random(_) => self.fill_bits(length),
+            Strategy::Pattern(pattern_sets) => pattern_sets
+                .iter()
+                .map(|set| (set.len() as f64).log2())
+                .sum(),
+            Strategy::Pronounceable(syllables) => {
+                let letters = length - usize::from(syllables.digits.is_some());
+                let mut bits = (letters / 2) as f64
+                    * ((syllables.consonants.len() * syllables.vowels.len()) as f64).log2();
+                if syllables.uppercase.is_some() {
+                    bits += (letters as f64).log2();
+                }
+                if let Some(digits) = &syllables.digits {
+                    bits += (digits.len() as f64).log2() + ((letters + 1) as f64).log2();
+                }
+                bits
             }
+            // The digit and capital positions are uniform and each pair yields different
+            // passwords, so their bits add to the average bits of the letters they leave.
+            Strategy::Memorable(chunks) => {
+                let layouts = Self::memorable_layouts(chunks);
+                let letters: f64 = layouts
+                    .iter()
+                    .map(|(digit, capital)| {
+                        (0..MEMORABLE_CHUNKS * MEMORABLE_CHUNK.len())
+                            .filter(|i| i != digit)
+                            .map(|i| (chunks.letters(i, i == *capital).len() as f64).log2())
+                            .sum::<f64>()
+                    })
+                    .sum::<f64>()
+                    / layouts.len() as f64;
+                (layouts.len() as f64).log2() + (chunks.digits.len() as f64).log2() + letters
+            }
+            // 122 of the 128 bits are random; the rest encode the version and variant.
+            Strategy::Uuid => 122.0,
         }
-        required_sets
     }
 
-    fn build_default_sets(config: &GeneratorConfig<'a>) -> Vec<Vec<char>> {
-        [
-            (config.use_lower, LOWERCASE),
-            (config.use_upper, UPPERCASE),
-            (config.use_digits, DIGITS),
-            (config.use_symbols, SYMBOLS),
-        ]
-        .iter()
-        .filter_map(|(use_set, set_str)| {
-            if *use_set {
-                Some(set_str.chars().collect())
+    /// Lazily generate passwords, one per call to `next`.
+    ///
+    /// The iterator is unbounded; use `take(generator.count())` to honor the configured count.
+    /// With `unique`, it never yields the same password twice.
+    pub fn iter<'a, R: SecureRng + ?Sized>(&'a self, rng: &'a mut R) -> PasswordIter<'a, R> {
+        PasswordIter {
+            generator: self,
+            rng,
+            seen: self.unique.then(HashSet::new),
+            hasher: RandomState::new(),
+        }
+    }
+
+    // The configured count of passwords, generated in parallel when more than one job is
+    // configured and the batch is large enough to benefit. Each job draws from its own
+    // generator seeded from `rng`, and the passwords keep the order of the jobs, so seeded
+    // output is reproducible for a given number of jobs. Uniqueness needs a single view of
+    // the batch, so unique batches stay on the calling thread.
+    fn batch<'a, R: SecureRng + ?Sized>(
+        &'a self,
+        rng: &'a mut R,
+    ) -> Box<dyn Iterator<Item = Result<String, Error>> + 'a> {
+        let jobs = self.jobs.min(self.count.div_ceil(PARALLEL_CHUNK));
+        if jobs <= 1 || self.unique {
+            return Box::new(self.iter(rng).take(self.count));
+        }
+
+        let mut rngs: Vec<StdRng> = (0..jobs).map(|_| StdRng::from_seed(rng.r#gen())).collect();
+        let mut remaining = self.count;
+        let rounds = std::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            // Each round generates up to one chunk per job, which bounds the memory held
+            // before the passwords are written.
+            let round = remaining.min(jobs * PARALLEL_CHUNK);
+            remaining -= round;
+            let passwords: Vec<Result<String, Error>> = thread::scope(|scope| {
+                let workers: Vec<_> = rngs
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, rng)| {
+                        let n = round / jobs + usize::from(i < round % jobs);
+                        scope.spawn(move || self.iter(rng).take(n).collect::<Vec<_>>())
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().expect("password worker panicked"))
+                    .collect()
+            });
+            Some(passwords)
+        });
+        Box::new(rounds.flatten())
+    }
+
+    /// Generate a single password that passes every configured check, regenerating rejected
+    /// candidates up to the attempt limit.
+    pub fn next_password(&self, rng: &mut (impl Rng + ?Sized)) -> Result<String, Error> {
+        let mut constraint = String::new();
+
+        for _ in 0..self.filters.max_attempts {
+            let mut password = self.create_one_password(rng);
+            let rejection = match self
+                .leet_rejection(&password)
+                .or_else(|| self.uniform_rejection(&password))
+            {
+                Some(rejection) => Ok(Some(rejection)),
+                None => self.filters.rejection(&password),
+            };
+            match rejection {
+                Ok(None) => return Ok(password),
+                Ok(Some(rejection)) => {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    constraint = rejection;
+                }
+                Err(err) => {
+                    password.zeroize();
+                    return Err(err);
+                }
+            }
+            password.zeroize();
+        }
+        Err(Error::AttemptsExhausted {
+            attempts: self.filters.max_attempts,
+            constraint,
+        })
+    }
+
+    /// Generate a single candidate password, without the configured checks.
+    pub fn create_one_password(&self, rng: &mut (impl Rng + ?Sized)) -> String {
+        match &self.strategy {
+            Strategy::Random(placement) => self.create_random_password(placement, rng),
+            // A pattern fully determines the password: one character per position.
+            Strategy::Pattern(pattern_sets) => pattern_sets
+                .iter()
+                .map(|set| *set.choose(rng).unwrap())
+                .collect(),
+            Strategy::Pronounceable(syllables) => {
+                self.create_pronounceable_password(syllables, rng)
+            }
+            Strategy::Memorable(chunks) => Self::create_memorable_password(chunks, rng),
+            Strategy::Uuid => Self::create_uuid(rng),
+        }
+    }
+
+    // Every placement of the digit and the capital: the digit at a chunk edge, the capital
+    // at another position that has a letter with an uppercase form.
+    fn memorable_layouts(chunks: &Chunks) -> Vec<(usize, usize)> {
+        let mut layouts = Vec::new();
+        for digit in Chunks::digit_positions() {
+            for capital in 0..MEMORABLE_CHUNKS * MEMORABLE_CHUNK.len() {
+                if capital != digit && !chunks.letters(capital, true).is_empty() {
+                    layouts.push((digit, capital));
+                }
+            }
+        }
+        layouts
+    }
+
+    // Fill the chunks, then join them with hyphens, which are fixed and add no entropy.
+    fn create_memorable_password(chunks: &Chunks, rng: &mut (impl Rng + ?Sized)) -> String {
+        let (digit, capital) = *Self::memorable_layouts(chunks).choose(rng).unwrap();
+
+        let mut password_chars: Vec<char> =
+            Vec::with_capacity(MEMORABLE_CHUNKS * (MEMORABLE_CHUNK.len() + 1));
+        for i in 0..MEMORABLE_CHUNKS * MEMORABLE_CHUNK.len() {
+            if i > 0 && i % MEMORABLE_CHUNK.len() == 0 {
+                password_chars.push('-');
+            }
+            let c = if i == digit {
+                *chunks.digits.choose(rng).unwrap()
+            } else if i == capital {
+                chunks
+                    .letters(i, true)
+                    .choose(rng)
+                    .unwrap()
+                    .to_ascii_uppercase()
             } else {
-                None
+                *chunks.letters(i, false).choose(rng).unwrap()
+            };
+            password_chars.push(c);
+        }
+
+        let password = password_chars.iter().collect();
+        password_chars.zeroize();
+        password
+    }
+
+    // Format 16 random bytes as a lowercase, hyphenated version 4 UUID.
+    fn create_uuid(rng: &mut (impl Rng + ?Sized)) -> String {
+        let mut bytes = [0u8; 16];
+        rng.fill(&mut bytes);
+
+        // Version 4 in the high nibble of byte 6, variant 0b10 in the high bits of byte 8.
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let mut hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let uuid = format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        );
+        bytes.zeroize();
+        hex.zeroize();
+        uuid
+    }
+
+    // Build a password from consonant-vowel (cv) and consonant-vowel-consonant (cvc)
+    // syllables, so at most two consonants ever appear next to each other.
+    fn create_pronounceable_password(
+        &self,
+        syllables: &Syllables,
+        rng: &mut (impl Rng + ?Sized),
+    ) -> String {
+        let length = self.draw_length(rng);
+        let letters = length - usize::from(syllables.digits.is_some());
+        let mut password_chars: Vec<char> = Vec::with_capacity(length + 2);
+
+        while password_chars.len() < letters {
+            password_chars.push(*syllables.consonants.choose(rng).unwrap());
+            password_chars.push(*syllables.vowels.choose(rng).unwrap());
+            if rng.gen_bool(0.5) {
+                password_chars.push(*syllables.consonants.choose(rng).unwrap());
+            }
+        }
+        password_chars.truncate(letters);
+
+        if let Some(uppercase) = &syllables.uppercase {
+            // Only capitalize letters whose uppercase form survived filtering.
+            let positions: Vec<usize> = (0..password_chars.len())
+                .filter(|&i| uppercase.contains(&password_chars[i].to_ascii_uppercase()))
+                .collect();
+            if let Some(&i) = positions.choose(rng) {
+                password_chars[i] = password_chars[i].to_ascii_uppercase();
+            }
+        }
+
+        if let Some(digits) = &syllables.digits {
+            let position = rng.gen_range(0..=password_chars.len());
+            password_chars.insert(position, *digits.choose(rng).unwrap());
+        }
+
+        let password = password_chars.iter().collect();
+        password_chars.zeroize();
+        password
+    }
+
+    // The length of the next password. A fixed length draws nothing, so seeded output does
+    // not depend on whether ranges are supported.
+    fn draw_length(&self, rng: &mut (impl Rng + ?Sized)) -> usize {
+        if self.max_length > self.length {
+            rng.gen_range(self.length..=self.max_length)
+        } else {
+            self.length
+        }
+    }
+
+    fn create_random_password(
+        &self,
+        placement: &Placement,
+        rng: &mut (impl Rng + ?Sized),
+    ) -> String {
+        BUFFERS.with_borrow_mut(|buffers| self.fill_random_password(placement, buffers, rng))
+    }
+
+    fn fill_random_password(
+        &self,
+        placement: &Placement,
+        buffers: &mut Buffers,
+        rng: &mut (impl Rng + ?Sized),
+    ) -> String {
+        let length = self.draw_length(rng);
+        let Buffers {
+            chars: password_chars,
+            pool,
+            counts,
+            sizes,
+        } = buffers;
+        password_chars.reserve(length);
+
+        // Every character is drawn alike, and `uniform_rejection` turns away the candidates
+        // that break the rules.
+        if placement.uniform {
+            password_chars.extend((0..length).map(|_| *self.final_charset.choose(rng).unwrap()));
+            if let Some(leet) = &self.leet {
+                Self::substitute(password_chars, leet, rng);
+            }
+            let password = password_chars.iter().collect();
+            password_chars.zeroize();
+            return password;
+        }
+
+        // Ensure the minimum number of characters from each required set.
+        for req_set in &self.required_sets {
+            for _ in 0..req_set.min {
+                let c = if self.no_repeat {
+                    self.choose_unused(&req_set.chars, password_chars, rng)
+                } else {
+                    *req_set.chars.choose(rng).unwrap()
+                };
+                password_chars.push(c);
+            }
+        }
+
+        // Fill the rest of the password with characters from the final character set,
+        // leaving out sets that have reached their maximum.
+        counts.extend(self.required_sets.iter().map(|set| {
+            password_chars
+                .iter()
+                .filter(|c| set.chars.contains(c))
+                .count()
+        }));
+        pool.extend_from_slice(&self.final_charset);
+        // Only maximums and distinct characters take characters out of the pool, and checking
+        // every character for each position is most of the work.
+        let shrinks = self.no_repeat || self.required_sets.iter().any(|set| set.max.is_some());
+
+        for _ in 0..(length.saturating_sub(password_chars.len())) {
+            if shrinks {
+                pool.retain(|c| {
+                    !(self.exceeds_max(*c, counts) || self.no_repeat && password_chars.contains(c))
+                });
             }
+            let c = match self.choose_fill(pool, sizes, rng) {
+                Some(c) => c,
+                None if self.no_repeat => {
+                    self.choose_unused(&self.final_charset, password_chars, rng)
+                }
+                None => *self.final_charset.choose(rng).unwrap(),
+            };
+
+            for (set, count) in self.required_sets.iter().zip(counts.iter_mut()) {
+                if set.chars.contains(&c) {
+                    *count += 1;
+                }
+            }
+            password_chars.push(c);
+        }
+
+        if let Some(leet) = &self.leet {
+            Self::substitute(password_chars, leet, rng);
+        }
+        password_chars.shuffle(rng);
+        Self::place(password_chars, placement, rng);
+
+        let password = password_chars.iter().collect();
+        // Wiped and emptied, keeping the allocations for the next password.
+        password_chars.zeroize();
+        pool.zeroize();
+        counts.clear();
+        sizes.clear();
+        password
+    }
+
+    // A character of the pool, weighted by set when weights are given.
+    fn choose_fill(
+        &self,
+        pool: &[char],
+        sizes: &mut Vec<usize>,
+        rng: &mut (impl Rng + ?Sized),
+    ) -> Option<char> {
+        if self.weights.is_empty() {
+            return pool.choose(rng).copied();
+        }
+        // Spreading a set's weight over its characters left in the pool picks the set by
+        // weight and then a character uniformly.
+        sizes.clear();
+        sizes.resize(self.required_sets.len(), 0);
+        for &c in pool {
+            sizes[set_of(&self.required_sets, c)] += 1;
+        }
+        pool.choose_weighted(rng, |&c| {
+            let set = set_of(&self.required_sets, c);
+            self.weights[set] / sizes[set] as f64
         })
-        .collect()
+        .ok()
+        .copied()
+    }
+
+    // Replace each letter of the leet map with one of its substitutes, with the map's
+    // probability.
+    fn substitute(password_chars: &mut [char], leet: &Leet, rng: &mut (impl Rng + ?Sized)) {
+        for c in password_chars.iter_mut() {
+            let letter = c.to_ascii_lowercase();
+            if let Some((_, substitutes)) = leet.map.0.iter().find(|(l, _)| *l == letter)
+                && rng.gen_bool(leet.probability)
+            {
+                *c = *substitutes.choose(rng).unwrap();
+            }
+        }
     }
 
-    fn new(config: GeneratorConfig<'a>) -> Result<Self, Box<dyn std::error::Error>> {
-        let required_sets = Self::build_required_sets(&config);
-        let final_charset: HashSet<char> = required_sets
+    // Why a candidate changed by leet substitutions no longer meets the guarantees that
+    // drawing its characters gave it, if it does not.
+    fn leet_rejection(&self, password: &str) -> Option<String> {
+        self.leet.as_ref()?;
+        for set in &self.required_sets {
+            let count = password.chars().filter(|c| set.chars.contains(c)).count();
+            if count < set.min || set.max.is_some_and(|max| count > max) {
+                return Some(format!("the {} counts after leet substitutions", set.name));
+            }
+        }
+        if let Strategy::Random(Placement {
+            leading: Some(leading),
+            ..
+        }) = &self.strategy
+            && !password
+                .chars()
+                .next()
+                .is_some_and(|c| leading.contains(&c))
+        {
+            return Some("a leading letter after leet substitutions".to_string());
+        }
+        if self.no_repeat {
+            let chars: BTreeSet<char> = password.chars().collect();
+            if chars.len() < password.chars().count() {
+                return Some("distinct characters after leet substitutions".to_string());
+            }
+        }
+        None
+    }
+
+    // The entropy of random passwords of `length` built by placing the required characters
+    // and filling the rest. Weights skew the characters, so each carries their Shannon
+    // entropy. A fixed number of emoji is counted apart: their own choices, those of the
+    // other characters, and which positions they take.
+    fn fill_bits(&self, length: usize) -> f64 {
+        // The emoji charset stands alone and requires none.
+        let Some(index) = self
+            .required_sets
+            .iter()
+            .position(|set| set.name == EMOJI_SET && set.min > 0)
+        else {
+            return length as f64
+                * char_bits(&self.required_sets, &self.weights, &self.final_charset);
+        };
+
+        let emoji = &self.required_sets[index];
+        let mut sets = self.required_sets.clone();
+        sets.remove(index);
+        let mut weights = self.weights.clone();
+        if !weights.is_empty() {
+            weights.remove(index);
+        }
+        let charset: Vec<char> = self
+            .final_charset
             .iter()
-            .flat_map(|set| set.iter())
+            .filter(|c| !emoji.chars.contains(c))
             .cloned()
             .collect();
 
-        if config.length < required_sets.len() {
-            return Err(format!(
-                "Password length ({}) is too short to include one character from each selected set ({}).",
-                config.length,
-                required_sets.len()
-            ).into());
+        let count = emoji.min.min(length);
+        (length - count) as f64 * char_bits(&sets, &weights, &charset)
+            + entropy_bits(emoji.chars.len(), count)
+            + log2_binomial(length, count)
+    }
+
+    // Why a uniformly drawn candidate is not a valid password, if it is not.
+    fn uniform_rejection(&self, password: &str) -> Option<String> {
+        let Strategy::Random(placement) = &self.strategy else {
+            return None;
+        };
+        if !placement.uniform {
+            return None;
+        }
+        for set in &self.required_sets {
+            let count = password.chars().filter(|c| set.chars.contains(c)).count();
+            if count < set.min || set.max.is_some_and(|max| count > max) {
+                return Some(format!("the {} counts", set.name));
+            }
+        }
+        let first = password.chars().next();
+        if let Some(leading) = &placement.leading
+            && !first.is_some_and(|c| leading.contains(&c))
+        {
+            return Some("a leading letter".to_string());
+        }
+        let length = password.chars().count();
+        if placement.no_edge_symbols
+            && length > 2
+            && [first, password.chars().last()]
+                .into_iter()
+                .flatten()
+                .any(is_symbol)
+        {
+            return Some("no symbols at the edges".to_string());
         }
+        None
+    }
 
-        if final_charset.is_empty() {
-            return Err(
-                "No character sets selected. Please use --all or select at least one category."
-                    .into(),
-            );
+    // Swap allowed characters into restricted positions. Swapping keeps every set's count.
+    fn place(password_chars: &mut [char], placement: &Placement, rng: &mut (impl Rng + ?Sized)) {
+        if let Some(leading) = &placement.leading
+            && !password_chars.first().is_some_and(|c| leading.contains(c))
+        {
+            let candidates: Vec<usize> = (1..password_chars.len())
+                .filter(|i| leading.contains(&password_chars[*i]))
+                .collect();
+            if let Some(i) = candidates.choose(rng) {
+                password_chars.swap(0, *i);
+            }
         }
 
-        Ok(Self {
-            final_charset: final_charset.into_iter().collect(),
-            required_sets,
-            length: config.length,
-            count: config.count,
-            output: config.output,
-        })
+        // Trade edge symbols for interior non-symbols. The first position is settled before
+        // the last one can take from the interior.
+        if placement.no_edge_symbols && password_chars.len() > 2 {
+            let last = password_chars.len() - 1;
+            for edge in [0, last] {
+                if !is_symbol(password_chars[edge]) {
+                    continue;
+                }
+                let candidates: Vec<usize> = (1..last)
+                    .filter(|i| !is_symbol(password_chars[*i]))
+                    .collect();
+                if let Some(i) = candidates.choose(rng) {
+                    password_chars.swap(edge, *i);
+                }
+            }
+        }
     }
 
-    fn create_one_password(&self, rng: &mut impl Rng) -> String {
-        let mut password_chars: Vec<char> = Vec::with_capacity(self.length);
+    // A character from `chars` that is not in `used`, falling back to any unused character
+    // of the final charset when overlapping sets have taken them all.
+    fn choose_unused(&self, chars: &[char], used: &[char], rng: &mut (impl Rng + ?Sized)) -> char {
+        let unused = |set: &[char]| -> Vec<char> {
+            set.iter().filter(|c| !used.contains(c)).cloned().collect()
+        };
 
-        // Ensure at least one character from each required set.
-        for req_set in &self.required_sets {
-            password_chars.push(*req_set.choose(rng).unwrap());
+        let mut candidates = unused(chars);
+        if candidates.is_empty() {
+            candidates = unused(&self.final_charset);
         }
+        *candidates.choose(rng).unwrap()
+    }
+}
 
-        // Fill the rest of the password with characters from the final character set.
-        for _ in 0..(self.length.saturating_sub(password_chars.len())) {
-            password_chars.push(*self.final_charset.choose(rng).unwrap());
+/// An unbounded iterator of passwords, created by [`PasswordGenerator::iter`].
+pub struct PasswordIter<'a, R: ?Sized> {
+    generator: &'a PasswordGenerator,
+    rng: &'a mut R,
+    // Keyed hashes of the passwords yielded so far, when they must be unique. Storing hashes
+    // keeps memory flat for long passwords; a rare false collision only costs a retry.
+    seen: Option<HashSet<u64>>,
+    hasher: RandomState,
+}
+
+impl<R: SecureRng + ?Sized> Iterator for PasswordIter<'_, R> {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(seen) = &mut self.seen else {
+            return Some(self.generator.next_password(self.rng));
+        };
+
+        let attempts = self.generator.filters.max_attempts;
+        for _ in 0..attempts {
+            match self.generator.next_password(self.rng) {
+                Ok(mut password) => {
+                    if seen.insert(self.hasher.hash_one(&password)) {
+                        return Some(Ok(password));
+                    }
+                    self.generator.rejected.fetch_add(1, Ordering::Relaxed);
+                    password.zeroize();
+                }
+                Err(err) => return Some(Err(err)),
+            }
         }
+        Some(Err(Error::AttemptsExhausted {
+            attempts,
+            constraint: "uniqueness within the batch".to_string(),
+        }))
+    }
+}
 
-        password_chars.shuffle(rng);
+/// Generate passwords using the operating system's CSPRNG.
+///
+/// The passwords are not wiped when the vector is dropped. Call
+/// [`zeroize::Zeroize::zeroize`] on it once they are no longer needed, or wrap it in
+/// [`zeroize::Zeroizing`].
+pub fn generate(config: &GeneratorConfig) -> Result<Vec<String>, Error> {
+    generate_with_rng(config, &mut OsRng)
+}
+
+/// Generate passwords with the given random number generator.
+///
+/// Passing a seeded generator (e.g. `StdRng::seed_from_u64`) makes the output
+/// reproducible, since the charset is kept in sorted order.
+pub fn generate_with_rng<R: SecureRng + ?Sized>(
+    config: &GeneratorConfig,
+    rng: &mut R,
+) -> Result<Vec<String>, Error> {
+    let generator = PasswordGenerator::new(config)?;
+
+    generator.batch(rng).collect()
+}
 
-        password_chars.into_iter().collect()
+/// Counters describing a batch written by [`generate_passwords`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationStats {
+    /// The number of passwords produced.
+    pub count: usize,
+    /// The shortest configured length.
+    pub min_length: usize,
+    /// The longest configured length, equal to `min_length` unless lengths vary.
+    pub max_length: usize,
+    /// The number of distinct characters a password may contain, 0 for UUIDs.
+    pub charset_size: usize,
+    /// The theoretical entropy of each password.
+    pub entropy: output::Entropy,
+    /// Whether checks such as `min_score` or `unique` could discard candidates.
+    pub rejects_candidates: bool,
+    /// The number of candidates those checks discarded.
+    pub rejected: usize,
+}
+
+impl GenerationStats {
+    /// The average number of discarded candidates per password produced.
+    pub fn average_retries(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.rejected as f64 / self.count as f64
+        }
     }
 
-    fn println(&mut self, s: &str) -> io::Result<()> {
-        writeln!(self.output, "{}", s)
+    /// Add the counters of another batch with the same configuration.
+    pub fn combine(&mut self, other: &GenerationStats) {
+        self.count += other.count;
+        self.rejected += other.rejected;
     }
 }
 
-/// Generate passwords and print them to stdout.
-pub fn generate_passwords(config: GeneratorConfig) -> Result<(), Box<dyn std::error::Error>> {
-    let mut generator = PasswordGenerator::new(config)?;
-    let mut rng = thread_rng();
+/// Generate passwords using the operating system's CSPRNG and write them to `output`
+/// in the format described by `options`.
+pub fn generate_passwords(
+    config: &GeneratorConfig,
+    options: &OutputOptions,
+    output: &mut dyn Write,
+) -> Result<GenerationStats, Error> {
+    generate_passwords_with_rng(config, &mut OsRng, options, output)
+}
+
+/// Generate passwords with the given random number generator and write them to `output`
+/// in the format described by `options`, without holding the whole batch in memory.
+pub fn generate_passwords_with_rng<R: SecureRng + ?Sized>(
+    config: &GeneratorConfig,
+    rng: &mut R,
+    options: &OutputOptions,
+    output: &mut dyn Write,
+) -> Result<GenerationStats, Error> {
+    let generator = PasswordGenerator::new(config)?;
+    let batch = output::Batch {
+        categories: generator.categories().to_vec(),
+        count: generator.count(),
+        entropy: generator.entropy(),
+    };
+    let mut formatter = output::formatter(options, output, &batch, rng);
+
+    output::write_all(generator.batch(rng), &mut *formatter)?;
+
+    Ok(GenerationStats {
+        count: generator.count(),
+        min_length: generator.length(),
+        max_length: generator.max_length(),
+        charset_size: generator.charset().len(),
+        entropy: batch.entropy,
+        rejects_candidates: generator.rejects_candidates(),
+        rejected: generator.rejected(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn bits_config(bits: f64) -> GeneratorConfigBuilder {
+        GeneratorConfig::builder().use_all().bits(bits)
+    }
+
+    #[test]
+    fn bits_picks_the_shortest_length_reaching_the_target() {
+        let config = bits_config(100.0).build().unwrap();
+        let generator = PasswordGenerator::new(&config).unwrap();
+        assert!(generator.entropy_bits() >= 100.0);
+        let shorter = GeneratorConfig::builder()
+            .use_all()
+            .length(generator.length() - 1)
+            .build()
+            .unwrap();
+        assert!(PasswordGenerator::new(&shorter).unwrap().entropy_bits() < 100.0);
+    }
+
+    #[test]
+    fn bits_rejects_targets_above_the_length_limit() {
+        let config = GeneratorConfig {
+            length_limit: Some(12),
+            ..bits_config(100.0).build().unwrap()
+        };
+        assert!(PasswordGenerator::new(&config).is_err());
+
+        let config = GeneratorConfig {
+            emoji: 2,
+            ..bits_config(120.0).build().unwrap()
+        };
+        let length = PasswordGenerator::new(&config).unwrap().length();
+        let config = GeneratorConfig {
+            length_limit: Some(length),
+            ..config
+        };
+        assert_eq!(PasswordGenerator::new(&config).unwrap().length(), length);
+        let config = GeneratorConfig {
+            length_limit: Some(length - 1),
+            ..config
+        };
+        assert!(PasswordGenerator::new(&config).is_err());
+    }
+
+    #[test]
+    fn pronounceable_rejects_lengths_below_one_syllable() {
+        let pronounceable = |length, digits| GeneratorConfig {
+            length,
+            pronounceable: true,
+            use_lower: true,
+            use_upper: true,
+            use_digits: digits,
+            ..Default::default()
+        };
+        for (length, digits) in [(0, false), (1, false), (2, true)] {
+            let error = PasswordGenerator::new(&pronounceable(length, digits))
+                .err()
+                .unwrap();
+            let shortest = 2 + usize::from(digits);
+            assert_eq!(
+                error.to_string(),
+                format!("Pronounceable passwords need a length of at least {shortest}.")
+            );
+        }
+        for (length, digits) in [(2, false), (3, true)] {
+            let generator = PasswordGenerator::new(&pronounceable(length, digits)).unwrap();
+            assert!(generator.entropy_bits().is_finite());
+            assert!(generator.entropy_bits() > 0.0);
+        }
+    }
+
+    #[test]
+    fn pronounceable_passwords_avoid_consonant_clusters() {
+        let config = GeneratorConfig::builder()
+            .length_range(6, 16)
+            .count(5000)
+            .use_lower(true)
+            .use_upper(true)
+            .use_digits(true)
+            .pronounceable(true)
+            .build()
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(9);
+        let passwords = generate_with_rng(&config, &mut rng).unwrap();
+        assert_eq!(passwords.len(), 5000);
+
+        let mut seen = HashSet::new();
+        for password in &passwords {
+            let mut run = 0;
+            for c in password.chars() {
+                let c = c.to_ascii_lowercase();
+                seen.insert(c);
+                run = if CONSONANTS.contains(c) { run + 1 } else { 0 };
+                assert!(run <= 2, "{password}");
+            }
+            assert!(
+                password
+                    .chars()
+                    .any(|c| VOWELS.contains(c.to_ascii_lowercase()))
+            );
+            assert_eq!(password.chars().filter(char::is_ascii_digit).count(), 1);
+            assert_eq!(password.chars().filter(char::is_ascii_uppercase).count(), 1);
+        }
+        assert!(
+            CONSONANTS
+                .chars()
+                .chain(VOWELS.chars())
+                .all(|c| seen.contains(&c))
+        );
+    }
+
+    #[test]
+    fn seeded_passwords_have_the_length_and_one_character_per_required_set() {
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length(12)
+            .count(1000)
+            .build()
+            .unwrap();
+        let generator = PasswordGenerator::new(&config).unwrap();
+        assert_eq!(generator.required_sets().len(), 4);
+        let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(16)).unwrap();
+        assert_eq!(passwords.len(), 1000);
+        for password in &passwords {
+            assert_eq!(password.chars().count(), 12);
+            for set in generator.required_sets() {
+                assert!(
+                    password.chars().any(|c| set.chars().contains(&c)),
+                    "{password} has no {}",
+                    set.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_passwords_shuffle_the_required_characters() {
+        // With one position per class, only the shuffle decides where each class goes.
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length(4)
+            .count(400)
+            .build()
+            .unwrap();
+        let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(16)).unwrap();
+        for class in CharClass::ALL {
+            for position in 0..4 {
+                assert!(
+                    passwords.iter().any(|password| CharClass::of(
+                        password.chars().nth(position).unwrap()
+                    ) == Some(class)),
+                    "{} never at position {position}",
+                    class.name()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_seed_and_flags_always_give_the_same_passwords() {
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length(16)
+            .count(3)
+            .build()
+            .unwrap();
+        // The charset is sorted, so the seeded draws map to the same characters on every run.
+        let charset = PasswordGenerator::new(&config).unwrap().charset().to_vec();
+        assert!(charset.is_sorted());
+        let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(81)).unwrap();
+        assert_eq!(
+            passwords,
+            [")ox1:}Qx:Hr{|Six", "v6m,O-mk5!FN#,D!", "{LOHvJWK6PWxDf%T"]
+        );
+    }
+
+    #[test]
+    fn uuids_parse_as_version_4_with_the_rfc_4122_variant() {
+        let config = GeneratorConfig::builder()
+            .uuid(true)
+            .count(500)
+            .build()
+            .unwrap();
+        for uuid in generate_with_rng(&config, &mut StdRng::seed_from_u64(13)).unwrap() {
+            let groups: Vec<&str> = uuid.split('-').collect();
+            assert_eq!(
+                groups.iter().map(|group| group.len()).collect::<Vec<_>>(),
+                [8, 4, 4, 4, 12],
+                "{uuid}"
+            );
+            assert!(
+                uuid.chars().all(|c| c == '-' || HEX_LOWER.contains(c)),
+                "{uuid}"
+            );
+            let value = u128::from_str_radix(&groups.concat(), 16).unwrap();
+            assert_eq!((value >> 76) & 0xf, 4, "{uuid}");
+            assert_eq!((value >> 62) & 0b11, 0b10, "{uuid}");
+        }
+    }
+
+    #[test]
+    fn avoid_ambiguous_keeps_look_alikes_out_of_a_large_batch() {
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length(20)
+            .count(5000)
+            .avoid_ambiguous(true)
+            .build()
+            .unwrap();
+        let charset = PasswordGenerator::new(&config).unwrap().charset().to_vec();
+        assert!(!charset.iter().any(|c| AMBIGUOUS.contains(*c)));
+        let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(2)).unwrap();
+        for password in &passwords {
+            assert!(!password.contains(|c| AMBIGUOUS.contains(c)), "{password}");
+        }
+    }
+
+    #[test]
+    fn base58_never_contains_the_excluded_characters() {
+        let config = GeneratorConfig::builder()
+            .charset(Charset::base58())
+            .length(32)
+            .count(5000)
+            .build()
+            .unwrap();
+        let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(12)).unwrap();
+        let mut seen = HashSet::new();
+        for password in &passwords {
+            assert_eq!(password.len(), 32);
+            assert!(!password.contains(['0', 'O', 'I', 'l']), "{password}");
+            seen.extend(password.chars());
+        }
+        assert_eq!(seen.len(), BASE58.len());
+        assert!(
+            BASE62
+                .chars()
+                .all(|c| seen.contains(&c) || "0OIl".contains(c))
+        );
+    }
+
+    #[test]
+    fn every_iterated_password_meets_the_required_sets() {
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length_range(8, 14)
+            .min_digits(2)
+            .min_symbols(2)
+            .build()
+            .unwrap();
+        let generator = PasswordGenerator::new(&config).unwrap();
+        let mut rng = StdRng::seed_from_u64(18);
+        let passwords: Vec<String> = generator
+            .iter(&mut rng)
+            .take(3000)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(passwords.len(), 3000);
+        for password in &passwords {
+            assert!((8..=14).contains(&password.chars().count()), "{password}");
+            for set in generator.required_sets() {
+                let count = password.chars().filter(|c| set.chars().contains(c)).count();
+                assert!(count >= set.min().max(1), "{password} lacks {}", set.name());
+            }
+        }
+    }
+
+    #[test]
+    fn passwords_can_be_written_into_a_buffer() {
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length(16)
+            .count(100)
+            .build()
+            .unwrap();
+        let mut buffer = Vec::new();
+        let stats = generate_passwords_with_rng(
+            &config,
+            &mut StdRng::seed_from_u64(21),
+            &OutputOptions::default(),
+            &mut buffer,
+        )
+        .unwrap();
+        assert_eq!(stats.count, 100);
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.ends_with('\n'));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 100);
+        let charset = PasswordGenerator::new(&config).unwrap().charset().to_vec();
+        for line in lines {
+            assert_eq!(line.len(), 16);
+            assert!(line.chars().all(|c| charset.contains(&c)), "{line}");
+        }
+
+        let config = GeneratorConfig::builder()
+            .use_lower(true)
+            .use_upper(true)
+            .use_digits(true)
+            .length(12)
+            .count(50)
+            .build()
+            .unwrap();
+        let options = OutputOptions {
+            format: output::Format::Csv,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        generate_passwords_with_rng(
+            &config,
+            &mut StdRng::seed_from_u64(21),
+            &options,
+            &mut buffer,
+        )
+        .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("index,password,length"));
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), 50);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(row.len(), 3);
+            assert_eq!(row[0], (i + 1).to_string());
+            assert_eq!(row[1].len(), 12);
+            assert_eq!(row[2], "12");
+        }
+    }
+
+    #[test]
+    fn entropy_bits_is_length_times_log2_of_the_charset_size() {
+        let cases = [
+            (16, 12, 48.0),
+            (16, 20, 80.0),
+            (26, 12, 56.405_276_617_693_1),
+            (26, 20, 94.008_794_362_821_83),
+            (62, 12, 71.450_355_724_642_49),
+            (62, 20, 119.083_926_207_737_5),
+            (94, 12, 78.655_066_220_131_66),
+            (94, 20, 131.091_777_033_552_75),
+        ];
+        for (charset_size, length, bits) in cases {
+            let actual = entropy_bits(charset_size, length);
+            assert!(
+                (actual - bits).abs() < 1e-9,
+                "{charset_size} x {length}: {actual}"
+            );
+        }
+        assert_eq!(entropy_bits(0, 12), 0.0);
+        assert_eq!(entropy_bits(94, 0), 0.0);
+        assert_eq!(entropy_bits(1, 12), 0.0);
+    }
+
+    #[test]
+    fn suggested_length_reaches_the_target_for_the_standard_charsets() {
+        let charsets = [
+            (DIGITS.len(), [20, 25, 39]),
+            (HEX_LOWER.len(), [16, 20, 32]),
+            (LOWERCASE.len(), [14, 18, 28]),
+            (LOWERCASE.len() + UPPERCASE.len(), [12, 15, 23]),
+            (BASE62.len(), [11, 14, 22]),
+            (BASE62.len() + SYMBOLS.len(), [10, 13, 20]),
+            // All printable ASCII but the space.
+            (94, [10, 13, 20]),
+        ];
+        for (charset_size, lengths) in charsets {
+            for (bits, length) in [64.0, 80.0, 128.0].into_iter().zip(lengths) {
+                assert_eq!(
+                    suggested_length(bits, charset_size),
+                    Some(length),
+                    "{bits} bits from {charset_size}"
+                );
+                assert!(entropy_bits(charset_size, length) >= bits);
+                assert!(entropy_bits(charset_size, length - 1) < bits);
+            }
+        }
+        assert_eq!(suggested_length(0.0, 94), Some(0));
+        assert_eq!(suggested_length(64.0, 1), None);
+        assert_eq!(suggested_length(64.0, 0), None);
+    }
+
+    #[test]
+    fn keyboard_walks_follow_rows_and_columns() {
+        assert_eq!(longest_keyboard_walk("qwerty"), 6);
+        assert_eq!(longest_keyboard_walk("asdf"), 4);
+        assert_eq!(longest_keyboard_walk("zxcv"), 4);
+        assert_eq!(longest_keyboard_walk("1234"), 4);
+        assert_eq!(longest_keyboard_walk("1qaz"), 4);
+        // Shift and case do not break a walk.
+        assert_eq!(longest_keyboard_walk("!@#$"), 4);
+        assert_eq!(longest_keyboard_walk("QwEr"), 4);
+    }
+
+    #[test]
+    fn keyboard_walks_count_in_either_direction() {
+        assert_eq!(longest_keyboard_walk("poiuy"), 5);
+        assert_eq!(longest_keyboard_walk("LKJH"), 4);
+        assert_eq!(longest_keyboard_walk("zaq1"), 4);
+        assert_eq!(longest_keyboard_walk("0987"), 4);
+    }
+
+    #[test]
+    fn keyboard_walks_are_found_among_noise() {
+        assert_eq!(longest_keyboard_walk("p9asdf0m"), 4);
+        assert_eq!(longest_keyboard_walk("x7zxcvb"), 5);
+        assert_eq!(longest_keyboard_walk("qw9er"), 2);
+        assert_eq!(longest_keyboard_walk("qéwer"), 3);
+        assert_eq!(longest_keyboard_walk("a1p"), 1);
+        assert_eq!(longest_keyboard_walk("ü é"), 0);
+        assert_eq!(longest_keyboard_walk(""), 0);
+    }
+
+    #[test]
+    fn no_consecutive_identical_holds_across_thousands_of_samples() {
+        let all = GeneratorConfig::builder().use_all().length(16);
+        // Ten digits over 20 positions double up in most unconstrained candidates.
+        let digits = GeneratorConfig::builder().use_digits(true).length(20);
+        let hex = GeneratorConfig::builder()
+            .charset(Charset::hex(false))
+            .length_range(8, 40);
+        for (seed, builder) in [all, digits, hex].into_iter().enumerate() {
+            let config = builder
+                .count(2000)
+                .no_consecutive_identical(true)
+                .build()
+                .unwrap();
+            let passwords =
+                generate_with_rng(&config, &mut StdRng::seed_from_u64(47 + seed as u64)).unwrap();
+            assert_eq!(passwords.len(), 2000);
+            for password in &passwords {
+                let chars: Vec<char> = password.chars().collect();
+                assert!(
+                    chars.windows(2).all(|pair| pair[0] != pair[1]),
+                    "{password}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn auto_length_fits_the_per_category_minimums() {
+        let short = GeneratorConfig::builder()
+            .use_all()
+            .length(6)
+            .min_digits(5)
+            .min_symbols(4);
+        assert!(matches!(
+            short.clone().build(),
+            Err(Error::LengthTooShort {
+                length: 6,
+                required: 11
+            })
+        ));
+
+        let config = short.count(500).auto_length(true).build().unwrap();
+        let generator = PasswordGenerator::new(&config).unwrap();
+        // 5 digits, 4 symbols, and one lowercase and one uppercase letter.
+        assert_eq!(generator.length(), 11);
+        for password in generate_with_rng(&config, &mut StdRng::seed_from_u64(93)).unwrap() {
+            assert_eq!(password.len(), 11);
+            assert!(password.chars().filter(char::is_ascii_digit).count() >= 5);
+            assert!(password.chars().filter(|c| SYMBOLS.contains(*c)).count() >= 4);
+        }
+
+        // A length that already fits is kept.
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length(30)
+            .min_digits(5)
+            .auto_length(true)
+            .build()
+            .unwrap();
+        assert_eq!(PasswordGenerator::new(&config).unwrap().length(), 30);
+    }
+
+    #[test]
+    fn auto_length_reaches_the_entropy_target() {
+        let short = GeneratorConfig::builder()
+            .use_all()
+            .length(8)
+            .min_entropy(100.0);
+        assert!(matches!(
+            short.clone().build(),
+            Err(Error::InsufficientEntropy { .. })
+        ));
+
+        let generator = PasswordGenerator::new(&short.auto_length(true).build().unwrap()).unwrap();
+        assert!(generator.entropy_bits() >= 100.0);
+        let shorter = GeneratorConfig::builder()
+            .use_all()
+            .length(generator.length() - 1)
+            .build()
+            .unwrap();
+        assert!(PasswordGenerator::new(&shorter).unwrap().entropy_bits() < 100.0);
+    }
+
+    #[test]
+    fn auto_length_takes_the_larger_of_the_minimums_and_the_entropy_target() {
+        let config = |min_digits, bits| {
+            GeneratorConfig::builder()
+                .use_all()
+                .length(6)
+                .min_digits(min_digits)
+                .min_entropy(bits)
+                .auto_length(true)
+                .build()
+                .unwrap()
+        };
+
+        // 12 digits and one of each other category outweigh 40 bits.
+        let generator = PasswordGenerator::new(&config(12, 40.0)).unwrap();
+        assert_eq!(generator.length(), 15);
+        assert!(generator.entropy_bits() >= 40.0);
+
+        // 120 bits outweigh 2 digits and one of each other category.
+        let generator = PasswordGenerator::new(&config(2, 120.0)).unwrap();
+        assert!(generator.entropy_bits() >= 120.0);
+        let shorter = GeneratorConfig {
+            length: generator.length() - 1,
+            auto_length: false,
+            ..config(2, 120.0)
+        };
+        assert!(matches!(
+            PasswordGenerator::new(&shorter),
+            Err(Error::InsufficientEntropy { .. })
+        ));
+        let passwords = generate_with_rng(
+            &GeneratorConfig {
+                count: 200,
+                ..config(2, 120.0)
+            },
+            &mut StdRng::seed_from_u64(93),
+        )
+        .unwrap();
+        for password in passwords {
+            assert_eq!(password.len(), generator.length());
+            assert!(password.chars().filter(char::is_ascii_digit).count() >= 2);
+        }
+    }
+
+    #[test]
+    fn uniform_and_default_sampling_differ_in_symbol_counts() {
+        // The share of 8-character passwords over all four categories holding 0 to 5 symbols.
+        // The default injects one symbol and draws the other 4 characters from all 88, so
+        // the count is 1 + Binomial(4, 26/88). Uniform sampling weighs every valid
+        // password equally, which gives the share of the valid passwords with each count.
+        let default_shares = [0.0, 0.2464, 0.4133, 0.2600, 0.0727, 0.0076];
+        let uniform_shares = [0.0, 0.2814, 0.3623, 0.2483, 0.0925, 0.0155];
+        let samples = 20_000;
+
+        let symbol_shares = |uniform| {
+            let config = GeneratorConfig::builder()
+                .use_all()
+                .length(8)
+                .count(samples)
+                .uniform(uniform)
+                .build()
+                .unwrap();
+            let mut counts = [0usize; 9];
+            for password in generate_with_rng(&config, &mut StdRng::seed_from_u64(94)).unwrap() {
+                counts[password.chars().filter(|c| SYMBOLS.contains(*c)).count()] += 1;
+            }
+            assert_eq!(counts[0] + counts[6..].iter().sum::<usize>(), 0);
+            counts.map(|count| count as f64 / samples as f64)
+        };
+
+        for (uniform, expected) in [(false, default_shares), (true, uniform_shares)] {
+            let shares = symbol_shares(uniform);
+            for (symbols, share) in expected.iter().enumerate() {
+                assert!(
+                    (shares[symbols] - share).abs() < 0.015,
+                    "uniform {uniform}: {} of the passwords have {symbols} symbols, expected {share}",
+                    shares[symbols]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn leet_probability_sets_the_share_of_replaced_letters() {
+        let passwords = |probability| {
+            let config = GeneratorConfig::builder()
+                .use_lower(true)
+                .use_digits(true)
+                .length(20)
+                .count(2000)
+                .leet(Leet {
+                    probability,
+                    ..Default::default()
+                })
+                .build()
+                .unwrap();
+            generate_with_rng(&config, &mut StdRng::seed_from_u64(76)).unwrap()
+        };
+        // The share of characters that are letters with a substitute.
+        let eligible_share = |passwords: &[String]| {
+            let eligible = passwords
+                .iter()
+                .flat_map(|password| password.chars())
+                .filter(|c| "aeiost".contains(*c))
+                .count();
+            eligible as f64 / (passwords.len() * 20) as f64
+        };
+
+        // One required letter is 6/26 eligible and the 18 other characters 6/36.
+        let expected = (6.0 / 26.0 + 18.0 * 6.0 / 36.0) / 20.0;
+        let kept = eligible_share(&passwords(0.0));
+        assert!((kept - expected).abs() < 0.01, "{kept}");
+        assert_eq!(eligible_share(&passwords(1.0)), 0.0);
+        let half = eligible_share(&passwords(0.5));
+        assert!((half - expected / 2.0).abs() < 0.01, "{half}");
+
+        // The substitutes take the place of the letters.
+        let digits = |passwords: &[String]| {
+            passwords
+                .iter()
+                .flat_map(|password| password.chars())
+                .filter(|c| "013457".contains(*c))
+                .count()
+        };
+        assert!(digits(&passwords(1.0)) > digits(&passwords(0.0)));
+        assert_eq!(passwords(0.5), passwords(0.5));
+    }
+
+    #[test]
+    fn multi_byte_characters_count_as_one_character_each() {
+        // One to four bytes per character.
+        let chars = "a§€😀";
+        let config = GeneratorConfig::builder()
+            .custom_chars(chars)
+            .length_range(10, 12)
+            .count(500)
+            .build()
+            .unwrap();
+        let generator = PasswordGenerator::new(&config).unwrap();
+        assert_eq!(generator.charset().len(), 4);
+        assert!((generator.entropy_bits() - 20.0).abs() < 1e-9);
+
+        let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(57)).unwrap();
+        let mut lengths = BTreeSet::new();
+        for password in &passwords {
+            let length = password.chars().count();
+            assert!((10..=12).contains(&length), "{password}");
+            assert!(password.len() > length);
+            assert!(password.chars().all(|c| chars.contains(c)));
+            lengths.insert(length);
+        }
+        assert_eq!(lengths, (10..=12).collect());
+
+        // Groups split between characters, never inside one.
+        let config = GeneratorConfig {
+            length: 9,
+            length_max: None,
+            count: 20,
+            exclude_chars: Some("€".to_string()),
+            ..config
+        };
+        let options = OutputOptions {
+            group: Some(3),
+            group_separator: "·".to_string(),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        generate_passwords_with_rng(
+            &config,
+            &mut StdRng::seed_from_u64(57),
+            &options,
+            &mut buffer,
+        )
+        .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(text.lines().count(), 20);
+        for line in text.lines() {
+            let groups: Vec<&str> = line.split('·').collect();
+            assert_eq!(groups.len(), 3, "{line}");
+            for group in groups {
+                assert_eq!(group.chars().count(), 3, "{line}");
+                assert!(group.chars().all(|c| "a§😀".contains(c)), "{line}");
+            }
+        }
+    }
+
+    // Counts the newline-terminated records written to it, and checks each has `length`
+    // characters, without keeping them.
+    struct RecordSink {
+        length: usize,
+        records: usize,
+        current: usize,
+    }
+
+    impl Write for RecordSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for byte in buf {
+                if *byte == b'\n' {
+                    assert_eq!(self.current, self.length, "record {}", self.records);
+                    self.records += 1;
+                    self.current = 0;
+                } else {
+                    self.current += 1;
+                }
+            }
+            Ok(buf.len())
+        }
 
-    for _ in 0..generator.count {
-        let password = generator.create_one_password(&mut rng);
-        generator.println(&password)?;
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn large_batches_write_exactly_the_configured_records() {
+        // Several rounds of chunks with 4 jobs, the last one split unevenly.
+        let count = 100_003;
+        for jobs in [1, 4] {
+            let config = GeneratorConfig::builder()
+                .use_all()
+                .length(12)
+                .count(count)
+                .jobs(jobs)
+                .build()
+                .unwrap();
+            let mut sink = RecordSink {
+                length: 12,
+                records: 0,
+                current: 0,
+            };
+            let stats = generate_passwords_with_rng(
+                &config,
+                &mut StdRng::seed_from_u64(72),
+                &OutputOptions::default(),
+                &mut sink,
+            )
+            .unwrap();
+            assert_eq!(stats.count, count);
+            assert_eq!(sink.records, count, "{jobs} jobs");
+            assert_eq!(sink.current, 0, "an unterminated record with {jobs} jobs");
+        }
     }
-    Ok(())
 }
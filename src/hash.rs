@@ -0,0 +1,75 @@
+//! Digests of generated passwords for `--hash`, e.g. to seed a user database alongside the
+//! plaintext. The hash functions are only built with the `hash` cargo feature.
+
+use clap::ValueEnum;
+
+/// The digest written next to each password.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    Sha1,
+}
+
+impl HashAlgorithm {
+    /// The name used on the command line and as the JSON and CSV field, e.g. `sha256`.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Sha1 => "sha1",
+        }
+    }
+
+    /// The lowercase hex digest of `bytes`.
+    #[cfg(feature = "hash")]
+    pub fn digest(self, bytes: &[u8]) -> String {
+        use sha2::{Digest, Sha256, Sha512};
+
+        match self {
+            HashAlgorithm::Sha256 => hex(&Sha256::digest(bytes)),
+            HashAlgorithm::Sha512 => hex(&Sha512::digest(bytes)),
+            HashAlgorithm::Sha1 => sha1_smol::Sha1::from(bytes).digest().to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "hash")]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(all(test, feature = "hash"))]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+    use crate::generator::{GeneratorConfig, generate_with_rng};
+
+    #[test]
+    fn seeded_passwords_have_known_digests() {
+        let config = GeneratorConfig::builder()
+            .use_all()
+            .length(16)
+            .build()
+            .unwrap();
+        let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(81)).unwrap();
+        assert_eq!(passwords, [")ox1:}Qx:Hr{|Six"]);
+
+        // The expected digests are those of Python's hashlib.
+        let password = passwords[0].as_bytes();
+        assert_eq!(
+            HashAlgorithm::Sha256.digest(password),
+            "9cc44f382623d037c343a5b534af6dd0b7729260386c4af521c490c62d12512c"
+        );
+        assert_eq!(
+            HashAlgorithm::Sha512.digest(password),
+            "f5693444fed2f5a740f314c14d501dc79a017c4e97adb466b0a49b63dcc6a59e1a38ef502b445f4dad1711b830df2690508fcf9ab6aed76780380d1d5d68bc42"
+        );
+        assert_eq!(
+            HashAlgorithm::Sha1.digest(password),
+            "682429d7431d555f0fea478cc97986e8bda1391d"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Credentials for HTTP basic authentication in the htpasswd format that Apache and nginx
+//! read, one `USER:HASH` line per user.
+
+use std::fs;
+
+use clap::ValueEnum;
+
+use crate::generator::Error;
+use crate::password_hash::{Algorithm, bcrypt};
+
+/// The hash schemes htpasswd files support.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Scheme {
+    /// bcrypt, as 'htpasswd -B' writes it.
+    Bcrypt,
+    /// Apache's MD5-based crypt, for servers without bcrypt.
+    Apr1,
+    /// Unsalted SHA-1, for the oldest servers only.
+    Sha,
+}
+
+impl Scheme {
+    /// The hash algorithm, with `cost` for bcrypt. `{SHA}` needs the `hash` feature.
+    pub fn algorithm(self, cost: u32) -> Result<Algorithm, Error> {
+        match self {
+            // Apache's own htpasswd writes '$2y$', which older readers expect.
+            Scheme::Bcrypt => Ok(Algorithm::Bcrypt {
+                cost,
+                version: bcrypt::Version::Y,
+            }),
+            Scheme::Apr1 => Ok(Algorithm::Apr1),
+            #[cfg(feature = "hash")]
+            Scheme::Sha => Ok(Algorithm::Sha1),
+            #[cfg(not(feature = "hash"))]
+            Scheme::Sha => Err(Error::InvalidConfig(
+                "The {SHA} htpasswd scheme requires building with the 'hash' feature.".to_string(),
+            )),
+        }
+    }
+}
+
+/// Check that `user` can be written to an htpasswd line.
+pub fn check_user(user: &str) -> Result<(), Error> {
+    if user.is_empty() {
+        return Err(Error::InvalidConfig(
+            "An htpasswd user name is empty.".into(),
+        ));
+    }
+    if user.contains(':') || user.chars().any(char::is_control) {
+        return Err(Error::InvalidConfig(format!(
+            "The htpasswd user name '{}' contains a colon or control character.",
+            user.escape_default()
+        )));
+    }
+    Ok(())
+}
+
+/// The htpasswd line of `user` with `hash`.
+pub fn line(user: &str, hash: &str) -> String {
+    format!("{}:{}", user, hash)
+}
+
+/// Read htpasswd user names, one per line.
+///
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_users(path: &str) -> Result<Vec<String>, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| Error::InvalidConfig(format!("Failed to read users '{}': {}", path, err)))?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|user| !user.is_empty() && !user.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashed_line(scheme: Scheme, cost: u32, salt: &[u8]) -> String {
+        let algorithm = scheme.algorithm(cost).unwrap();
+        assert_eq!(salt.len(), algorithm.salt_len());
+        line("alice", &algorithm.hash(b"password", salt))
+    }
+
+    // The expected lines are those of 'openssl passwd -apr1' and crypt(3).
+    #[test]
+    fn lines_match_known_answers() {
+        assert_eq!(
+            hashed_line(Scheme::Apr1, 0, &[0, 1, 63, 64, 255, 12, 37, 38]),
+            "alice:$apr1$./z.zAZa$Biq58uxssybha03YtsDdt0"
+        );
+        assert_eq!(
+            hashed_line(
+                Scheme::Bcrypt,
+                4,
+                &[
+                    113, 215, 159, 130, 24, 163, 146, 89, 167, 162, 154, 171, 178, 219, 175, 195
+                ]
+            ),
+            "alice:$2y$04$abcdefghijklmnopqrstuughE8Ev8uGFaUgY2cNEySvxngrb/Jzdm"
+        );
+    }
+
+    // The expected line is that of 'htpasswd -bns'.
+    #[cfg(feature = "hash")]
+    #[test]
+    fn sha_lines_match_htpasswd() {
+        assert_eq!(
+            hashed_line(Scheme::Sha, 0, &[]),
+            "alice:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g="
+        );
+    }
+
+    #[cfg(not(feature = "hash"))]
+    #[test]
+    fn sha_lines_need_the_hash_feature() {
+        assert!(Scheme::Sha.algorithm(0).is_err());
+    }
+
+    #[test]
+    fn check_user_rejects_unwritable_names() {
+        assert!(check_user("alice").is_ok());
+        for user in ["", "al:ice", "al\nice", "al\tice"] {
+            assert!(check_user(user).is_err(), "{user:?}");
+        }
+    }
+}
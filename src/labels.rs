@@ -0,0 +1,49 @@
+//! Labels that `--labels-file` pairs with the passwords, one per password in file order.
+
+use std::collections::HashSet;
+use std::fs;
+
+use crate::generator::Error;
+
+/// Read labels, one per line.
+///
+/// Every line is a label, so an empty line or one with a tab is an error rather than being
+/// skipped, which would shift the pairing of every label after it.
+pub fn read_labels(path: &str) -> Result<Vec<String>, Error> {
+    let content = fs::read_to_string(path).map_err(|err| {
+        Error::InvalidConfig(format!("Failed to read labels '{}': {}", path, err))
+    })?;
+
+    let mut labels = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let label = line.trim();
+        if label.is_empty() {
+            return Err(Error::InvalidConfig(format!(
+                "{}:{}: The label is empty.",
+                path,
+                i + 1
+            )));
+        }
+        if label.contains('\t') {
+            return Err(Error::InvalidConfig(format!(
+                "{}:{}: The label contains a tab.",
+                path,
+                i + 1
+            )));
+        }
+        labels.push(label.to_string());
+    }
+    Ok(labels)
+}
+
+/// The labels that appear more than once, in the order of their first repeat.
+pub fn duplicates(labels: &[String]) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    let mut repeated = Vec::new();
+    for label in labels {
+        if !seen.insert(label.as_str()) && !repeated.contains(&label.as_str()) {
+            repeated.push(label.as_str());
+        }
+    }
+    repeated
+}
@@ -0,0 +1,33 @@
+//! A password generator library backing the `pwgen` command.
+
+#[cfg(feature = "bip39")]
+pub mod bip39;
+pub mod check;
+pub mod clipboard;
+pub mod completions;
+pub mod config;
+pub mod generator;
+pub mod hash;
+pub mod htpasswd;
+pub mod labels;
+pub mod manpage;
+pub mod output;
+pub mod passphrase;
+pub mod password_hash;
+pub mod phonetic;
+pub mod policy;
+pub mod preset;
+pub mod pwned;
+pub mod sequence;
+pub mod strength;
+pub mod validation;
+#[cfg(feature = "common-words")]
+pub mod words;
+
+// Re-exported for wiping the passwords returned by `generate`.
+pub use zeroize;
+
+pub use generator::{
+    Error, GenerationStats, GeneratorConfig, GeneratorConfigBuilder, PasswordGenerator,
+    PasswordIter, generate, generate_passwords, generate_passwords_with_rng, generate_with_rng,
+};
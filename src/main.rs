@@ -1,18 +1,97 @@
-mod generator;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+#[cfg(feature = "bip39")]
+use pwgen_command::bip39;
+use pwgen_command::output::note;
+use pwgen_command::{
+    check, clipboard, completions, config, generator, hash, htpasswd, labels, manpage, output,
+    passphrase, password_hash, policy, preset, pwned, strength, validation,
+};
+use rand::SeedableRng;
+use rand::rngs::{OsRng, StdRng};
+use zeroize::Zeroize;
 
-use clap::Parser;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
-use std::fs::File;
-use std::io::{self, BufWriter, Write};
+/// The source of randomness used for generation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum RngSource {
+    /// The operating system's CSPRNG (getrandom).
+    Os,
+    /// A thread-local CSPRNG (ChaCha12) periodically reseeded from the operating system.
+    Thread,
+    /// A deterministic CSPRNG (ChaCha12) seeded from --seed.
+    Seeded,
+}
+
+impl RngSource {
+    fn description(self) -> &'static str {
+        match self {
+            RngSource::Os => "os (operating system CSPRNG)",
+            RngSource::Thread => "thread (thread-local ChaCha12, reseeded from the OS)",
+            RngSource::Seeded => "seeded (deterministic ChaCha12, NOT suitable for real passwords)",
+        }
+    }
+}
+
+/// When to color the passwords.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    /// Color when writing to a terminal and NO_COLOR is not set.
+    Auto,
+    /// Always color, even in files and pipes.
+    Always,
+    /// Never color.
+    Never,
+}
 
 /// A command-line password generator.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(group(
+    ArgGroup::new("mode")
+        .args(["pattern", "pronounceable", "memorable", "pin", "passphrase", "bip39", "hex", "base58", "base62", "emoji_only", "uuid"])
+        .multiple(false)
+))]
+#[command(group(
+    ArgGroup::new("destination")
+        .args(["output", "output_dir"])
+        .multiple(false)
+))]
+#[command(group(
+    ArgGroup::new("hashing")
+        .args(["hash_password", "crypt", "htpasswd", "htpasswd_users"])
+        .multiple(false)
+))]
+#[command(group(
+    ArgGroup::new("htpasswd_source")
+        .args(["htpasswd", "htpasswd_users"])
+        .multiple(false)
+))]
+#[command(group(
+    ArgGroup::new("categories")
+        .args(["all", "alnum", "letters", "chars", "chars_file", "chars_optional", "use_lower", "use_upper", "use_digits", "use_symbols"])
+        .multiple(true)
+))]
+#[command(group(
+    ArgGroup::new("composition")
+        .args([
+            "min_lower", "min_upper", "min_digits", "min_symbols",
+            "max_lower", "max_upper", "max_digits", "max_symbols",
+        ])
+        .multiple(true)
+))]
 pub struct Args {
     /// Include all default character categories: lowercase, uppercase, digits, and symbols.
     #[arg(
         short = 'a',
         long,
+        global = true,
         action = clap::ArgAction::SetTrue,
         help = "Include all default character categories:\nlowercase, uppercase, digits, and symbols."
     )]
@@ -23,133 +102,3770 @@ pub struct Args {
         short = 'c',
         long,
         value_name = "CHARS",
-        help = "Specify an additional set of characters to include in the password.",
-        value_parser = parse_chars
+        help = "Specify an additional set of characters to include in the password, with ranges like a-f0-9 and escapes \\xNN, \\s (space), \\\\, and \\-. Repeat to combine sets; at least one character of the combined set appears. Use - to read the set from stdin.",
+        action = clap::ArgAction::Append,
+        value_parser = validation::parse_chars
+    )]
+    chars: Vec<String>,
+
+    /// Read an additional set of characters from FILE, or from stdin if FILE is '-'.
+    /// Line breaks are removed, each line uses the syntax of --chars, and the result is
+    /// combined with any --chars values.
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    chars_file: Option<String>,
+
+    /// Specify characters that may appear in the password without being required.
+    #[arg(
+        long,
+        value_name = "CHARS",
+        help = "Specify characters that may appear in the password without being required. Unlike --chars, the selected categories are kept and none of these characters is guaranteed.",
+        action = clap::ArgAction::Append,
+        value_parser = validation::parse_chars
+    )]
+    chars_optional: Vec<String>,
+
+    /// Use a named character set instead of the category flags: alnum (one of each letter case
+    /// and a digit), base32, base58, base62, hex, or printable. See --list-presets.
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = [
+            "categories", "composition", "symbols_set", "pin", "hex", "base58", "base62", "emoji_only", "uuid",
+            "passphrase", "bip39", "pronounceable", "memorable",
+        ]
+    )]
+    preset: Option<String>,
+
+    /// Print the resolved charset, its size, the required sets, and the entropy at the
+    /// configured length, then exit without generating passwords.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["passphrase", "bip39"])]
+    show_charset: bool,
+
+    /// Print the available presets and their characters, then exit.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    list_presets: bool,
+
+    /// Meet a named set of requirements: legacy8, nist, or pci. The policy raises the default
+    /// length and category minimums, and options that would weaken it are rejected. Also
+    /// applies to 'check'. See --list-policies.
+    #[arg(long, global = true, value_name = "NAME")]
+    policy: Option<String>,
+
+    /// Print the available policies and their rules, then exit.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    list_policies: bool,
+
+    /// Ask for the length, categories, count, and clipboard use on the terminal, with the
+    /// other options as defaults, then offer to save the passwords to a file.
+    #[arg(
+        short = 'i',
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = [
+            "pin", "hex", "base58", "base62", "emoji_only", "uuid", "passphrase", "bip39", "pronounceable", "memorable",
+            "pattern", "preset", "output", "output_dir", "show_charset", "list_presets"
+        ]
+    )]
+    interactive: bool,
+
+    /// Replace the built-in symbols with CHARS, using the same syntax as --chars.
+    /// Unlike --chars, they form the symbols category, so --min-symbols and --max-symbols apply.
+    #[arg(
+        long,
+        value_name = "CHARS",
+        value_parser = validation::parse_chars,
+        conflicts_with_all = ["pin", "hex", "base58", "base62", "emoji_only", "uuid", "passphrase", "bip39", "pronounceable"]
+    )]
+    symbols_set: Option<String>,
+
+    /// Allow non-ASCII characters such as §µ±€ in --chars, --chars-optional, and --symbols-set.
+    /// --length counts characters, not bytes.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    unicode: bool,
+
+    /// Specify a set of characters to remove from the password's character pool.
+    #[arg(
+        short = 'e',
+        long,
+        global = true,
+        value_name = "CHARS",
+        help = "Specify a set of characters to remove from the password's character pool.",
+        value_parser = validation::parse_exclude
     )]
-    chars: Option<String>,
+    exclude: Option<String>,
+
+    /// Remove visually ambiguous characters (0 O o 1 l I | 5 S 2 Z) from the built-in sets.
+    #[arg(short = 'B', long, global = true, action = clap::ArgAction::SetTrue)]
+    avoid_ambiguous: bool,
 
-    /// The number of passwords to generate.
-    #[arg(short = 'C', long, default_value_t = 1)]
+    /// The number of passwords to generate, optionally with a 'k' or 'm' suffix for thousands
+    /// or millions, e.g. 10k. 0 only checks the options and prints a summary of what they
+    /// generate to stderr, failing like a real run would.
+    #[arg(
+        short = 'C',
+        long,
+        default_value = "1",
+        value_parser = validation::parse_count
+    )]
     count: usize,
 
-    /// The total length of the password to be generated.
-    #[arg(short = 'L', long, default_value_t = 8, value_parser = parse_length)]
-    length: usize,
+    /// The largest --count accepted, which guards against a mistyped count starting a run
+    /// that never ends.
+    #[arg(
+        long,
+        value_name = "COUNT",
+        default_value_t = validation::DEFAULT_MAX_COUNT,
+        value_parser = validation::parse_count
+    )]
+    max_count: usize,
+
+    /// Print a summary of the batch to stderr afterwards: the count, lengths, charset size,
+    /// entropy, and how many candidates checks such as --min-score rejected.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["passphrase", "bip39"])]
+    stats: bool,
+
+    /// Generate large batches on N threads, or one per CPU with 0. The passwords are written
+    /// in a fixed order, but differ from a single-threaded run with the same --seed.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        conflicts_with_all = ["passphrase", "bip39"]
+    )]
+    jobs: usize,
+
+    /// The total length of the password to be generated, or a range like 12-16 to vary it.
+    /// At least 6, unless --allow-short is given, and at most --max-length.
+    #[arg(
+        short = 'L',
+        long,
+        global = true,
+        default_value = "8",
+        value_parser = |s: &str| validation::parse_length_within(s, 1, usize::MAX)
+    )]
+    length: generator::LengthRange,
+
+    /// The longest --length accepted, which guards against a mistyped length.
+    #[arg(
+        long,
+        value_name = "N",
+        global = true,
+        default_value_t = validation::MAX_LENGTH
+    )]
+    max_length: usize,
+
+    /// Allow lengths below 6, e.g. for one-time codes or test fixtures. A warning shows the
+    /// entropy of such short passwords.
+    #[arg(long, global = true, action = clap::ArgAction::SetTrue)]
+    allow_short: bool,
+
+    /// Include lowercase letters, uppercase letters, and digits, but no symbols.
+    /// The same as -lud.
+    #[arg(
+        short = 'A',
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["all", "use_symbols", "min_symbols"]
+    )]
+    alnum: bool,
+
+    /// Include lowercase and uppercase letters only, for keyboards without digits or
+    /// symbols. The same as -lu. Each letter adds only 5.7 bits, so use a longer --length,
+    /// e.g. 16 for about 91 bits.
+    #[arg(
+        short = 'w',
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["all", "alnum", "use_digits", "use_symbols", "min_digits", "min_symbols"]
+    )]
+    letters: bool,
 
     /// Include lowercase letters (a-z) in the password.
-    #[arg(short = 'l', long, action = clap::ArgAction::SetTrue)]
+    #[arg(short = 'l', long, global = true, action = clap::ArgAction::SetTrue)]
     use_lower: bool,
 
     /// Include uppercase letters (A-Z) in the password.
-    #[arg(short = 'u', long, action = clap::ArgAction::SetTrue)]
+    #[arg(short = 'u', long, global = true, action = clap::ArgAction::SetTrue)]
     use_upper: bool,
 
     /// Include digits (0-9) in the password.
-    #[arg(short = 'd', long, action = clap::ArgAction::SetTrue)]
+    #[arg(short = 'd', long, global = true, action = clap::ArgAction::SetTrue)]
     use_digits: bool,
 
     /// Include symbols or special characters (e.g., !@#) in the password.
-    #[arg(short = 's', long, action = clap::ArgAction::SetTrue)]
+    #[arg(short = 's', long, global = true, action = clap::ArgAction::SetTrue)]
     use_symbols: bool,
 
-    /// The output file path. If not specified, output to stdout.
-    #[arg(short = 'o', long, value_name = "FILE")]
-    output: Option<String>,
-}
+    /// Leave out lowercase letters, even with --all or the default categories.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["use_lower", "min_lower", "preset"])]
+    no_lower: bool,
 
-fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
-    }
-}
+    /// Leave out uppercase letters, even with --all or the default categories.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["use_upper", "min_upper", "preset"])]
+    no_upper: bool,
 
-// Validate length option
-fn parse_length(s: &str) -> Result<usize, String> {
-    const MIN_LENGTH: usize = 6;
+    /// Leave out digits, even with --all or the default categories.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["use_digits", "min_digits", "preset", "pin"])]
+    no_digits: bool,
 
-    let length: usize = s
-        .parse()
-        .map_err(|_| format!("'{}' is not a valid number.", s))?;
+    /// Leave out symbols, even with --all or the default categories.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["use_symbols", "min_symbols", "preset"])]
+    no_symbols: bool,
 
-    if length < MIN_LENGTH {
-        Err(format!(
-            "The password length must be at least {}.",
-            MIN_LENGTH
-        ))
-    } else {
-        Ok(length)
-    }
-}
+    /// The minimum number of lowercase letters in the password.
+    #[arg(long, global = true, value_name = "N")]
+    min_lower: Option<usize>,
 
-// Validate chars option
-fn parse_chars(s: &str) -> Result<String, String> {
-    if s.is_empty() {
-        return Err(
-            "The custom character set cannot be empty. Please provide at least one character."
-                .to_string(),
-        );
-    }
+    /// The minimum number of uppercase letters in the password.
+    #[arg(long, global = true, value_name = "N")]
+    min_upper: Option<usize>,
 
-    if s.chars().any(|c| c.is_whitespace()) {
-        return Err("The custom character set cannot contain whitespace characters.".to_string());
-    }
+    /// The minimum number of digits in the password.
+    #[arg(long, global = true, value_name = "N")]
+    min_digits: Option<usize>,
 
-    if s.chars().any(|c| c.is_control()) {
-        return Err("The custom character set cannot contain control characters.".to_string());
-    }
+    /// The minimum number of symbols in the password.
+    #[arg(long, global = true, value_name = "N")]
+    min_symbols: Option<usize>,
 
-    if !s.is_ascii() {
-        return Err("The custom character set can only contain ASCII characters.".to_string());
-    }
+    /// The maximum number of lowercase letters in the password.
+    #[arg(long, global = true, value_name = "N")]
+    max_lower: Option<usize>,
 
-    Ok(s.to_string())
-}
+    /// The maximum number of uppercase letters in the password.
+    #[arg(long, global = true, value_name = "N")]
+    max_upper: Option<usize>,
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+    /// The maximum number of digits in the password.
+    #[arg(long, global = true, value_name = "N")]
+    max_digits: Option<usize>,
 
-    // Determine if any character type flag is explicitly set.
-    let any_flag_set = args.use_lower
-        || args.use_upper
-        || args.use_digits
-        || args.use_symbols
-        || args.chars.is_some();
+    /// The maximum number of symbols in the password.
+    #[arg(long, global = true, value_name = "N")]
+    max_symbols: Option<usize>,
 
-    // Use all categories if '--all' is specified, or if no specific category is chosen.
-    let use_all = args.all || !any_flag_set;
+    /// Generate passwords matching a mask, one class per character:
+    /// l (lowercase), u (uppercase), d (digit), s (symbol), a (alphanumeric),
+    /// x (any selected character). Prefix a character with '\' to use it literally.
+    #[arg(
+        short = 'p',
+        long,
+        value_name = "MASK",
+        value_parser = validation::parse_pattern,
+        conflicts_with_all = ["length", "composition"]
+    )]
+    pattern: Option<generator::Pattern>,
 
-    // Determine the output destination.
-    // Use a BufWriter for better performance with file I/O.
-    let output_path = args.output.clone(); // Clone for error reporting context
-    let output: Box<dyn Write> = match args.output {
-        Some(path) => {
-            let file = File::create(path)?;
-            Box::new(BufWriter::new(file))
-        }
-        None => Box::new(io::stdout()),
-    };
+    /// Generate pronounceable passwords from consonant/vowel syllables.
+    /// A digit and an uppercase letter are mixed in when those categories are enabled.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["chars", "chars_optional", "use_symbols", "composition"]
+    )]
+    pronounceable: bool,
 
-    // Build GeneratorConfig from Args
-    let config = generator::GeneratorConfig {
-        length: args.length,
-        count: args.count,
-        use_lower: args.use_lower || use_all, // If `use_all` is true, enable this category.
-        use_upper: args.use_upper || use_all,
-        use_digits: args.use_digits || use_all,
-        use_symbols: args.use_symbols || use_all,
-        custom_chars: args.chars,
-        output: output,
-    };
+    /// Generate Safari-style passwords like 'hyzvox-2Qojny-mandiq': three six-letter chunks of
+    /// consonants and vowels joined by hyphens, with one digit at a chunk edge and one
+    /// uppercase letter. The hyphens add no entropy.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["length", "categories", "composition", "chars_optional", "symbols_set"]
+    )]
+    memorable: bool,
 
-    generator::generate_passwords(config).map_err(|err| -> Box<dyn std::error::Error> {
-        if let Some(path) = output_path {
-            // Add context if the error is related to file output
-            Box::from(format!("Failed to write to file '{}': {}", path, err))
-        } else {
-            // For stdout, the original error is sufficient
-            Box::from(format!("An output error occurred: {}", err))
-        }
-    })?;
-    Ok(())
+    /// Generate numeric PINs of the given length (default 4), exempt from the minimum password length.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "4",
+        value_parser = validation::parse_pin,
+        conflicts_with_all = ["length", "categories", "avoid_ambiguous", "composition"]
+    )]
+    pin: Option<usize>,
+
+    /// Generate random hexadecimal strings (0-9, a-f).
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["categories", "composition"]
+    )]
+    hex: bool,
+
+    /// Use uppercase hexadecimal digits (A-F) with --hex.
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "hex")]
+    upper: bool,
+
+    /// Generate base58 tokens (letters and digits without 0, O, I, and l).
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["categories", "composition"]
+    )]
+    base58: bool,
+
+    /// Generate base62 tokens (letters and digits).
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["categories", "composition"]
+    )]
+    base62: bool,
+
+    /// Generate strings of emoji only, from a built-in set of 64 single-character animals,
+    /// foods, and objects. --length counts emoji, not bytes.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["categories", "composition"]
+    )]
+    emoji_only: bool,
+
+    /// Mix N emoji (default 1) into each password, from the set of --emoji-only. They count
+    /// toward --length, and the reported entropy counts their set and positions.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        value_parser = validation::parse_emoji,
+        conflicts_with_all = ["pattern", "pronounceable", "memorable", "pin", "hex", "base58", "base62", "emoji_only", "uuid", "passphrase", "bip39"]
+    )]
+    emoji: Option<usize>,
+
+    /// Generate random version 4 UUIDs.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["length", "categories", "exclude", "avoid_ambiguous", "composition"]
+    )]
+    uuid: bool,
+
+    /// Generate passphrases of random words from the EFF long wordlist instead of passwords.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = [
+            "length", "categories", "exclude", "avoid_ambiguous", "composition",
+        ]
+    )]
+    passphrase: bool,
+
+    /// Generate BIP-39 mnemonics of N words (12, 15, 18, 21, or 24) from the English wordlist,
+    /// with the checksum in the last word. Requires building with '--features bip39'.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = validation::parse_mnemonic_words,
+        conflicts_with_all = [
+            "length", "categories", "exclude", "avoid_ambiguous", "composition",
+        ]
+    )]
+    bip39: Option<usize>,
+
+    /// The number of words in each passphrase.
+    #[arg(long, value_name = "N", default_value_t = 6, requires = "passphrase")]
+    words: usize,
+
+    /// Read passphrase words from a file (one per line) instead of the embedded wordlist.
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath, requires = "passphrase")]
+    wordlist: Option<String>,
+
+    /// The embedded wordlist for passphrases. --wordlist replaces it. See --list-wordlists.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "NAME",
+        default_value_t = passphrase::Wordlist::EffLong,
+        requires = "passphrase"
+    )]
+    wordlist_name: passphrase::Wordlist,
+
+    /// Print the embedded wordlists with their sizes and bits per word, then exit.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    list_wordlists: bool,
+
+    /// The string placed between passphrase words. May be empty.
+    #[arg(
+        long,
+        value_name = "STR",
+        default_value = "-",
+        requires = "passphrase",
+        value_parser = validation::parse_separator
+    )]
+    separator: String,
+
+    /// Which passphrase words start with a capital letter.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        default_value_t = passphrase::Capitalize::None,
+        requires = "passphrase"
+    )]
+    capitalize: passphrase::Capitalize,
+
+    /// Case each passphrase word at random, after lowercasing the wordlist: 'capital' picks
+    /// lowercase or Capitalized (1 bit per word), 'word' also ALL-CAPS (about 1.58 bits),
+    /// and 'letter' cases every letter on its own (1 bit per letter). The bits are added to
+    /// the reported entropy.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "word",
+        requires = "passphrase",
+        conflicts_with = "capitalize"
+    )]
+    random_case: Option<passphrase::RandomCase>,
+
+    /// Add N random digits to each passphrase, each at a random word boundary.
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "passphrase")]
+    add_digit: usize,
+
+    /// Add N random symbols to each passphrase, each at a random word boundary. Symbols in
+    /// the separator are left out.
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "passphrase")]
+    add_symbol: usize,
+
+    /// Seed the random number generator to make the output reproducible.
+    /// WARNING: seeded output is predictable and must not be used for real passwords.
+    #[arg(long, value_name = "SEED")]
+    seed: Option<u64>,
+
+    /// The source of randomness. Defaults to 'os', or 'seeded' when --seed is given.
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    rng: Option<RngSource>,
+
+    /// Print the resolved configuration, such as the character set, the constraints, and the
+    /// random number generator, to stderr. Repeat for more detail: -vv also prints the
+    /// characters themselves and the number of rejected candidates.
+    #[arg(short = 'v', long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Don't print warnings, notes, or the progress bar that large batches get on a terminal.
+    /// Errors, prompts, and requested output like --stats are still shown.
+    #[arg(
+        short = 'q',
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with = "verbose"
+    )]
+    quiet: bool,
+
+    /// The output format.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = output::Format::Plain)]
+    format: output::Format,
+
+    /// Write TEMPLATE for each password instead of the password, e.g.
+    /// 'export APP_PASS_{index}="{password}"'. '{password}', '{index}' (the 1-based index,
+    /// also 'n' in braces), '{length}', and '{entropy}' (in bits) are filled in, and '{{' and
+    /// '}}' are literal braces. The password is inserted verbatim, even if it contains '"' or
+    /// '$', so quoting it is up to the template, e.g. with --no-symbols or --exclude.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        value_parser = validation::parse_template,
+        conflicts_with_all = [
+            "format", "numbered", "columns", "show_entropy", "strength", "group", "labels_file",
+            "hash", "hashing", "hash_only", "interactive",
+        ]
+    )]
+    template: Option<output::Template>,
+
+    /// Omit the header row from '--format csv' output, e.g. when appending to an existing file.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    no_header: bool,
+
+    /// Terminate each password with a NUL byte instead of a newline, for use with 'xargs -0'.
+    #[arg(
+        short = '0',
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["format", "no_header"]
+    )]
+    print0: bool,
+
+    /// Do not write a newline after the last password, e.g. when piping a single secret.
+    #[arg(
+        short = 'n',
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["format", "no_header"]
+    )]
+    no_newline: bool,
+
+    /// Lay plain output out in columns: 'auto' fills the terminal width, or give a number.
+    /// Defaults to 'auto' on a terminal and one per line otherwise.
+    #[arg(
+        long,
+        value_name = "auto|N",
+        value_parser = validation::parse_columns,
+        conflicts_with_all = ["format", "no_header", "print0", "no_newline"]
+    )]
+    columns: Option<output::Columns>,
+
+    /// Prefix each password with its 1-based index, e.g. '23: Xk3$...'.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["format", "no_header", "print0"]
+    )]
+    numbered: bool,
+
+    /// Show the theoretical entropy of each password in bits, e.g. 'Xk3$9vQw  (52.4 bits)'.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    show_entropy: bool,
+
+    /// Copy the first password to the clipboard instead of printing it.
+    /// Any further passwords are printed as usual.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["format", "numbered"])]
+    copy: bool,
+
+    /// Clear the clipboard after this many seconds when it still holds the copied password.
+    /// 0 leaves it in place.
+    #[arg(long, value_name = "SECONDS", default_value_t = 30, requires = "copy")]
+    clear_after: u64,
+
+    // Used by a detached copy of the command to clear the clipboard after '--clear-after'.
+    #[arg(long = "clear-clipboard", value_name = "SECONDS", hide = true)]
+    clear_clipboard: Option<u64>,
+
+    /// Also render each password as a QR code on stderr, for scanning with a phone.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    qr: bool,
+
+    /// Also spell each password out on stderr, indented below it: the NATO alphabet for
+    /// letters, e.g. 'capital X-ray' and 'x-ray', and names for digits and symbols, e.g.
+    /// 'three' and 'dollar sign'. Passwords are printed one per line.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "columns")]
+    phonetic: bool,
+
+    /// Append to the output file instead of overwriting it.
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "output")]
+    append: bool,
+
+    /// Overwrite the output file if it already exists. Without it an existing file is an error.
+    /// '--append' takes precedence and never truncates.
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "destination")]
+    force: bool,
+
+    /// Write the passwords even if they add up to more than 1 GiB, which is refused by default
+    /// as a likely mistake in --length or --count.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    force_large: bool,
+
+    /// Display passwords in groups of N characters, e.g. 'Xk3$-9vQw-7Lm!'.
+    /// The separators do not count toward --length. --copy always copies the raw password.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = validation::parse_group,
+        conflicts_with_all = ["format", "no_header"]
+    )]
+    group: Option<usize>,
+
+    /// The string placed between groups.
+    #[arg(
+        long,
+        value_name = "STR",
+        default_value = "-",
+        requires = "group",
+        value_parser = validation::parse_separator
+    )]
+    group_separator: String,
+
+    /// Write ungrouped passwords to --output and --output-dir files; only the terminal
+    /// display is grouped.
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "group")]
+    group_raw: bool,
+
+    /// Color digits and symbols to tell them apart from letters.
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Show the zxcvbn strength score (0-4) and estimated guesses of each password.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    strength: bool,
+
+    /// Write the hex digest of each password after it: a tab apart in plain output, or as a
+    /// field named after the algorithm in JSON and CSV. The digest covers the password alone,
+    /// without the terminator. Requires building with '--features hash'.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ALGORITHM",
+        conflicts_with = "interactive"
+    )]
+    hash: Option<hash::HashAlgorithm>,
+
+    /// Write a salted hash of each password for storing instead of it: a tab apart in plain
+    /// output, or as the 'password_hash' field in JSON and CSV. The salts come from the same
+    /// random number generator as the passwords, and batches are hashed on --jobs threads.
+    /// bcrypt only uses the first 72 bytes of a password.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SCHEME",
+        conflicts_with_all = ["interactive", "copy"]
+    )]
+    hash_password: Option<password_hash::Scheme>,
+
+    /// Write only the hash of each password, without the password itself.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        requires = "hashing",
+        conflicts_with_all = [
+            "format", "show_entropy", "strength", "group", "qr", "hash", "htpasswd", "htpasswd_users",
+        ]
+    )]
+    hash_only: bool,
+
+    /// The bcrypt cost, the log2 of its rounds (4-31). Each step doubles the time per hash.
+    #[arg(long, value_name = "N", default_value_t = 12, requires = "hashing")]
+    cost: u32,
+
+    /// The memory that Argon2id fills for each hash, in KiB.
+    #[arg(
+        long,
+        value_name = "KIB",
+        default_value_t = 19456,
+        requires = "hash_password"
+    )]
+    argon2_memory: u32,
+
+    /// The passes that Argon2id makes over its memory.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2,
+        requires = "hash_password"
+    )]
+    argon2_iterations: u32,
+
+    /// The lanes that Argon2id splits its memory into.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        requires = "hash_password"
+    )]
+    argon2_parallelism: u32,
+
+    /// Write the crypt(3) string of each password after it like --hash-password, e.g.
+    /// '$6$...' for /etc/shadow, kickstart files, or Ansible's user module. The 16-character
+    /// salts come from the same random number generator as the passwords.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SCHEME",
+        conflicts_with_all = ["interactive", "copy"]
+    )]
+    crypt: Option<password_hash::CryptScheme>,
+
+    /// The rounds of --crypt hashes (1000-999999999). Rounds other than the default of
+    /// 5000 are recorded in the hash.
+    #[arg(long, value_name = "N", default_value_t = 5000, requires = "crypt")]
+    rounds: u32,
+
+    /// Write an htpasswd line, 'USER:HASH', for each of these comma-separated users instead
+    /// of the passwords. The passwords go to stderr, or to --htpasswd-plaintext, as
+    /// 'USER:PASSWORD' lines to hand out. --count defaults to the number of users.
+    #[arg(
+        long,
+        value_name = "USERS",
+        value_delimiter = ',',
+        conflicts_with_all = [
+            "format", "numbered", "columns", "show_entropy", "strength", "qr", "group", "print0",
+            "no_newline", "hash", "interactive", "copy", "output_dir",
+        ]
+    )]
+    htpasswd: Vec<String>,
+
+    /// Read the --htpasswd users from FILE, one per line. Blank lines and lines starting with
+    /// '#' are skipped.
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        conflicts_with_all = [
+            "format", "numbered", "columns", "show_entropy", "strength", "qr", "group", "print0",
+            "no_newline", "hash", "interactive", "copy", "output_dir",
+        ]
+    )]
+    htpasswd_users: Option<String>,
+
+    /// The hash in htpasswd lines. 'apr1' and 'sha' are weak and only meant for servers
+    /// without bcrypt support. 'sha' requires building with '--features hash'.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SCHEME",
+        default_value_t = htpasswd::Scheme::Bcrypt,
+        requires = "htpasswd_source"
+    )]
+    htpasswd_scheme: htpasswd::Scheme,
+
+    /// Write the plaintext passwords of htpasswd users to FILE instead of stderr.
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        requires = "htpasswd_source"
+    )]
+    htpasswd_plaintext: Option<String>,
+
+    /// Pair each password with a label read from FILE, one per line, writing
+    /// 'LABEL<TAB>PASSWORD', or a 'label' field in JSON and CSV. The labels are paired in file
+    /// order, and --count defaults to the number of labels.
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        conflicts_with_all = [
+            "interactive", "copy", "output_dir", "htpasswd", "htpasswd_users", "hash_only",
+        ]
+    )]
+    labels_file: Option<String>,
+
+    /// Reject duplicate --labels-file labels instead of warning about them.
+    #[arg(long, action = clap::ArgAction::SetTrue, requires = "labels_file")]
+    strict_labels: bool,
+
+    /// Regenerate passwords until their zxcvbn strength score is at least N (0-4).
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = validation::parse_score,
+        conflicts_with_all = ["passphrase", "bip39"]
+    )]
+    min_score: Option<u8>,
+
+    /// Regenerate passwords that appear in a known breach, according to Have I Been Pwned.
+    /// Only the first 5 hex digits of each password's SHA-1 hash are sent. 'strict' fails
+    /// when the service is unreachable, 'best-effort' warns and continues without the check.
+    /// Requires building with '--features pwned'.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "strict",
+        conflicts_with_all = ["passphrase", "bip39"]
+    )]
+    check_pwned: Option<pwned::Mode>,
+
+    /// Fail unless the options give at least BITS of entropy, and suggest the length
+    /// (or number of words) that would.
+    #[arg(long, value_name = "BITS", value_parser = validation::parse_bits)]
+    min_entropy: Option<f64>,
+
+    /// Use the shortest length that gives at least BITS of entropy with the selected
+    /// characters, instead of --length.
+    #[arg(
+        long,
+        value_name = "BITS",
+        value_parser = validation::parse_bits,
+        conflicts_with_all = ["length", "pattern", "pronounceable", "memorable", "pin", "uuid", "passphrase", "bip39"]
+    )]
+    bits: Option<f64>,
+
+    /// Raise --length to what the required characters, such as --min-digits, and
+    /// --min-entropy need, instead of rejecting a length that is too short. The length used
+    /// is printed to stderr.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["bits", "pattern", "pronounceable", "memorable", "pin", "uuid", "passphrase", "bip39"]
+    )]
+    auto_length: bool,
+
+    /// Draw every character from the whole charset and regenerate passwords that miss a
+    /// required character or position rule, so each valid password is equally likely. The
+    /// default places the required characters directly, which is faster but slightly skews
+    /// how many of each category passwords hold; its reported entropy is an upper bound.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["pattern", "pronounceable", "memorable", "pin", "uuid", "passphrase", "bip39", "no_repeat"]
+    )]
+    uniform: bool,
+
+    /// Never print the same password twice in one run. Fails up front when the options
+    /// allow too few different passwords for --count.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["passphrase", "bip39"])]
+    unique: bool,
+
+    /// Regenerate passwords containing a common English word of at least MINLEN letters
+    /// (default 4), ignoring case and simple leetspeak such as '4' for 'a'.
+    #[arg(
+        long,
+        value_name = "MINLEN",
+        num_args = 0..=1,
+        default_missing_value = "4",
+        value_parser = validation::parse_word_length,
+        conflicts_with_all = ["passphrase", "bip39"]
+    )]
+    no_words: Option<usize>,
+
+    /// Use every character at most once in each password.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["pattern", "pronounceable", "memorable", "uuid", "passphrase", "bip39"]
+    )]
+    no_repeat: bool,
+
+    /// Never put two identical characters next to each other, e.g. 'pp'.
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["passphrase", "bip39"])]
+    no_consecutive_identical: bool,
+
+    /// Regenerate passwords containing an ascending or descending run of at least LEN
+    /// (default 3) letters, digits, or QWERTY keyboard keys, e.g. 'abc', '321', or 'qwe'.
+    #[arg(
+        long,
+        value_name = "LEN",
+        num_args = 0..=1,
+        default_missing_value = "3",
+        value_parser = validation::parse_run_length,
+        conflicts_with_all = ["passphrase", "bip39"]
+    )]
+    no_sequential: Option<usize>,
+
+    /// Begin each password with a letter from the enabled lowercase or uppercase set.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["pattern", "pronounceable", "memorable", "pin", "uuid", "passphrase", "bip39"]
+    )]
+    start_with_letter: bool,
+
+    /// Keep symbols, including custom ones, out of the first and last positions.
+    #[arg(
+        long,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["pattern", "pronounceable", "memorable", "pin", "uuid", "passphrase", "bip39"]
+    )]
+    no_edge_symbols: bool,
+
+    /// Regenerate passwords containing a walk of at least LEN (default 4) touching QWERTY
+    /// keys along a row, column, or diagonal, e.g. 'asdf' or '1qaz'.
+    #[arg(
+        long,
+        value_name = "LEN",
+        num_args = 0..=1,
+        default_missing_value = "4",
+        value_parser = validation::parse_run_length,
+        conflicts_with_all = ["passphrase", "bip39"]
+    )]
+    no_keyboard_walks: Option<usize>,
+
+    /// Replace letters with look-alikes after generation, e.g. a with 4 or @, using the map
+    /// MAP (default a=4@,e=3,i=1,o=0,s=5$,t=7). Substitutes outside the selected characters
+    /// are skipped, and candidates that no longer meet the category counts are regenerated.
+    /// The reported entropy is an upper bound.
+    #[arg(
+        long,
+        value_name = "MAP",
+        num_args = 0..=1,
+        default_missing_value = generator::LEET_MAP,
+        value_parser = validation::parse_leet_map,
+        conflicts_with_all = ["pattern", "pronounceable", "memorable", "pin", "uuid", "passphrase", "bip39"]
+    )]
+    leet: Option<generator::LeetMap>,
+
+    /// The chance, from 0 to 1, that --leet replaces each eligible letter.
+    #[arg(
+        long,
+        value_name = "P",
+        default_value_t = 0.5,
+        value_parser = validation::parse_probability,
+        requires = "leet"
+    )]
+    leet_probability: f64,
+
+    /// Make the characters of CLASS (lowercase, uppercase, digits, or symbols) FACTOR times
+    /// as likely to fill the password beyond the required characters as those of other
+    /// categories, e.g. 'symbols=0.2' for about one symbol in 16 with all four. Can be given
+    /// multiple times. The reported entropy accounts for the weights.
+    #[arg(
+        long,
+        value_name = "CLASS=FACTOR",
+        action = clap::ArgAction::Append,
+        value_parser = validation::parse_weight,
+        conflicts_with_all = ["pattern", "pronounceable", "memorable", "pin", "uuid", "passphrase", "bip39", "uniform", "no_repeat"]
+    )]
+    weight: Vec<(generator::CharClass, f64)>,
+
+    /// Regenerate passwords containing any term from FILE (one per line, '#' starts a
+    /// comment), ignoring case. Can be given multiple times.
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath, conflicts_with_all = ["passphrase", "bip39"])]
+    blacklist_file: Vec<String>,
+
+    /// The number of candidates tried per password before --blacklist-file, --min-score,
+    /// or --check-pwned is reported as unsatisfiable.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = generator::DEFAULT_MAX_ATTEMPTS,
+        value_parser = validation::parse_attempts
+    )]
+    max_attempts: usize,
+
+    /// The output file path. If not specified, output to stdout.
+    #[arg(short = 'o', long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+    output: Option<String>,
+
+    /// Write each password to its own file in this directory, which is created if missing.
+    #[arg(
+        long,
+        value_name = "DIR",
+        value_hint = clap::ValueHint::DirPath,
+        conflicts_with_all = ["append", "copy", "numbered", "columns"]
+    )]
+    output_dir: Option<String>,
+
+    /// The file names used with --output-dir. '{n}' is replaced by the zero-padded index.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        default_value = "{n}",
+        requires = "output_dir",
+        value_parser = validation::parse_filename_template
+    )]
+    filename_template: String,
+
+    /// Read default options from FILE instead of $XDG_CONFIG_HOME/pwgen/config.toml.
+    /// Options given on the command line take precedence.
+    #[arg(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath, global = true)]
+    config: Option<String>,
+
+    /// Ignore the config file.
+    #[arg(long, global = true, action = clap::ArgAction::SetTrue, conflicts_with = "config")]
+    no_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Generate passwords, the default when no command is given.
+    ///
+    /// Takes the same options as pwgen without a command, so `pwgen generate -L 16 -a` is
+    /// `pwgen -L 16 -a`.
+    Generate,
+
+    /// Generate passphrases, the same as --passphrase.
+    ///
+    /// `pwgen passphrase --words 5` is `pwgen --passphrase --words 5`, and takes the same
+    /// options.
+    Passphrase,
+
+    /// Analyze a password read from stdin.
+    ///
+    /// Reports its length, character categories, entropy, and strength, and exits with a
+    /// nonzero status unless it satisfies the given --length (as a minimum), category,
+    /// --min-*/--max-*, --exclude, and -B options.
+    Check,
+
+    /// Print a shell completion script.
+    ///
+    /// Load it from the shell's startup file, e.g. `source <(pwgen completions bash)`, or
+    /// save it where the shell looks for completions. Preset names, including those from the
+    /// config file, are completed for --preset.
+    Completions {
+        #[arg(value_enum)]
+        shell: completions::Shell,
+    },
+
+    /// Print the man page in roff format.
+    ///
+    /// Generated from the same definitions as --help, e.g. for packaging:
+    /// `pwgen man > pwgen.1`.
+    Man,
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// Options that only make sense on the command line.
+const CLI_ONLY: [&str; 5] = ["config", "no_config", "interactive", "help", "version"];
+
+// The prefix of the environment variables for options, e.g. `PWGEN_LENGTH`.
+const ENV_PREFIX: &str = "PWGEN_";
+
+// A default for an option from the environment or the config file.
+struct Setting {
+    id: String,
+    value: config::Value,
+    // Earlier layers win: 0 for the environment and 1 for the config file.
+    layer: usize,
+    // Where the value came from, for error messages.
+    origin: String,
+}
+
+// Where the options that were not left at their defaults got their values, by option id:
+// a flag like `--length`, a variable like `PWGEN_LENGTH`, or a config file key.
+type Origins = HashMap<String, String>;
+
+// Parse the command line and fill in the options it leaves unset, first from `PWGEN_*`
+// environment variables and then from the config file. Defaults that conflict with the
+// command line, such as a length with '--pin', are skipped.
+fn parse_args() -> Result<(ArgMatches, preset::Presets, Origins), Box<dyn std::error::Error>> {
+    parse_args_from(std::env::args_os().collect(), |name| std::env::var_os(name))
+}
+
+// `parse_args` with the given command line and environment.
+fn parse_args_from(
+    mut argv: Vec<OsString>,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Result<(ArgMatches, preset::Presets, Origins), Box<dyn std::error::Error>> {
+    let mut command = Args::command();
+    command.build();
+    expand_subcommand(&command, &mut argv);
+    let matches = command.clone().get_matches_from(&argv);
+    let mut presets = preset::Presets::builtin();
+
+    let mut origins: Origins = command
+        .get_arguments()
+        .filter(|arg| is_explicit(&matches, arg.get_id().as_str()))
+        .filter_map(|arg| {
+            let long = arg.get_long()?;
+            Some((arg.get_id().to_string(), format!("--{}", long)))
+        })
+        .collect();
+
+    let mut settings = env_settings(&command, env)?;
+    settings.extend(config_settings(&command, &matches, &mut presets)?);
+
+    // The options each default was applied to, with its layer.
+    let mut applied: Vec<(String, usize)> = Vec::new();
+    for setting in settings {
+        let decided = |id: &str| {
+            is_explicit(&matches, id)
+                || applied
+                    .iter()
+                    .any(|(applied, layer)| applied == id && *layer < setting.layer)
+        };
+        if decided(&setting.id) || in_decided_group(&command, &setting.id, decided) {
+            continue;
+        }
+
+        let arg = find_option(&command, &setting.id).expect("settings name known options");
+        let error = |message: String| format!("{}: {}", setting.origin, message);
+        let mut candidate = argv.clone();
+        candidate.splice(1..1, config_args(arg, &setting.value).map_err(error)?);
+        match command.clone().try_get_matches_from(&candidate) {
+            Ok(_) => {
+                argv = candidate;
+                origins.insert(setting.id.clone(), setting.origin);
+                applied.push((setting.id, setting.layer));
+            }
+            Err(err) if err.kind() == ErrorKind::ArgumentConflict => {}
+            Err(err) => return Err(error(clap_message(&err)).into()),
+        }
+    }
+
+    Ok((command.get_matches_from(&argv), presets, origins))
+}
+
+// Replace the subcommands that stand for options by those options, so that
+// `pwgen passphrase --words 5` is parsed exactly like `pwgen --passphrase --words 5`.
+fn expand_subcommand(command: &clap::Command, argv: &mut Vec<OsString>) {
+    let Some(i) = subcommand_index(command, argv) else {
+        return;
+    };
+    match argv[i].to_str() {
+        Some("generate") => {
+            argv.remove(i);
+        }
+        Some("passphrase") => argv[i] = "--passphrase".into(),
+        _ => {}
+    }
+}
+
+// The position of the subcommand in `argv`: the first argument that is neither an option
+// nor the value of one.
+fn subcommand_index(command: &clap::Command, argv: &[OsString]) -> Option<usize> {
+    let mut i = 1;
+    while i < argv.len() {
+        let token = argv[i].to_string_lossy();
+        // Whether the option at `i` consumes the next argument as its value, as clap does.
+        let takes_next = |arg: &clap::Arg| {
+            let next_is_option = argv
+                .get(i + 1)
+                .is_some_and(|next| next.to_string_lossy().starts_with('-'));
+            arg.get_action().takes_values()
+                && !arg.is_require_equals_set()
+                && !(takes_optional_value(arg) && next_is_option)
+        };
+
+        if token == "--" {
+            return None;
+        } else if let Some(long) = token.strip_prefix("--") {
+            if !long.contains('=')
+                && command
+                    .get_arguments()
+                    .find(|arg| arg.get_long() == Some(long))
+                    .is_some_and(takes_next)
+            {
+                i += 1;
+            }
+        } else if let Some(shorts) = token.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            // In a cluster like '-ludC5', the first option with a value takes the rest.
+            for (j, c) in shorts.char_indices() {
+                if let Some(arg) = command
+                    .get_arguments()
+                    .find(|arg| arg.get_short() == Some(c))
+                    && arg.get_action().takes_values()
+                {
+                    if j + c.len_utf8() == shorts.len() && takes_next(arg) {
+                        i += 1;
+                    }
+                    break;
+                }
+            }
+        } else {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+// The option with the given id that can be set outside of the command line.
+fn find_option<'a>(command: &'a clap::Command, id: &str) -> Option<&'a clap::Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_id() == id && arg.get_long().is_some())
+        .filter(|_| !CLI_ONLY.contains(&id))
+}
+
+// Defaults from the environment, named after the options, e.g. PWGEN_USE_SYMBOLS=true.
+fn env_settings(
+    command: &clap::Command,
+    env: impl Fn(&str) -> Option<OsString>,
+) -> Result<Vec<Setting>, Box<dyn std::error::Error>> {
+    let mut settings = Vec::new();
+    for arg in command.get_arguments() {
+        let id = arg.get_id().as_str();
+        let name = format!("{}{}", ENV_PREFIX, id.to_uppercase());
+        let Some(raw) = env(&name).filter(|raw| !raw.is_empty()) else {
+            continue;
+        };
+        if find_option(command, id).is_none() {
+            continue;
+        }
+
+        let raw = raw
+            .into_string()
+            .map_err(|_| format!("{}: The value is not valid UTF-8.", name))?;
+        let boolean = match raw.to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(true),
+            "false" | "no" | "off" | "0" => Some(false),
+            _ => None,
+        };
+        // Flags take a boolean. Options with an optional value, like '--pin', take either,
+        // but a number is always a value.
+        let value = match boolean {
+            Some(enabled) if matches!(arg.get_action(), clap::ArgAction::SetTrue) => {
+                config::Value::Boolean(enabled)
+            }
+            Some(enabled) if takes_optional_value(arg) && !raw.starts_with(['0', '1']) => {
+                config::Value::Boolean(enabled)
+            }
+            _ => config::Value::String(raw),
+        };
+        settings.push(Setting {
+            id: id.to_string(),
+            value,
+            layer: 0,
+            origin: name,
+        });
+    }
+    Ok(settings)
+}
+
+// Defaults from the config file, whose keys are the option names with underscores, e.g.
+// `use_symbols = true`. A `[presets]` table defines presets.
+fn config_settings(
+    command: &clap::Command,
+    matches: &ArgMatches,
+    presets: &mut preset::Presets,
+) -> Result<Vec<Setting>, Box<dyn std::error::Error>> {
+    let explicit = matches.get_one::<String>("config").map(PathBuf::from);
+    let path = match explicit.clone().or_else(config::default_path) {
+        Some(path) if !matches.get_flag("no_config") => path,
+        _ => return Ok(Vec::new()),
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        // Only an explicitly named file has to exist.
+        Err(err) if err.kind() == io::ErrorKind::NotFound && explicit.is_none() => {
+            return Ok(Vec::new());
+        }
+        Err(err) => {
+            return Err(format!("Failed to read config file '{}': {}", path.display(), err).into());
+        }
+    };
+    let entries = config::parse(&content).map_err(|err| format!("{}:{}", path.display(), err))?;
+
+    let mut settings = Vec::new();
+    for entry in entries {
+        let origin = format!("{}:{}: key '{}'", path.display(), entry.line, entry.key);
+
+        if let Some(name) = entry.key.strip_prefix("presets.") {
+            match &entry.value {
+                config::Value::String(chars) => presets
+                    .define(name, chars)
+                    .map_err(|err| format!("{}: {}", origin, err))?,
+                _ => {
+                    return Err(
+                        format!("{}: A preset must be a string of characters.", origin).into(),
+                    );
+                }
+            }
+            continue;
+        }
+
+        let id = entry.key.replace('-', "_");
+        if find_option(command, &id).is_none() {
+            return Err(format!("{}: This is not a known option.", origin).into());
+        }
+        settings.push(Setting {
+            id,
+            value: entry.value,
+            layer: 1,
+            origin,
+        });
+    }
+    Ok(settings)
+}
+
+// Whether the user set `id` themselves rather than leaving it at its default.
+fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+// Whether another member of a group containing `id`, such as the categories, was already
+// decided. A group is chosen as a whole, so '-l' on the command line replaces a config
+// `use_symbols = true`.
+fn in_decided_group(command: &clap::Command, id: &str, decided: impl Fn(&str) -> bool) -> bool {
+    // The derive also groups every field under the struct's name, which is not a choice.
+    command
+        .get_groups()
+        .filter(|group| group.get_id() != "Args")
+        .filter(|group| group.get_args().any(|arg| arg == id))
+        .flat_map(|group| group.get_args())
+        .any(|arg| decided(arg.as_str()))
+}
+
+// Whether `arg` is a flag or an option whose value may be left out, such as '--pin'.
+fn takes_optional_value(arg: &clap::Arg) -> bool {
+    arg.get_num_args()
+        .is_none_or(|range| range.min_values() == 0)
+}
+
+// The command line arguments equivalent to a config value for `arg`.
+fn config_args(arg: &clap::Arg, value: &config::Value) -> Result<Vec<OsString>, String> {
+    let long = format!("--{}", arg.get_long().unwrap_or_default());
+    // Flags and options with an optional value such as '--pin' accept booleans.
+    let optional = takes_optional_value(arg);
+
+    match value {
+        config::Value::Boolean(enabled) if optional => Ok(if *enabled {
+            vec![long.into()]
+        } else {
+            Vec::new()
+        }),
+        _ if matches!(arg.get_action(), clap::ArgAction::SetTrue) => {
+            Err("Expected true or false.".to_string())
+        }
+        // Counted flags like '--verbose' are repeated, so `verbose = 2` means '-vv'.
+        _ if matches!(arg.get_action(), clap::ArgAction::Count) => value
+            .to_string()
+            .parse::<usize>()
+            .map(|count| vec![long.clone().into(); count])
+            .map_err(|_| "Expected true, false, or a count.".to_string()),
+        config::Value::Boolean(_) => Err("Expected a value, not a boolean.".to_string()),
+        config::Value::Array(values) => {
+            if !matches!(arg.get_action(), clap::ArgAction::Append) {
+                return Err("Expected a single value, not an array.".to_string());
+            }
+            values
+                .iter()
+                .map(|value| match value {
+                    config::Value::Array(_) | config::Value::Boolean(_) => {
+                        Err("Array elements must be strings or numbers.".to_string())
+                    }
+                    value => Ok(format!("{}={}", long, value).into()),
+                })
+                .collect()
+        }
+        value => Ok(vec![format!("{}={}", long, value).into()]),
+    }
+}
+
+// The message of a clap error without its 'error: ' prefix and usage hints.
+fn clap_message(err: &clap::Error) -> String {
+    let rendered = err.render().to_string();
+    let first = rendered.lines().next().unwrap_or_default();
+    first.strip_prefix("error: ").unwrap_or(first).to_string()
+}
+
+// Pick the random number generator from '--rng' and '--seed'.
+fn resolve_rng_source(args: &Args) -> Result<RngSource, Box<dyn std::error::Error>> {
+    match (args.rng, args.seed) {
+        (None, None) => Ok(RngSource::Os),
+        (None, Some(_)) | (Some(RngSource::Seeded), Some(_)) => Ok(RngSource::Seeded),
+        (Some(RngSource::Seeded), None) => Err("'--rng seeded' requires --seed.".into()),
+        (Some(source), None) => Ok(source),
+        (Some(_), Some(_)) => Err("--seed can only be used with '--rng seeded'.".into()),
+    }
+}
+
+// The width of the terminal on stdout, falling back to $COLUMNS and then 80.
+fn terminal_width() -> usize {
+    #[cfg(unix)]
+    {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        // SAFETY: TIOCGWINSZ only writes a `winsize` through the valid pointer.
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if result == 0 && size.ws_col > 0 {
+            return size.ws_col as usize;
+        }
+    }
+
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(80)
+}
+
+// Options for writing an output file. On Unix a newly created file is readable by its owner
+// only; elsewhere the platform default applies.
+fn new_file_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+}
+
+// Warn when an existing output file grants access to its group or others.
+fn warn_if_readable_by_others(file: &File, path: &str) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = file.metadata()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            note(format_args!(
+                "Warning: '{}' is accessible by other users (mode {:o}). Consider 'chmod 600 {}'.",
+                path,
+                mode & 0o777,
+                path
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (file, path);
+    Ok(())
+}
+
+// Open a file for appending, creating it if needed. A missing final newline is added
+// so the new passwords don't run into the last existing line.
+fn open_for_append(path: &str) -> io::Result<File> {
+    let mut file = new_file_options()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    warn_if_readable_by_others(&file, path)?;
+
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")?;
+        }
+    }
+    Ok(file)
+}
+
+// The buffer size for output files and piped stdout.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+// The size of output, in bytes, that needs '--force-large'.
+const LARGE_OUTPUT: u128 = 1 << 30;
+
+// Batches of more passwords than this show a progress bar.
+const PROGRESS_THRESHOLD: usize = 10_000;
+
+/// Where the passwords are written.
+enum Output {
+    Stdout(output::ZeroizingWriter<io::StdoutLock<'static>>),
+    Append(output::ZeroizingWriter<File>),
+    Atomic(AtomicFile),
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::Append(out) => out.write(buf),
+            Output::Atomic(out) => out.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::Append(out) => out.flush(),
+            Output::Atomic(out) => out.flush(),
+        }
+    }
+}
+
+// Open the output file. '--append' never truncates and writes in place. Otherwise the
+// passwords are written to a temporary file, and '--force' allows it to replace an existing
+// file.
+fn open_output(
+    path: &str,
+    append: bool,
+    force: bool,
+) -> Result<Output, Box<dyn std::error::Error>> {
+    if append {
+        return Ok(Output::Append(output::ZeroizingWriter::with_capacity(
+            BUFFER_SIZE,
+            open_for_append(path)?,
+        )));
+    }
+    Ok(Output::Atomic(AtomicFile::create(path, force)?))
+}
+
+/// An output file written through a temporary file in the same directory, so the rename
+/// stays on one filesystem. It only replaces the destination once complete; if the run
+/// fails, the temporary file is removed again.
+struct AtomicFile {
+    writer: output::ZeroizingWriter<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    // Without '--force', the destination is created empty up front to claim it.
+    reserved: bool,
+    persisted: bool,
+}
+
+impl AtomicFile {
+    fn create(path: &str, overwrite: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        if !overwrite {
+            new_file_options()
+                .create_new(true)
+                .open(path)
+                .map_err(|err| -> Box<dyn std::error::Error> {
+                    if err.kind() == io::ErrorKind::AlreadyExists {
+                        format!(
+                            "The output file '{}' already exists. Use --force to overwrite it or --append to add to it.",
+                            path
+                        )
+                        .into()
+                    } else {
+                        format!("Failed to create file '{}': {}", path, err).into()
+                    }
+                })?;
+        }
+
+        let dest = PathBuf::from(path);
+        let name = dest
+            .file_name()
+            .ok_or_else(|| format!("The output path '{}' is not a file name.", path))?;
+        let temp_path = dest.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            std::process::id()
+        ));
+
+        let file = new_file_options()
+            .create_new(true)
+            .open(&temp_path)
+            .map_err(|err| {
+                if !overwrite {
+                    let _ = fs::remove_file(&dest);
+                }
+                format!(
+                    "Failed to create a temporary file next to '{}': {}",
+                    path, err
+                )
+            })?;
+
+        Ok(Self {
+            writer: output::ZeroizingWriter::with_capacity(BUFFER_SIZE, file),
+            temp_path,
+            path: dest,
+            reserved: !overwrite,
+            persisted: false,
+        })
+    }
+
+    // Flush and sync the temporary file, then rename it over the destination.
+    fn persist(mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        fs::rename(&self.temp_path, &self.path)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.temp_path);
+            if self.reserved {
+                let _ = fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+// Refuse a batch that could add up to more than LARGE_OUTPUT, which is more likely a
+// mistyped '--length' or '--count' than intended. '--force-large' skips the check.
+fn check_output_size(
+    config: &generator::GeneratorConfig,
+    args: &Args,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.force_large {
+        return Ok(());
+    }
+    let generator = generator::PasswordGenerator::new(config)?;
+    // Each password at its longest, in its widest characters, and a terminator.
+    let widest = generator
+        .charset()
+        .iter()
+        .map(|c| c.len_utf8())
+        .max()
+        .unwrap_or(1);
+    let bytes = (generator.max_length() as u128 * widest as u128 + 1) * args.count as u128;
+    if bytes > LARGE_OUTPUT {
+        return Err(format!(
+            "The passwords would take up to {} ({} of up to {} characters). Use --force-large to write them anyway.",
+            format_size(bytes),
+            args.count,
+            generator.max_length()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+// Check the length against '--allow-short' and '--max-length'. Errors start with where the
+// length was set, or the maximum when the length is the default.
+fn check_length_bounds(args: &Args, origins: &Origins) -> Result<(), String> {
+    let error = |message: String| match origins.get("length").or_else(|| origins.get("max_length"))
+    {
+        Some(origin) => format!("{}: {}", origin, message),
+        None => message,
+    };
+    if !args.allow_short && args.length.min < validation::MIN_LENGTH {
+        return Err(error(format!(
+            "The password length must be at least {}. Use --allow-short for shorter passwords.",
+            validation::MIN_LENGTH
+        )));
+    }
+    if args.length.max > args.max_length {
+        return Err(error(format!(
+            "The password length {} is above the maximum of {}. Use --max-length to raise it.",
+            args.length.max, args.max_length
+        )));
+    }
+    Ok(())
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let (matches, presets, origins) = parse_args()?;
+    let mut args = Args::from_arg_matches(&matches)?;
+    output::set_quiet(args.quiet);
+
+    if let Some(seconds) = args.clear_clipboard {
+        return Ok(clipboard::run_clear(seconds)?);
+    }
+
+    let policies = policy::Policies::builtin();
+    let policy = match &args.policy {
+        Some(name) => Some(policies.get(name)?),
+        None => None,
+    };
+    if let Some(policy) = policy {
+        apply_policy(&mut args, &matches, policy)?;
+    }
+
+    if let Some(Command::Check) = args.command {
+        return run_check(&args, &matches, policy);
+    }
+
+    if let Some(Command::Completions { shell }) = args.command {
+        let names = presets.iter().map(|(name, _)| name.to_string()).collect();
+        let mut stdout = io::stdout().lock();
+        completions::generate(
+            shell,
+            &Args::command(),
+            env!("CARGO_BIN_NAME"),
+            &[("preset", names)],
+            &mut stdout,
+        )?;
+        return Ok(());
+    }
+
+    if let Some(Command::Man) = args.command {
+        let mut stdout = io::stdout().lock();
+        manpage::generate(&Args::command(), env!("CARGO_BIN_NAME"), &mut stdout)?;
+        return Ok(());
+    }
+
+    // '--chars -' reads the set from stdin like '--chars-file -', and stdin can only be read once.
+    // The raw values are checked, since an escaped '\-' also parses to '-'.
+    let from_stdin: Vec<bool> = matches
+        .get_raw("chars")
+        .into_iter()
+        .flatten()
+        .map(|raw| raw == "-")
+        .collect();
+    let stdin_readers = from_stdin.iter().filter(|from_stdin| **from_stdin).count()
+        + usize::from(args.chars_file.as_deref() == Some("-"));
+    if stdin_readers > 1 {
+        return Err("Only one of '--chars -' and '--chars-file -' can read from stdin.".into());
+    }
+    for (chars, from_stdin) in args.chars.iter_mut().zip(from_stdin) {
+        if from_stdin {
+            *chars = generator::read_chars_file("-")?;
+        }
+    }
+    // A character file is one more '--chars' value.
+    if let Some(path) = &args.chars_file {
+        let chars = generator::read_chars_file(path)?;
+        args.chars.push(chars);
+    }
+
+    if args.list_presets {
+        for (name, preset) in presets.iter() {
+            let chars = preset.chars();
+            let note = match preset {
+                preset::Preset::Categories(_) => "  (one of each category)",
+                preset::Preset::Charset(_) => "",
+            };
+            println!(
+                "{:<10} {:>3}  {}{}",
+                name,
+                chars.chars().count(),
+                chars,
+                note
+            );
+        }
+        return Ok(());
+    }
+
+    if args.list_wordlists {
+        for wordlist in passphrase::Wordlist::ALL {
+            match (wordlist.words(), wordlist.feature()) {
+                (Some(words), _) => println!(
+                    "{:<10} {:>5} words  {:.1} bits per word",
+                    wordlist.name(),
+                    words.len(),
+                    (words.len() as f64).log2()
+                ),
+                (None, feature) => println!(
+                    "{:<10} (requires building with the '{}' feature)",
+                    wordlist.name(),
+                    feature.unwrap_or_default()
+                ),
+            }
+        }
+        return Ok(());
+    }
+
+    if args.list_policies {
+        for (name, policy) in policies.iter() {
+            println!("{:<10} {} ({})", name, policy.describe(), policy.source);
+        }
+        return Ok(());
+    }
+
+    if args.interactive {
+        prompt_options(&mut args)?;
+    }
+
+    let preset = match &args.preset {
+        Some(name) => Some(presets.get(name)?),
+        None => None,
+    };
+
+    // The length parser accepts any length, since it cannot see '--allow-short' or
+    // '--max-length'. Either may come from the environment or the config file, so the
+    // errors name where the length was set.
+    check_length_bounds(&args, &origins)?;
+
+    // The parsers accept any characters, since they cannot see '--unicode'.
+    let mut custom = args
+        .chars
+        .iter()
+        .chain(&args.chars_optional)
+        .chain(&args.symbols_set);
+    if !args.unicode && custom.any(|s| !s.is_ascii()) {
+        return Err(
+            "The custom character set can only contain ASCII characters. Use --unicode to allow others."
+                .into(),
+        );
+    }
+
+    if args.show_charset {
+        return show_charset(&password_config(&args, preset));
+    }
+
+    #[cfg(not(feature = "bip39"))]
+    if args.bip39.is_some() {
+        return Err("BIP-39 mnemonics require building with the 'bip39' feature.".into());
+    }
+
+    #[cfg(not(feature = "hash"))]
+    if args.hash.is_some() {
+        return Err("Password digests require building with the 'hash' feature.".into());
+    }
+
+    // Load a custom wordlist before the output file is created.
+    let wordlist = match &args.wordlist {
+        Some(path) => Some(passphrase::read_wordlist(path)?),
+        None => None,
+    };
+
+    let mut blacklist = Vec::new();
+    for path in &args.blacklist_file {
+        blacklist.extend(generator::read_blacklist(path)?);
+    }
+
+    // Each htpasswd user gets one password.
+    let htpasswd_users = match &args.htpasswd_users {
+        Some(path) => Some(htpasswd::read_users(path)?),
+        None if !args.htpasswd.is_empty() => Some(args.htpasswd.clone()),
+        None => None,
+    };
+    if let Some(users) = &htpasswd_users {
+        if users.is_empty() {
+            return Err("No htpasswd users were given.".into());
+        }
+        for user in users {
+            htpasswd::check_user(user)?;
+        }
+        match_count(&mut args, &matches, users.len(), "htpasswd users")?;
+        args.htpasswd_scheme.algorithm(args.cost)?.validate()?;
+    }
+
+    // Each label gets one password, in file order.
+    let labels = match &args.labels_file {
+        Some(path) => Some(labels::read_labels(path)?),
+        None => None,
+    };
+    if let Some(labels) = &labels {
+        if labels.is_empty() {
+            return Err("No labels were given.".into());
+        }
+        let duplicates = labels::duplicates(labels);
+        if !duplicates.is_empty() {
+            let message = format!("Duplicate labels: {}.", duplicates.join(", "));
+            if args.strict_labels {
+                return Err(message.into());
+            }
+            note(format_args!("Warning: {}", message));
+        }
+        match_count(&mut args, &matches, labels.len(), "labels")?;
+    }
+
+    if args.count > args.max_count {
+        return Err(format!(
+            "--count {} is above the limit of {}. Use --max-count to raise it.",
+            args.count, args.max_count
+        )
+        .into());
+    }
+
+    let source = resolve_rng_source(&args)?;
+
+    let mut rng: Box<dyn generator::SecureRng> = match source {
+        RngSource::Os => Box::new(OsRng),
+        RngSource::Thread => Box::new(rand::thread_rng()),
+        RngSource::Seeded => Box::new(StdRng::seed_from_u64(args.seed.unwrap_or_default())),
+    };
+
+    // Columns only fill a terminal; pipes and files keep one password per line
+    // unless a count is given explicitly.
+    let on_terminal = args.output.is_none() && io::stdout().is_terminal();
+    let columns = match args.columns {
+        Some(output::Columns::Auto) | None if on_terminal => output::Columns::Auto,
+        Some(output::Columns::Fixed(n)) => output::Columns::Fixed(n),
+        _ => output::Columns::Fixed(1),
+    };
+
+    #[cfg(not(feature = "crypt"))]
+    if args.crypt.is_some() {
+        return Err("crypt(3) hashes require building with the 'crypt' feature.".into());
+    }
+    #[cfg(feature = "crypt")]
+    let crypt = args.crypt.map(|scheme| match scheme {
+        password_hash::CryptScheme::Sha512 => password_hash::Algorithm::Sha512Crypt {
+            rounds: args.rounds,
+        },
+    });
+    #[cfg(not(feature = "crypt"))]
+    let crypt = None;
+
+    let hash_password = args.hash_password.map(|scheme| match scheme {
+        password_hash::Scheme::Bcrypt => password_hash::Algorithm::Bcrypt {
+            cost: args.cost,
+            version: password_hash::bcrypt::Version::B,
+        },
+        password_hash::Scheme::Argon2id => {
+            password_hash::Algorithm::Argon2id(password_hash::argon2::Params {
+                memory: args.argon2_memory,
+                iterations: args.argon2_iterations,
+                parallelism: args.argon2_parallelism,
+            })
+        }
+    });
+    let password_hash = hash_password
+        .or(crypt)
+        .map(|algorithm| password_hash::Hashing {
+            algorithm,
+            hash_only: args.hash_only,
+            jobs: job_count(args.jobs),
+        });
+    if let Some(hashing) = &password_hash {
+        hashing.algorithm.validate()?;
+    }
+
+    let options = output::OutputOptions {
+        format: args.format,
+        header: !args.no_header,
+        terminator: if args.print0 { "\0" } else { "\n" }.to_string(),
+        final_terminator: !args.no_newline,
+        // Machine formats, custom terminators, and digests always write one password per
+        // record. Interactive output is kept plain so that it can be saved as shown.
+        columns: if args.print0
+            || args.no_newline
+            || args.phonetic
+            || args.template.is_some()
+            || args.interactive
+            || args.hash.is_some()
+            || password_hash.is_some()
+            || labels.is_some()
+            || args.format != output::Format::Plain
+        {
+            output::Columns::Fixed(1)
+        } else {
+            columns
+        },
+        width: terminal_width(),
+        numbered: args.numbered,
+        show_entropy: args.show_entropy,
+        show_strength: args.strength,
+        qr: args.qr,
+        phonetic: args.phonetic,
+        template: args.template.clone(),
+        group: args
+            .group
+            .filter(|_| !(args.group_raw && (args.output.is_some() || args.output_dir.is_some()))),
+        group_separator: args.group_separator.clone(),
+        // Templates write lines for shells and config files, which color codes would break.
+        color: !args.interactive
+            && args.template.is_none()
+            && match args.color {
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+                ColorChoice::Auto => {
+                    on_terminal
+                        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                        && std::env::var_os("TERM").is_none_or(|term| term != "dumb")
+                }
+            },
+        hash: args.hash,
+        password_hash,
+        labels,
+        // Only when the passwords go elsewhere, since they would break up the bar, and
+        // nothing else writes to stderr meanwhile.
+        progress: !args.quiet
+            && args.count > PROGRESS_THRESHOLD
+            && !on_terminal
+            && !args.qr
+            && !args.interactive
+            && io::stderr().is_terminal(),
+    };
+
+    let job = match args.bip39 {
+        #[cfg(feature = "bip39")]
+        Some(words) => Job::Mnemonics(words),
+        _ if args.passphrase => Job::Passphrases(passphrase::PassphraseConfig {
+            words: args.words,
+            count: args.count,
+            wordlist,
+            wordlist_name: args.wordlist_name,
+            separator: args.separator.clone(),
+            min_entropy: args.min_entropy,
+            capitalize: args.capitalize,
+            random_case: args.random_case,
+            add_digits: args.add_digit,
+            add_symbols: args.add_symbol,
+        }),
+        _ => Job::Passwords(Box::new(generator::GeneratorConfig {
+            blacklist,
+            ..password_config(&args, preset)
+        })),
+    };
+
+    // Tell the user which length '--bits' or '--auto-length' settled on.
+    if let Job::Passwords(config) = &job
+        && (args.bits.is_some() || args.auto_length)
+    {
+        let generator = generator::PasswordGenerator::new(config)?;
+        if generator.max_length() > args.max_length {
+            return Err(format!(
+                "The password length {} is above the maximum of {}. Use --max-length to raise it.",
+                generator.max_length(),
+                args.max_length
+            )
+            .into());
+        }
+        if args.bits.is_some() || generator.length() > config.length {
+            note(format_args!(
+                "Using a length of {} ({:.1} bits of entropy).",
+                generator.length(),
+                generator.entropy_bits()
+            ));
+        }
+    }
+
+    if let Job::Passwords(config) = &job
+        && args.allow_short
+    {
+        let generator = generator::PasswordGenerator::new(config)?;
+        if generator.length() < validation::MIN_LENGTH {
+            note(format_args!(
+                "Warning: Passwords of {} character{} only have {:.1} bits of entropy.",
+                generator.length(),
+                if generator.length() == 1 { "" } else { "s" },
+                generator.entropy_bits()
+            ));
+        }
+    }
+
+    if args.verbose > 0 {
+        print_config(&job, &args, source)?;
+    }
+
+    if let Job::Passwords(config) = &job {
+        check_output_size(config, &args)?;
+    }
+
+    // The counters of '--stats', over every write of the batch.
+    let mut stats = None;
+
+    // '--count 0' only validates the configuration, without creating any output.
+    if args.count == 0 {
+        job.write(0, &mut *rng, &options, &mut io::sink(), &mut stats)
+            .map_err(|err| describe_error(err, &args.output))?;
+        note(format_args!(
+            "The configuration is valid: {}.",
+            describe_job(&job, &stats)
+        ));
+        return Ok(());
+    }
+
+    if let Some(dir) = &args.output_dir {
+        write_to_dir(&job, &args, dir, &mut *rng, &options, &mut stats)?;
+        return report_stats(&args, &stats);
+    }
+
+    // Determine the output destination.
+    // Buffer the output for better performance, except on a terminal, where stdout is already
+    // line buffered and each password should appear as soon as it is generated.
+    let output_path = args.output.clone(); // Clone for error reporting context
+    let mut output = match &args.output {
+        Some(path) => open_output(path, args.append, args.force)?,
+        None => {
+            let capacity = if io::stdout().is_terminal() {
+                0
+            } else {
+                BUFFER_SIZE
+            };
+            Output::Stdout(output::ZeroizingWriter::with_capacity(
+                capacity,
+                io::stdout().lock(),
+            ))
+        }
+    };
+
+    let mut remaining = args.count;
+    if args.copy && remaining > 0 {
+        let mut first = Vec::new();
+        let single = output::OutputOptions {
+            final_terminator: false,
+            ..Default::default()
+        };
+        job.write(1, &mut *rng, &single, &mut first, &mut stats)
+            .map_err(|err| describe_error(err, &output_path))?;
+        let mut password = String::from_utf8_lossy(&first).into_owned();
+        first.zeroize();
+
+        // Without a clipboard, fall back to printing every password.
+        match clipboard::copy(&password) {
+            Ok(()) => {
+                remaining -= 1;
+                if args.clear_after > 0 {
+                    note(format_args!(
+                        "Copied the password to the clipboard. It will be cleared in {} seconds.",
+                        args.clear_after
+                    ));
+                    if let Err(err) = clipboard::clear_after(&password, args.clear_after) {
+                        note(format_args!(
+                            "Warning: The clipboard will not be cleared: {}",
+                            err
+                        ));
+                    }
+                } else {
+                    note("Copied the password to the clipboard.");
+                }
+            }
+            Err(err) => {
+                note(format_args!(
+                    "Warning: Could not copy to the clipboard ({}), printing instead.",
+                    err
+                ));
+            }
+        }
+        password.zeroize();
+    }
+
+    if args.interactive {
+        let mut shown = Vec::new();
+        job.write(remaining, &mut *rng, &options, &mut shown, &mut stats)
+            .map_err(|err| describe_error(err, &output_path))?;
+        let mut result = output
+            .write_all(&shown)
+            .and_then(|()| output.flush())
+            .map_err(Box::from);
+        if result.is_ok() && remaining > 0 {
+            result = save_interactive(&shown);
+        }
+        shown.zeroize();
+        result?;
+        return report_stats(&args, &stats);
+    }
+
+    if let Some(users) = &htpasswd_users {
+        write_htpasswd(&job, users, &args, &mut *rng, &mut output, &mut stats)?;
+    } else if !args.copy || remaining > 0 {
+        job.write(remaining, &mut *rng, &options, &mut output, &mut stats)
+            .map_err(|err| describe_error(err, &output_path))?;
+    }
+
+    match output {
+        Output::Atomic(file) => file
+            .persist()
+            .map_err(|err| describe_error(err.into(), &output_path))?,
+        // Flush explicitly, since dropping the writer ignores errors.
+        mut output => output
+            .flush()
+            .map_err(|err| describe_error(err.into(), &output_path))?,
+    }
+    report_stats(&args, &stats)
+}
+
+// Print the '--stats' summary to stderr, as JSON with '--format json'.
+fn report_stats(
+    args: &Args,
+    stats: &Option<generator::GenerationStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(stats) = stats.as_ref() else {
+        return Ok(());
+    };
+    if !args.stats {
+        // '-vv' reports the rejected candidates that '--stats' would include.
+        if args.verbose >= 2 && stats.rejects_candidates {
+            eprintln!(
+                "Rejected candidates: {} ({:.2} per password)",
+                stats.rejected,
+                stats.average_retries()
+            );
+        }
+        return Ok(());
+    }
+    let (min_bits, max_bits) = match &stats.entropy {
+        output::Entropy::Fixed(bits) => (*bits, *bits),
+        output::Entropy::ByLength { bits, .. } => (bits[0], bits[bits.len() - 1]),
+    };
+    let range = |min: String, max: String| {
+        if min == max {
+            min
+        } else {
+            format!("{} to {}", min, max)
+        }
+    };
+
+    if args.format == output::Format::Json {
+        eprintln!(
+            "{{\"count\": {}, \"min_length\": {}, \"max_length\": {}, \"charset_size\": {}, \"min_entropy_bits\": {:.1}, \"max_entropy_bits\": {:.1}, \"rejected\": {}, \"average_retries\": {:.2}}}",
+            stats.count,
+            stats.min_length,
+            stats.max_length,
+            stats.charset_size,
+            min_bits,
+            max_bits,
+            stats.rejected,
+            stats.average_retries()
+        );
+        return Ok(());
+    }
+
+    eprintln!("Passwords: {}", stats.count);
+    eprintln!(
+        "Length: {}",
+        range(stats.min_length.to_string(), stats.max_length.to_string())
+    );
+    if stats.charset_size > 0 {
+        eprintln!("Charset size: {}", stats.charset_size);
+    }
+    eprintln!(
+        "Entropy: {} bits per password",
+        range(format!("{:.1}", min_bits), format!("{:.1}", max_bits))
+    );
+    if stats.rejects_candidates {
+        eprintln!(
+            "Rejected candidates: {} ({:.2} per password)",
+            stats.rejected,
+            stats.average_retries()
+        );
+    }
+    Ok(())
+}
+
+// Ask for the options of '--interactive' on stderr, using the current values as defaults.
+// The answers are stored in `args`, so the passwords come from the same configuration as
+// the equivalent flags.
+fn prompt_options(args: &mut Args) -> Result<(), Box<dyn std::error::Error>> {
+    if !io::stdin().is_terminal() {
+        return Err("--interactive needs a terminal on stdin.".into());
+    }
+    let current = password_config(args, None);
+
+    let length = match current.length_max {
+        Some(max) => format!("{}-{}", current.length, max),
+        None => current.length.to_string(),
+    };
+    let min_length = if args.allow_short {
+        1
+    } else {
+        validation::MIN_LENGTH
+    };
+    args.length = prompt("Length", &length, |answer| {
+        validation::parse_length_within(answer, min_length, args.max_length)
+    })?;
+
+    let flags = [
+        (current.use_lower, 'l'),
+        (current.use_upper, 'u'),
+        (current.use_digits, 'd'),
+        (current.use_symbols, 's'),
+    ];
+    let categories: String = flags
+        .iter()
+        .filter(|(used, _)| *used)
+        .map(|(_, flag)| *flag)
+        .collect();
+    let categories = prompt(
+        "Categories: (l)owercase, (u)ppercase, (d)igits, (s)ymbols",
+        &categories,
+        |answer| {
+            if !answer.is_empty() && answer.chars().all(|c| "luds".contains(c)) {
+                Ok(answer.to_string())
+            } else {
+                Err("Enter one or more of the letters l, u, d, and s.".to_string())
+            }
+        },
+    )?;
+    // The answer replaces '--all', '--alnum', '--letters', and the category flags.
+    args.all = false;
+    args.alnum = false;
+    args.letters = false;
+    args.use_lower = categories.contains('l');
+    args.use_upper = categories.contains('u');
+    args.use_digits = categories.contains('d');
+    args.use_symbols = categories.contains('s');
+    args.no_lower = !args.use_lower;
+    args.no_upper = !args.use_upper;
+    args.no_digits = !args.use_digits;
+    args.no_symbols = !args.use_symbols;
+
+    let extra = prompt(
+        "Additional characters, in the syntax of --chars",
+        "",
+        |answer| match answer {
+            "" => Ok(None),
+            chars => validation::parse_chars(chars).map(Some),
+        },
+    )?;
+    args.chars.extend(extra);
+
+    args.count = prompt(
+        "Count",
+        &args.count.to_string(),
+        |answer| match validation::parse_count(answer)? {
+            0 => Err("The count must be at least 1.".to_string()),
+            count => Ok(count),
+        },
+    )?;
+
+    args.copy = prompt(
+        "Copy the first password to the clipboard? (y/n)",
+        if args.copy { "y" } else { "n" },
+        parse_yes_no,
+    )?;
+    Ok(())
+}
+
+// Offer to save the passwords shown by '--interactive'. An existing file is not overwritten.
+fn save_interactive(shown: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let path = prompt(
+        "Save the passwords to a file? Enter a path, or nothing to skip",
+        "",
+        |answer| Ok(answer.to_string()),
+    )?;
+    if path.is_empty() {
+        return Ok(());
+    }
+    let mut file = AtomicFile::create(&path, false)?;
+    let context = Some(path.clone());
+    file.write_all(shown)
+        .map_err(|err| describe_error(err.into(), &context))?;
+    file.persist()
+        .map_err(|err| describe_error(err.into(), &context))?;
+    note(format_args!("Saved the passwords to '{}'.", path));
+    Ok(())
+}
+
+// Ask `question` on stderr until the answer passes `parse`. An empty answer means
+// `default`, which is shown in brackets when there is one.
+fn prompt<T>(
+    question: &str,
+    default: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> io::Result<T> {
+    let stdin = io::stdin();
+    loop {
+        if default.is_empty() {
+            eprint!("{}: ", question);
+        } else {
+            eprint!("{} [{}]: ", question, default);
+        }
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The input ended before all questions were answered.",
+            ));
+        }
+        let answer = match line.trim() {
+            "" => default,
+            answer => answer,
+        };
+        match parse(answer) {
+            Ok(value) => return Ok(value),
+            Err(message) => eprintln!("{}", message),
+        }
+    }
+}
+
+fn parse_yes_no(answer: &str) -> Result<bool, String> {
+    match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "n" | "no" => Ok(false),
+        _ => Err("Answer 'y' or 'n'.".to_string()),
+    }
+}
+
+// Write each password into its own file in `dir`, named by '--filename-template'.
+// Nothing is written if any of the files already exists, unless '--force' is given.
+fn write_to_dir(
+    job: &Job,
+    args: &Args,
+    dir: &str,
+    rng: &mut dyn generator::SecureRng,
+    options: &output::OutputOptions,
+    stats: &mut Option<generator::GenerationStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let width = args.count.to_string().len().max(3);
+    let names: Vec<String> = (1..=args.count)
+        .map(|n| {
+            args.filename_template
+                .replace("{n}", &format!("{:0width$}", n, width = width))
+        })
+        .collect();
+
+    if args.count > 1 && !args.filename_template.contains("{n}") {
+        return Err(
+            "The file name template must contain '{n}' when --count is greater than 1.".into(),
+        );
+    }
+
+    let paths: Vec<PathBuf> = names.iter().map(|name| Path::new(dir).join(name)).collect();
+    if !args.force
+        && let Some(path) = paths.iter().find(|path| path.exists())
+    {
+        return Err(format!(
+            "The output file '{}' already exists. Use --force to overwrite it.",
+            path.display()
+        )
+        .into());
+    }
+
+    fs::create_dir_all(dir)
+        .map_err(|err| format!("Failed to create directory '{}': {}", dir, err))?;
+
+    for path in &paths {
+        let path = path.to_string_lossy();
+        let mut file = AtomicFile::create(&path, args.force)?;
+        let path = Some(path.into_owned());
+
+        job.write(1, rng, options, &mut file, stats)
+            .map_err(|err| describe_error(err, &path))?;
+        file.persist()
+            .map_err(|err| describe_error(err.into(), &path))?;
+    }
+    Ok(())
+}
+
+/// What the command generates.
+enum Job {
+    Passwords(Box<generator::GeneratorConfig>),
+    Passphrases(passphrase::PassphraseConfig),
+    /// BIP-39 mnemonics of this many words.
+    #[cfg(feature = "bip39")]
+    Mnemonics(usize),
+}
+
+impl Job {
+    // Write `count` passwords or passphrases in the format described by `options`, adding
+    // the counters of passwords to `stats`.
+    fn write(
+        &self,
+        count: usize,
+        rng: &mut dyn generator::SecureRng,
+        options: &output::OutputOptions,
+        output: &mut dyn Write,
+        stats: &mut Option<generator::GenerationStats>,
+    ) -> Result<(), generator::Error> {
+        match self {
+            Job::Passwords(config) => {
+                let config = generator::GeneratorConfig {
+                    count,
+                    ..(**config).clone()
+                };
+                let batch = generator::generate_passwords_with_rng(&config, rng, options, output)?;
+                match stats {
+                    Some(stats) => stats.combine(&batch),
+                    None => *stats = Some(batch),
+                }
+                Ok(())
+            }
+            Job::Passphrases(config) => {
+                let config = passphrase::PassphraseConfig {
+                    count,
+                    ..config.clone()
+                };
+                passphrase::generate_passphrases(&config, rng, options, output)
+            }
+            #[cfg(feature = "bip39")]
+            Job::Mnemonics(words) => bip39::generate_mnemonics(*words, count, rng, options, output),
+        }
+    }
+}
+
+// What `job` generates in a few words, e.g. 'passwords of 8 characters from 88, with 51.7
+// bits of entropy each', from the counters of a write.
+fn describe_job(job: &Job, stats: &Option<generator::GenerationStats>) -> String {
+    match (job, stats) {
+        (Job::Passwords(_), Some(stats)) => {
+            let bits = match &stats.entropy {
+                output::Entropy::Fixed(bits) => format!("{:.1}", bits),
+                output::Entropy::ByLength { bits, .. } => {
+                    format!("{:.1} to {:.1}", bits[0], bits[bits.len() - 1])
+                }
+            };
+            let length = if stats.min_length == stats.max_length {
+                stats.min_length.to_string()
+            } else {
+                format!("{} to {}", stats.min_length, stats.max_length)
+            };
+            let charset = if stats.charset_size > 0 {
+                format!(" from {}", stats.charset_size)
+            } else {
+                String::new()
+            };
+            format!(
+                "passwords of {} characters{}, with {} bits of entropy each",
+                length, charset, bits
+            )
+        }
+        (Job::Passphrases(config), _) => format!("passphrases of {} words", config.words),
+        #[cfg(feature = "bip39")]
+        (Job::Mnemonics(words), _) => format!("BIP39 mnemonics of {} words", words),
+        (Job::Passwords(_), None) => "passwords".to_string(),
+    }
+}
+
+// Print the configuration that `job` was resolved to for '--verbose'.
+fn print_config(
+    job: &Job,
+    args: &Args,
+    source: RngSource,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match job {
+        Job::Passwords(config) => {
+            let generator = generator::PasswordGenerator::new(config)?;
+            let kind = if config.uuid {
+                "UUIDs"
+            } else if config.memorable {
+                "memorable passwords"
+            } else if config.pronounceable {
+                "pronounceable passwords"
+            } else if config.pattern.is_some() {
+                "passwords from a pattern"
+            } else {
+                "random passwords"
+            };
+            eprintln!("Generating: {}", kind);
+            eprintln!("Categories: {}", generator.categories().join(", "));
+            eprintln!("Charset size: {}", generator.charset().len());
+            if args.verbose >= 2 {
+                eprintln!(
+                    "Charset: {}",
+                    generator.charset().iter().collect::<String>()
+                );
+            }
+            if generator.max_length() == generator.length() {
+                eprintln!("Length: {}", generator.length());
+            } else {
+                eprintln!(
+                    "Length: {} to {}",
+                    generator.length(),
+                    generator.max_length()
+                );
+            }
+            eprintln!("Entropy: {:.1} bits per password", generator.entropy_bits());
+            let counts: Vec<String> = generator
+                .required_sets()
+                .iter()
+                .filter(|set| set.min() > 0 || set.max().is_some())
+                .map(|set| match set.max() {
+                    Some(max) => format!("{} {} to {}", set.name(), set.min(), max),
+                    None => format!("{} at least {}", set.name(), set.min()),
+                })
+                .collect();
+            if !counts.is_empty() {
+                eprintln!("Category counts: {}", counts.join(", "));
+            }
+            let constraints = constraints(config);
+            if constraints.is_empty() {
+                eprintln!("Constraints: none");
+            } else {
+                eprintln!("Constraints: {}", constraints.join("; "));
+            }
+            if args.verbose >= 2 && generator.rejects_candidates() {
+                eprintln!(
+                    "Attempts per password: {}",
+                    config
+                        .max_attempts
+                        .unwrap_or(generator::DEFAULT_MAX_ATTEMPTS)
+                );
+            }
+        }
+        Job::Passphrases(config) => {
+            eprintln!("Generating: passphrases");
+            eprintln!("Words: {}", config.words);
+            match &config.wordlist {
+                Some(words) => eprintln!("Wordlist: custom, {} words", words.len()),
+                None => eprintln!("Wordlist: {}", value_name(config.wordlist_name)),
+            }
+            eprintln!("Separator: '{}'", config.separator);
+            match config.random_case {
+                Some(case) => eprintln!("Random case: {}", value_name(case)),
+                None => eprintln!("Capitalize: {}", value_name(config.capitalize)),
+            }
+            if config.add_digits > 0 || config.add_symbols > 0 {
+                eprintln!(
+                    "Added: {} digits, {} symbols",
+                    config.add_digits, config.add_symbols
+                );
+            }
+        }
+        #[cfg(feature = "bip39")]
+        Job::Mnemonics(words) => eprintln!("Generating: BIP39 mnemonics of {} words", words),
+    }
+
+    eprintln!("Count: {}", args.count);
+    eprintln!("Format: {}", value_name(args.format));
+    let output = match (&args.output_dir, &args.output) {
+        (Some(dir), _) => format!("a file per password in '{}'", dir),
+        (None, Some(path)) if args.append => format!("'{}', appending", path),
+        (None, Some(path)) => format!("'{}'", path),
+        (None, None) => "stdout".to_string(),
+    };
+    if args.copy {
+        eprintln!("Output: the clipboard, then {}", output);
+    } else {
+        eprintln!("Output: {}", output);
+    }
+    eprintln!("Random number generator: {}", source.description());
+    Ok(())
+}
+
+// The checks that passwords from `config` must pass, in words.
+fn constraints(config: &generator::GeneratorConfig) -> Vec<String> {
+    let mut constraints = Vec::new();
+    if config.avoid_ambiguous {
+        constraints.push("no ambiguous characters".to_string());
+    }
+    if let Some(exclude) = &config.exclude_chars {
+        constraints.push(format!("none of '{}'", exclude));
+    }
+    if config.uniform {
+        constraints.push("uniform sampling".to_string());
+    }
+    if config.unique {
+        constraints.push("unique".to_string());
+    }
+    if config.no_repeat {
+        constraints.push("no repeated characters".to_string());
+    }
+    if config.no_consecutive_identical {
+        constraints.push("no identical neighbors".to_string());
+    }
+    if let Some(n) = config.no_sequential {
+        constraints.push(format!("no sequential runs of {} or more", n));
+    }
+    if let Some(n) = config.no_keyboard_walks {
+        constraints.push(format!("no keyboard walks of {} or more keys", n));
+    }
+    if let Some(n) = config.no_words {
+        constraints.push(format!("no common words of {} or more letters", n));
+    }
+    if config.start_with_letter {
+        constraints.push("starts with a letter".to_string());
+    }
+    if config.no_edge_symbols {
+        constraints.push("no symbols at the edges".to_string());
+    }
+    if let Some(score) = config.min_score {
+        constraints.push(format!("zxcvbn score of at least {}", score));
+    }
+    if let Some(bits) = config.min_entropy {
+        constraints.push(format!("at least {} bits of entropy", bits));
+    }
+    if !config.blacklist.is_empty() {
+        constraints.push(format!(
+            "none of {} blacklisted terms",
+            config.blacklist.len()
+        ));
+    }
+    if !config.weights.is_empty() {
+        let weights: Vec<String> = config
+            .weights
+            .iter()
+            .map(|(class, factor)| format!("{}={}", class.name(), factor))
+            .collect();
+        constraints.push(format!("weights {}", weights.join(", ")));
+    }
+    if let Some(leet) = &config.leet {
+        constraints.push(format!(
+            "leet substitutions with a chance of {}",
+            leet.probability
+        ));
+    }
+    if let Some(mode) = config.check_pwned {
+        constraints.push(format!("not in Have I Been Pwned ({})", value_name(mode)));
+    }
+    constraints
+}
+
+// A size in bytes for people, e.g. '9.3 GiB'.
+fn format_size(bytes: u128) -> String {
+    const UNITS: [&str; 5] = ["bytes", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} bytes", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// The command-line name of an enum value, e.g. 'best-effort'.
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+// Set '--count' to the number of `items` it defaults to, or check that an explicit count
+// agrees with it.
+fn match_count(args: &mut Args, matches: &ArgMatches, n: usize, items: &str) -> Result<(), String> {
+    if matches.value_source("count") == Some(ValueSource::DefaultValue) {
+        args.count = n;
+    } else if args.count != n {
+        return Err(format!(
+            "--count is {}, but {} {} were given.",
+            args.count, n, items
+        ));
+    }
+    Ok(())
+}
+
+// Write an htpasswd line for each of `users` to `output`, and their passwords as
+// 'USER:PASSWORD' lines to the '--htpasswd-plaintext' file or stderr.
+fn write_htpasswd(
+    job: &Job,
+    users: &[String],
+    args: &Args,
+    rng: &mut dyn generator::SecureRng,
+    output: &mut dyn Write,
+    stats: &mut Option<generator::GenerationStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = match &args.htpasswd_plaintext {
+        Some(path) => Some(AtomicFile::create(path, args.force)?),
+        None => None,
+    };
+    let mut stderr = io::stderr();
+    let plaintext: &mut dyn Write = match &mut file {
+        Some(file) => file,
+        None => &mut stderr,
+    };
+
+    // Each record is the password and its hash, a tab apart. None of the hashes contain a
+    // tab, and the records are separated by NUL, which no password contains.
+    let options = output::OutputOptions {
+        terminator: "\0".to_string(),
+        password_hash: Some(password_hash::Hashing {
+            algorithm: args.htpasswd_scheme.algorithm(args.cost)?,
+            hash_only: false,
+            jobs: job_count(args.jobs),
+        }),
+        ..Default::default()
+    };
+    let mut records = Vec::new();
+    job.write(users.len(), rng, &options, &mut records, stats)
+        .map_err(|err| describe_error(err, &args.output))?;
+
+    let mut result = Ok(());
+    for (user, record) in users.iter().zip(records.split(|&byte| byte == 0)) {
+        let (password, hash) = std::str::from_utf8(record)
+            .ok()
+            .and_then(|record| record.rsplit_once('\t'))
+            .unwrap_or_default();
+        result = writeln!(output, "{}", htpasswd::line(user, hash))
+            .and_then(|()| writeln!(plaintext, "{}:{}", user, password));
+        if result.is_err() {
+            break;
+        }
+    }
+    records.zeroize();
+    result.map_err(|err| describe_error(err.into(), &args.output))?;
+
+    match (file, &args.htpasswd_plaintext) {
+        (Some(file), Some(path)) => file
+            .persist()
+            .map_err(|err| describe_error(err.into(), &Some(path.clone())))?,
+        _ => stderr.flush()?,
+    }
+    Ok(())
+}
+
+// Turn a generation error into a message, attributing output errors to the destination.
+fn describe_error(
+    err: generator::Error,
+    output_path: &Option<String>,
+) -> Box<dyn std::error::Error> {
+    match (err, output_path) {
+        // Add context if the error is related to file output
+        (generator::Error::Io(err), Some(path)) => {
+            Box::from(format!("Failed to write to file '{}': {}", path, err))
+        }
+        (generator::Error::Io(err), None) => {
+            Box::from(format!("An output error occurred: {}", err))
+        }
+        // Configuration errors are reported as they are
+        (err, _) => Box::new(err),
+    }
+}
+
+// Print the characters and sets a configuration resolves to, without generating anything.
+fn show_charset(config: &generator::GeneratorConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let generator = generator::PasswordGenerator::new(config)?;
+
+    // UUIDs have a fixed format instead of a charset.
+    if generator.charset().is_empty() {
+        println!("Charset: none (fixed format)");
+    } else {
+        let charset: String = generator.charset().iter().collect();
+        println!("Charset: {}", charset);
+        println!("Size: {}", generator.charset().len());
+    }
+
+    if generator.required_sets().is_empty() {
+        println!("Required sets: none");
+    } else {
+        println!("Required sets:");
+        for set in generator.required_sets() {
+            let chars: String = set.chars().iter().collect();
+            let limit = match (set.min(), set.max()) {
+                (min, Some(max)) => format!("{} to {}", min, max),
+                (0, None) => "any number".to_string(),
+                (min, None) => format!("at least {}", min),
+            };
+            println!("  {}: {} of {}", set.name(), limit, chars);
+        }
+    }
+
+    match generator.entropy() {
+        output::Entropy::Fixed(bits) => {
+            println!("Entropy: {:.1} bits at length {}", bits, generator.length())
+        }
+        output::Entropy::ByLength { min_length, bits } => println!(
+            "Entropy: {:.1} to {:.1} bits at lengths {} to {}",
+            bits[0],
+            bits[bits.len() - 1],
+            min_length,
+            generator.max_length()
+        ),
+    }
+    Ok(())
+}
+
+// Analyze a password read from stdin against the options given on the command line.
+fn run_check(
+    args: &Args,
+    matches: &ArgMatches,
+    policy: Option<&policy::NamedPolicy>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let password = read_password()?;
+    let analysis = check::analyze(&password);
+
+    // '--all' asks for every category; otherwise only the categories that were named.
+    let named = [
+        args.use_lower,
+        args.use_upper,
+        args.use_digits,
+        args.use_symbols,
+    ];
+    let required = generator::CharClass::ALL
+        .into_iter()
+        .zip(named)
+        .filter(|(_, named)| args.all || *named)
+        .map(|(class, _)| class)
+        .collect();
+
+    // '--length' has a default, which is not a requirement, unlike the minimum of a policy.
+    let min_length = (matches.value_source("length") == Some(ValueSource::CommandLine))
+        .then_some(args.length.min)
+        .into_iter()
+        .chain(policy.and_then(|policy| policy.rules.min_length))
+        .max();
+
+    let policy = check::Policy {
+        min_length,
+        required,
+        min_lower: args.min_lower.unwrap_or(0),
+        min_upper: args.min_upper.unwrap_or(0),
+        min_digits: args.min_digits.unwrap_or(0),
+        min_symbols: args.min_symbols.unwrap_or(0),
+        max_lower: args.max_lower,
+        max_upper: args.max_upper,
+        max_digits: args.max_digits,
+        max_symbols: args.max_symbols,
+        exclude_chars: args.exclude.clone(),
+        avoid_ambiguous: args.avoid_ambiguous,
+    };
+    let violations = policy.violations(&password);
+
+    let classes: Vec<&str> = analysis.classes.iter().map(|class| class.name()).collect();
+    println!("Length: {}", analysis.length);
+    println!(
+        "Categories: {}",
+        if classes.is_empty() {
+            "none".to_string()
+        } else {
+            classes.join(", ")
+        }
+    );
+    if !analysis.other_chars.is_empty() {
+        let others: String = analysis.other_chars.iter().collect();
+        println!("Other characters: {}", others);
+    }
+    println!(
+        "Entropy: {:.1} bits ({} possible characters)",
+        analysis.entropy_bits, analysis.pool_size
+    );
+    println!(
+        "Strength: {}/{} (10^{:.1} guesses)",
+        analysis.strength.score,
+        strength::MAX_SCORE,
+        analysis.strength.guesses_log10
+    );
+
+    if violations.is_empty() {
+        println!("Result: pass");
+        return Ok(());
+    }
+    println!("Result: fail");
+    for violation in &violations {
+        println!("  - {}", violation);
+    }
+    Err("The password does not satisfy the given requirements.".into())
+}
+
+// Read one line from stdin without its line ending, hiding the input on a terminal.
+fn read_password() -> io::Result<String> {
+    let stdin = io::stdin();
+    let mut line = String::new();
+
+    if stdin.is_terminal() {
+        eprint!("Password: ");
+        let echo = EchoGuard::disable();
+        let result = stdin.lock().read_line(&mut line);
+        drop(echo);
+        eprintln!();
+        result?;
+    } else {
+        stdin.lock().read_line(&mut line)?;
+    }
+
+    let password = line.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No password was given on stdin.",
+        ));
+    }
+    Ok(password.to_string())
+}
+
+// Turns off terminal echo on stdin until dropped.
+struct EchoGuard {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl EchoGuard {
+    #[cfg(unix)]
+    fn disable() -> Self {
+        // SAFETY: `termios` is plain data, and both calls only read and write the struct
+        // passed to them.
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Self { original: None };
+            }
+            let original = termios;
+            termios.c_lflag &= !libc::ECHO;
+            termios.c_lflag |= libc::ECHONL;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            Self {
+                original: Some(original),
+            }
+        }
+    }
+
+    // Other platforms keep echoing.
+    #[cfg(not(unix))]
+    fn disable() -> Self {
+        Self {}
+    }
+}
+
+impl Drop for EchoGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = &self.original {
+            // SAFETY: restores the settings read in `disable`.
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+            }
+        }
+    }
+}
+
+// Raise the length and category minimums to those of `policy`, rejecting options that
+// would weaken it.
+fn apply_policy(
+    args: &mut Args,
+    matches: &ArgMatches,
+    policy: &policy::NamedPolicy,
+) -> Result<(), String> {
+    let name = args.policy.clone().unwrap_or_default();
+
+    // The option is global for 'check', where these do not exist, so clap cannot reject them.
+    let modes = [
+        (args.preset.is_some(), "--preset"),
+        (args.pin.is_some(), "--pin"),
+        (args.hex, "--hex"),
+        (args.base58, "--base58"),
+        (args.base62, "--base62"),
+        (args.emoji_only, "--emoji-only"),
+        (args.uuid, "--uuid"),
+        (args.passphrase, "--passphrase"),
+        (args.bip39.is_some(), "--bip39"),
+        (args.pronounceable, "--pronounceable"),
+        (args.memorable, "--memorable"),
+        (args.pattern.is_some(), "--pattern"),
+        (args.bits.is_some(), "--bits"),
+        (args.interactive, "--interactive"),
+    ];
+    if let Some((_, flag)) = modes.iter().find(|(set, _)| *set) {
+        return Err(format!("--policy cannot be used with {}.", flag));
+    }
+
+    if let Some(min_length) = policy.rules.min_length
+        && args.length.min < min_length
+    {
+        if matches.value_source("length") != Some(ValueSource::DefaultValue) {
+            return Err(format!(
+                "--length {} conflicts with the '{}' policy, which requires at least {} characters.",
+                args.length.min, name, min_length
+            ));
+        }
+        args.length = generator::LengthRange {
+            min: min_length,
+            max: args.length.max.max(min_length),
+        };
+    }
+
+    // Naming categories leaves out the others, unless '--all' adds them back.
+    let chosen = !args.all
+        && (args.alnum
+            || args.letters
+            || args.use_lower
+            || args.use_upper
+            || args.use_digits
+            || args.use_symbols
+            || !args.chars.is_empty());
+    for class in generator::CharClass::ALL {
+        let min = policy.min_count(class);
+        if min == 0 {
+            continue;
+        }
+        let (flag, option, excluded, selected, min_count, max_count) = match class {
+            generator::CharClass::Lower => (
+                "lower",
+                'l',
+                args.no_lower,
+                args.use_lower || args.alnum || args.letters,
+                &mut args.min_lower,
+                args.max_lower,
+            ),
+            generator::CharClass::Upper => (
+                "upper",
+                'u',
+                args.no_upper,
+                args.use_upper || args.alnum || args.letters,
+                &mut args.min_upper,
+                args.max_upper,
+            ),
+            generator::CharClass::Digit => (
+                "digits",
+                'd',
+                args.no_digits,
+                args.use_digits || args.alnum,
+                &mut args.min_digits,
+                args.max_digits,
+            ),
+            generator::CharClass::Symbol => (
+                "symbols",
+                's',
+                args.no_symbols,
+                args.use_symbols,
+                &mut args.min_symbols,
+                args.max_symbols,
+            ),
+        };
+        if excluded {
+            return Err(format!(
+                "--no-{} conflicts with the '{}' policy, which requires {}.",
+                flag,
+                name,
+                class.name()
+            ));
+        }
+        if let Some(max) = max_count
+            && max < min
+        {
+            return Err(format!(
+                "--max-{} {} conflicts with the '{}' policy, which requires at least {} {}.",
+                flag,
+                max,
+                name,
+                min,
+                class.name()
+            ));
+        }
+        if chosen && !selected && min_count.is_none_or(|n| n == 0) {
+            return Err(format!(
+                "The selected categories leave out {}, which the '{}' policy requires. Add -{} or use --all.",
+                class.name(),
+                name,
+                option
+            ));
+        }
+        *min_count = Some(min_count.unwrap_or(0).max(min));
+    }
+
+    args.avoid_ambiguous |= policy.rules.avoid_ambiguous;
+    if let Some(exclude) = &policy.rules.exclude_chars {
+        args.exclude = Some(args.exclude.take().unwrap_or_default() + exclude);
+    }
+    Ok(())
+}
+
+// The number of threads for '--jobs', where 0 asks for one per CPU.
+fn job_count(jobs: usize) -> usize {
+    match jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    }
+}
+
+// Configure character-based passwords from the category flags.
+fn password_config(args: &Args, preset: Option<&preset::Preset>) -> generator::GeneratorConfig {
+    // A preset selects either categories or a complete charset.
+    let preset_has = |class| matches!(preset, Some(preset::Preset::Categories(classes)) if classes.contains(&class));
+
+    // Determine if any character type flag is explicitly set.
+    let any_flag_set = args.alnum
+        || args.letters
+        || args.use_lower
+        || args.use_upper
+        || args.use_digits
+        || args.use_symbols
+        || !args.chars.is_empty()
+        || preset.is_some();
+
+    // Token modes replace the category flags with a complete charset.
+    let charset = if let Some(preset::Preset::Charset(charset)) = preset {
+        Some(charset.clone())
+    } else if args.hex {
+        Some(generator::Charset::hex(args.upper))
+    } else if args.base58 {
+        Some(generator::Charset::base58())
+    } else if args.base62 {
+        Some(generator::Charset::base62())
+    } else if args.emoji_only {
+        Some(generator::Charset::emoji())
+    } else {
+        None
+    };
+
+    // A PIN uses digits only and replaces the validated '--length'.
+    let pin_mode = args.pin.is_some();
+    let (length, length_max) = match args.pin {
+        Some(pin) => (pin, None),
+        None => (
+            args.length.min,
+            (args.length.max > args.length.min).then_some(args.length.max),
+        ),
+    };
+
+    // Use all categories if '--all' is specified, or if no specific category is chosen
+    // and no complete charset replaces them.
+    let use_all = !pin_mode && charset.is_none() && (args.all || !any_flag_set);
+
+    // Build GeneratorConfig from Args
+    generator::GeneratorConfig {
+        length,
+        length_max,
+        count: args.count,
+        // If `use_all` is true, enable this category. A minimum count also implies the category.
+        // The '--no-*' flags subtract a category afterwards.
+        use_lower: (args.use_lower
+            || args.alnum
+            || args.letters
+            || preset_has(generator::CharClass::Lower)
+            || use_all
+            || args.min_lower.is_some_and(|n| n > 0))
+            && !args.no_lower,
+        use_upper: (args.use_upper
+            || args.alnum
+            || args.letters
+            || preset_has(generator::CharClass::Upper)
+            || use_all
+            || args.min_upper.is_some_and(|n| n > 0))
+            && !args.no_upper,
+        use_digits: (args.use_digits
+            || args.alnum
+            || preset_has(generator::CharClass::Digit)
+            || use_all
+            || pin_mode
+            || args.min_digits.is_some_and(|n| n > 0))
+            && !args.no_digits,
+        use_symbols: (args.use_symbols
+            || preset_has(generator::CharClass::Symbol)
+            || use_all
+            || args.min_symbols.is_some_and(|n| n > 0))
+            && !args.no_symbols,
+        custom_chars: (!args.chars.is_empty()).then(|| args.chars.concat()),
+        // Optional characters extend the pool, so they do not replace the default categories.
+        optional_chars: (!args.chars_optional.is_empty()).then(|| args.chars_optional.concat()),
+        symbols_set: args.symbols_set.clone(),
+        exclude_chars: args.exclude.clone(),
+        avoid_ambiguous: args.avoid_ambiguous,
+        min_lower: args.min_lower.unwrap_or(0),
+        min_upper: args.min_upper.unwrap_or(0),
+        min_digits: args.min_digits.unwrap_or(0),
+        min_symbols: args.min_symbols.unwrap_or(0),
+        max_lower: args.max_lower,
+        max_upper: args.max_upper,
+        max_digits: args.max_digits,
+        max_symbols: args.max_symbols,
+        pattern: args.pattern.clone(),
+        pronounceable: args.pronounceable,
+        memorable: args.memorable,
+        charset,
+        uuid: args.uuid,
+        min_score: args.min_score,
+        max_attempts: Some(args.max_attempts),
+        check_pwned: args.check_pwned,
+        pwned_api_url: None,
+        blacklist: Vec::new(),
+        min_entropy: args.min_entropy,
+        bits: args.bits,
+        length_limit: Some(args.max_length),
+        auto_length: args.auto_length,
+        uniform: args.uniform,
+        weights: args.weight.clone(),
+        emoji: args.emoji.unwrap_or(0),
+        unique: args.unique,
+        no_words: args.no_words,
+        no_repeat: args.no_repeat,
+        no_consecutive_identical: args.no_consecutive_identical,
+        no_sequential: args.no_sequential,
+        start_with_letter: args.start_with_letter,
+        no_edge_symbols: args.no_edge_symbols,
+        no_keyboard_walks: args.no_keyboard_walks,
+        leet: args.leet.clone().map(|map| generator::Leet {
+            map,
+            probability: args.leet_probability,
+        }),
+        jobs: job_count(args.jobs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(argv: &[&str]) -> (Args, ArgMatches) {
+        let matches = Args::command().try_get_matches_from(argv).unwrap();
+        (Args::from_arg_matches(&matches).unwrap(), matches)
+    }
+
+    #[test]
+    fn match_count_takes_the_number_of_users() {
+        let (mut args, matches) = parse(&["pwgen", "--htpasswd", "alice", "--htpasswd", "bob"]);
+        assert_eq!(
+            match_count(&mut args, &matches, 2, "htpasswd users"),
+            Ok(())
+        );
+        assert_eq!(args.count, 2);
+    }
+
+    #[test]
+    fn match_count_rejects_a_mismatched_count() {
+        let (mut args, matches) = parse(&["pwgen", "--count", "3", "--htpasswd", "alice"]);
+        assert_eq!(
+            match_count(&mut args, &matches, 1, "htpasswd users"),
+            Err("--count is 3, but 1 htpasswd users were given.".to_string())
+        );
+    }
+
+    // The long options clap shows, which every generated document must list.
+    fn long_options() -> Vec<String> {
+        let mut command = Args::command();
+        command.build();
+        command
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .filter_map(|arg| arg.get_long())
+            .map(|long| format!("--{}", long))
+            .collect()
+    }
+
+    #[test]
+    fn bash_completions_list_every_long_option() {
+        let mut script = Vec::new();
+        completions::generate(
+            completions::Shell::Bash,
+            &Args::command(),
+            "pwgen",
+            &[("preset", vec!["team".to_string()])],
+            &mut script,
+        )
+        .unwrap();
+        let script = String::from_utf8(script).unwrap();
+        assert!(!script.is_empty());
+        let words: std::collections::HashSet<&str> = script
+            .split(|c: char| c.is_whitespace() || c == '"')
+            .collect();
+        for option in long_options() {
+            assert!(words.contains(option.as_str()), "{option}");
+        }
+        assert!(words.contains("team"));
+    }
+
+    #[test]
+    fn man_page_lists_every_long_option() {
+        let mut page = Vec::new();
+        manpage::generate(&Args::command(), "pwgen", &mut page).unwrap();
+        let page = String::from_utf8(page).unwrap();
+        assert!(page.lines().any(|line| line.starts_with(".TH pwgen 1")));
+        for option in long_options() {
+            // roff escapes every dash.
+            let escaped = format!("\\fB{}\\fR", option.replace('-', "\\-"));
+            assert!(page.contains(&escaped), "{option}");
+        }
+    }
+
+    // Write `content` to a config file of its own, returning its path.
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("pwgen-test-{}-{}.toml", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    // The settings and presets of a config file, as `parse_args` reads them.
+    fn read_config(
+        name: &str,
+        content: &str,
+    ) -> Result<(Vec<Setting>, preset::Presets), Box<dyn std::error::Error>> {
+        let path = config_file(name, content);
+        let command = Args::command();
+        let matches = command
+            .clone()
+            .try_get_matches_from(["pwgen", "--config", path.to_str().unwrap()])
+            .unwrap();
+        let mut presets = preset::Presets::builtin();
+        let settings = config_settings(&command, &matches, &mut presets);
+        fs::remove_file(&path).unwrap();
+        settings.map(|settings| (settings, presets))
+    }
+
+    #[test]
+    fn config_presets_can_be_looked_up() {
+        let (settings, presets) = read_config(
+            "presets",
+            "preset = 'vendor'\n\n[presets]\nvendor = 'abc123'\n",
+        )
+        .unwrap();
+        assert_eq!(presets.get("vendor").unwrap().chars(), "abc123");
+        assert!(presets.get("hex").is_ok());
+        assert!(presets.get("missing").is_err());
+        assert_eq!(settings.len(), 1);
+        assert_eq!(settings[0].id, "preset");
+        assert_eq!(
+            settings[0].value,
+            config::Value::String("vendor".to_string())
+        );
+    }
+
+    #[test]
+    fn config_rejects_unknown_keys() {
+        let err = read_config("unknown", "length = 12\nlenght = 14\n")
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            err.ends_with(":2: key 'lenght': This is not a known option."),
+            "{err}"
+        );
+
+        let err = read_config("cli-only", "no_config = true\n").err().unwrap();
+        assert!(
+            err.to_string().ends_with("This is not a known option."),
+            "{err}"
+        );
+    }
+
+    // `parse_args` with the given command line and `PWGEN_*` variables.
+    fn parse_with_env(argv: &[&str], env: &[(&str, &str)]) -> (Args, Origins) {
+        let argv = argv.iter().map(OsString::from).collect();
+        let env: HashMap<String, OsString> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect();
+        let (matches, _, origins) = parse_args_from(argv, |name| env.get(name).cloned()).unwrap();
+        (Args::from_arg_matches(&matches).unwrap(), origins)
+    }
+
+    #[test]
+    fn flags_override_environment_variables() {
+        let (args, origins) = parse_with_env(
+            &["pwgen", "--no-config", "--length", "12"],
+            &[("PWGEN_LENGTH", "24"), ("PWGEN_COUNT", "3")],
+        );
+        assert_eq!(args.length.min, 12);
+        assert_eq!(origins["length"], "--length");
+        assert_eq!(args.count, 3);
+        assert_eq!(origins["count"], "PWGEN_COUNT");
+    }
+
+    #[test]
+    fn environment_variables_override_config_defaults() {
+        let path = config_file("precedence", "length = 20\ncount = 4\n");
+        let (args, origins) = parse_with_env(
+            &["pwgen", "--config", path.to_str().unwrap()],
+            &[("PWGEN_LENGTH", "24")],
+        );
+        fs::remove_file(&path).unwrap();
+        assert_eq!(args.length.min, 24);
+        assert_eq!(origins["length"], "PWGEN_LENGTH");
+        assert_eq!(args.count, 4);
+        assert_eq!(
+            origins["count"],
+            format!("{}:2: key 'count'", path.display())
+        );
+    }
+
+    #[test]
+    fn length_errors_name_where_the_length_was_set() {
+        let (args, origins) = parse_with_env(&["pwgen", "--no-config"], &[("PWGEN_LENGTH", "4")]);
+        assert_eq!(
+            check_length_bounds(&args, &origins),
+            Err(format!(
+                "PWGEN_LENGTH: The password length must be at least {}. Use --allow-short for shorter passwords.",
+                validation::MIN_LENGTH
+            ))
+        );
+
+        let path = config_file(
+            "max-length",
+            "max_length = 30
+",
+        );
+        let (args, origins) = parse_with_env(
+            &["pwgen", "--config", path.to_str().unwrap(), "-L", "40"],
+            &[],
+        );
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            check_length_bounds(&args, &origins),
+            Err("--length: The password length 40 is above the maximum of 30. Use --max-length to raise it.".to_string())
+        );
+    }
+
+    // Check the output size of the batch a command line configures, without generating it.
+    fn output_size(argv: &[&str]) -> Result<(), String> {
+        let (args, _) = parse(argv);
+        let config = password_config(&args, None);
+        check_output_size(&config, &args).map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn batches_over_a_gibibyte_are_refused() {
+        // Each password of 8 ASCII characters and its newline take 9 bytes.
+        let largest = (LARGE_OUTPUT / 9).to_string();
+        let over = (LARGE_OUTPUT / 9 + 1).to_string();
+        let max_count = ["--max-count", "1000m"];
+        assert_eq!(
+            output_size(&[&["pwgen", "-L", "8", "-C", &largest], &max_count[..]].concat()),
+            Ok(())
+        );
+        assert_eq!(
+            output_size(&[&["pwgen", "-L", "8", "-C", &over], &max_count[..]].concat()),
+            Err(format!(
+                "The passwords would take up to 1.0 GiB ({} of up to 8 characters). Use --force-large to write them anyway.",
+                over
+            ))
+        );
+    }
+
+    #[test]
+    fn the_output_size_counts_the_longest_password_in_its_widest_characters() {
+        // Passwords of up to 16 characters, of 3 bytes each for '€', and a newline.
+        let largest = (LARGE_OUTPUT / 49).to_string();
+        let over = (LARGE_OUTPUT / 49 + 1).to_string();
+        let unicode = ["pwgen", "-L", "12-16", "--unicode", "-c", "ab€"];
+        assert_eq!(
+            output_size(&[&unicode[..], &["-C", &largest]].concat()),
+            Ok(())
+        );
+        assert!(
+            output_size(&[&unicode[..], &["-C", &over]].concat())
+                .unwrap_err()
+                .contains(&format!("({} of up to 16 characters)", over))
+        );
+        assert_eq!(
+            output_size(&["pwgen", "-L", "16", "-c", "ab", "-C", &over]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn force_large_skips_the_output_size_check() {
+        let large = ["pwgen", "-C", "2m", "-L", "1024"];
+        assert!(output_size(&large).is_err());
+        assert_eq!(
+            output_size(&[&large[..], &["--force-large"]].concat()),
+            Ok(())
+        );
+    }
+
+    // Passwords generated from a command line, with a seeded generator.
+    fn seeded_passwords(argv: &[&str], seed: u64) -> Vec<String> {
+        let (args, _) = parse(argv);
+        let config = password_config(&args, None);
+        generator::generate_with_rng(&config, &mut StdRng::seed_from_u64(seed)).unwrap()
+    }
+
+    #[test]
+    fn letters_never_include_digits_or_symbols() {
+        let (args, _) = parse(&["pwgen", "-w"]);
+        let generator = generator::PasswordGenerator::new(&password_config(&args, None)).unwrap();
+        assert_eq!(generator.charset().len(), 52);
+
+        for password in seeded_passwords(&["pwgen", "-w", "-L", "24", "-C", "2000"], 99) {
+            assert!(
+                password.chars().all(|c| c.is_ascii_alphabetic()),
+                "{password}"
+            );
+        }
+        let argv = [
+            "pwgen",
+            "--letters",
+            "-B",
+            "--exclude",
+            "xyz",
+            "-L",
+            "24",
+            "-C",
+            "2000",
+        ];
+        for password in seeded_passwords(&argv, 99) {
+            assert!(
+                password.chars().all(|c| c.is_ascii_alphabetic()
+                    && !"xyz".contains(c)
+                    && !generator::AMBIGUOUS.contains(c)),
+                "{password}"
+            );
+        }
+    }
+
+    // The categories that a command line enables, as lower, upper, digits, and symbols.
+    fn categories(argv: &[&str]) -> [bool; 4] {
+        let (args, _) = parse(argv);
+        let config = password_config(&args, None);
+        [
+            config.use_lower,
+            config.use_upper,
+            config.use_digits,
+            config.use_symbols,
+        ]
+    }
+
+    #[test]
+    fn negations_subtract_from_all() {
+        assert_eq!(categories(&["pwgen", "--all"]), [true; 4]);
+        assert_eq!(
+            categories(&["pwgen", "--all", "--no-symbols"]),
+            [true, true, true, false]
+        );
+        assert_eq!(
+            categories(&["pwgen", "-a", "--no-lower", "--no-digits"]),
+            [false, true, false, true]
+        );
+        // '--all' brings back the categories that naming one would leave out.
+        assert_eq!(
+            categories(&["pwgen", "--all", "-d", "--no-upper"]),
+            [true, false, true, true]
+        );
+    }
+
+    #[test]
+    fn negations_subtract_from_the_defaults() {
+        assert_eq!(categories(&["pwgen"]), [true; 4]);
+        assert_eq!(
+            categories(&["pwgen", "--no-digits"]),
+            [true, true, false, true]
+        );
+        assert_eq!(
+            categories(&["pwgen", "--no-upper", "--no-symbols"]),
+            [true, false, true, false]
+        );
+        // Naming categories replaces the defaults, and negations apply after.
+        assert_eq!(
+            categories(&["pwgen", "--alnum", "--no-upper"]),
+            [true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn negations_conflict_with_their_categories_and_can_leave_nothing() {
+        for (flag, negation) in [
+            ("-l", "--no-lower"),
+            ("-u", "--no-upper"),
+            ("-d", "--no-digits"),
+            ("-s", "--no-symbols"),
+        ] {
+            let err = Args::command()
+                .try_get_matches_from(["pwgen", flag, negation])
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+        }
+
+        let (args, _) = parse(&[
+            "pwgen",
+            "--all",
+            "--no-lower",
+            "--no-upper",
+            "--no-digits",
+            "--no-symbols",
+        ]);
+        let err = generator::PasswordGenerator::new(&password_config(&args, None))
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string().starts_with("No character sets selected."),
+            "{err}"
+        );
+    }
+
+    // `argv` with the subcommands that stand for options expanded.
+    fn expand(argv: &[&str]) -> Vec<String> {
+        let mut command = Args::command();
+        command.build();
+        let mut argv: Vec<OsString> = argv.iter().map(OsString::from).collect();
+        expand_subcommand(&command, &mut argv);
+        argv.iter()
+            .map(|arg| arg.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn flag_only_command_lines_parse_as_before() {
+        assert_eq!(
+            expand(&["pwgen", "-L", "16", "-a"]),
+            ["pwgen", "-L", "16", "-a"]
+        );
+        let (args, _) = parse(&["pwgen", "-L", "16", "-a"]);
+        assert_eq!(args.length.min, 16);
+        assert!(args.all);
+        assert!(args.command.is_none());
+        assert!(!args.passphrase);
+    }
+
+    #[test]
+    fn generate_and_passphrase_expand_to_their_options() {
+        assert_eq!(
+            expand(&["pwgen", "generate", "-L", "16", "-a"]),
+            ["pwgen", "-L", "16", "-a"]
+        );
+        assert_eq!(
+            expand(&["pwgen", "-L", "16", "generate", "-a"]),
+            ["pwgen", "-L", "16", "-a"]
+        );
+        assert_eq!(
+            expand(&["pwgen", "passphrase", "--words", "5"]),
+            ["pwgen", "--passphrase", "--words", "5"]
+        );
+
+        let argv = expand(&["pwgen", "passphrase", "--words", "5"]);
+        let (args, _) = parse(&argv.iter().map(String::as_str).collect::<Vec<_>>());
+        assert!(args.passphrase);
+        assert_eq!(args.words, 5);
+        assert!(args.command.is_none());
+    }
+
+    #[test]
+    fn subcommand_names_as_values_stay_values() {
+        for argv in [
+            &["pwgen", "-c", "generate"][..],
+            &["pwgen", "--separator", "passphrase", "--passphrase"],
+            &["pwgen", "--", "generate"],
+            &["pwgen", "check"],
+        ] {
+            assert_eq!(expand(argv), argv);
+        }
+        let (args, _) = parse(&["pwgen", "check"]);
+        assert!(matches!(args.command, Some(Command::Check)));
+    }
+
+    // A path in the temporary directory that no file takes up yet.
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pwgen-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    // The content of `path` after appending `line` to it, as `--append` does.
+    fn append_line(path: &Path, line: &str) -> String {
+        let mut file = open_for_append(path.to_str().unwrap()).unwrap();
+        file.write_all(line.as_bytes()).unwrap();
+        drop(file);
+        let content = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        content
+    }
+
+    #[test]
+    fn appending_creates_a_missing_file() {
+        let path = temp_path("append-missing");
+        assert_eq!(append_line(&path, "new\n"), "new\n");
+    }
+
+    #[test]
+    fn appending_to_an_empty_file_adds_no_newline() {
+        let path = temp_path("append-empty");
+        fs::write(&path, "").unwrap();
+        assert_eq!(append_line(&path, "new\n"), "new\n");
+    }
+
+    #[test]
+    fn appending_ends_the_last_line_first() {
+        let path = temp_path("append-unterminated");
+        fs::write(&path, "old").unwrap();
+        assert_eq!(append_line(&path, "new\n"), "old\nnew\n");
+
+        fs::write(&path, "old\n").unwrap();
+        assert_eq!(append_line(&path, "new\n"), "old\nnew\n");
+    }
+
+    // Write `line` to `path` with the given flags, as `run` does.
+    fn write_output(path: &Path, append: bool, force: bool, line: &str) -> Result<(), String> {
+        let mut output =
+            open_output(path.to_str().unwrap(), append, force).map_err(|err| err.to_string())?;
+        output.write_all(line.as_bytes()).unwrap();
+        match output {
+            Output::Atomic(file) => file.persist().unwrap(),
+            mut output => output.flush().unwrap(),
+        }
+        Ok(())
+    }
+
+    // The outcome of writing `line` with the given flags to `path`, which holds `old\n`, with
+    // the content of the file afterwards.
+    fn write_existing(
+        path: &Path,
+        append: bool,
+        force: bool,
+        line: &str,
+    ) -> (Result<(), String>, String) {
+        fs::write(path, "old\n").unwrap();
+        let result = write_output(path, append, force, line);
+        let content = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        (result, content)
+    }
+
+    #[test]
+    fn append_never_truncates() {
+        let path = temp_path("append-existing");
+        for force in [false, true] {
+            assert_eq!(
+                write_existing(&path, true, force, "new\n"),
+                (Ok(()), "old\nnew\n".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn force_truncates() {
+        let path = temp_path("force-existing");
+        assert_eq!(
+            write_existing(&path, false, true, "new\n"),
+            (Ok(()), "new\n".to_string())
+        );
+    }
+
+    #[test]
+    fn existing_files_are_kept_without_append_or_force() {
+        let path = temp_path("neither-existing");
+        let display = path.display();
+        assert_eq!(
+            write_existing(&path, false, false, "new\n"),
+            (
+                Err(format!(
+                    "The output file '{display}' already exists. Use --force to overwrite it or --append to add to it."
+                )),
+                "old\n".to_string()
+            )
+        );
+        // A new file needs neither flag.
+        write_output(&path, false, false, "new\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_files_are_created_readable_by_the_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let write =
+            |path: &Path, append, force| write_output(path, append, force, "new\n").unwrap();
+
+        for (name, append) in [("mode-new", false), ("mode-append", true)] {
+            let path = temp_path(name);
+            write(&path, append, false);
+            assert_eq!(mode(&path), 0o600, "{name}");
+            fs::remove_file(&path).unwrap();
+        }
+
+        // '--force' replaces the file, and its permissions with it.
+        let path = temp_path("mode-force");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write(&path, false, true);
+        assert_eq!(mode(&path), 0o600);
+
+        // '--append' keeps the existing file and only warns about its permissions.
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write(&path, true, false);
+        assert_eq!(mode(&path), 0o644);
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,11 @@
+//! A roff man page generated from the clap definition by `clap_mangen`.
+
+use std::io::{self, Write};
+
+use clap::Command;
+
+/// Write the man page for `command` to `out`, in section 1.
+pub fn generate(command: &Command, bin_name: &str, out: &mut dyn Write) -> io::Result<()> {
+    let command = command.clone().name(bin_name.to_string());
+    clap_mangen::Man::new(command).section("1").render(out)
+}
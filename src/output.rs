@@ -0,0 +1,1477 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use qrcode::QrCode;
+use qrcode::render::unicode::Dense1x2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use zeroize::Zeroize;
+
+use crate::generator::SecureRng;
+use crate::hash::HashAlgorithm;
+use crate::password_hash::Hashing;
+use crate::phonetic;
+use crate::strength;
+
+/// The output format for generated passwords.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One password per line.
+    #[default]
+    Plain,
+    /// A JSON array of objects with the password, its length, and the enabled categories.
+    Json,
+    /// CSV rows of index, password, and length (RFC 4180).
+    Csv,
+}
+
+/// A line written for each password in place of it, e.g. `export APP_PASS_{n}="{password}"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template(pub Vec<TemplatePart>);
+
+/// A piece of a [`Template`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplatePart {
+    /// Text written as it is.
+    Text(String),
+    /// `{password}`, the password unchanged.
+    Password,
+    /// `{n}` or `{index}`, the 1-based index of the password.
+    Index,
+    /// `{length}`, the number of characters of the password.
+    Length,
+    /// `{entropy}`, the entropy of the password in bits.
+    Entropy,
+}
+
+/// How many passwords to place on each line of plain output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Columns {
+    /// As many as fit in the line width.
+    Auto,
+    /// A fixed number per line.
+    Fixed(usize),
+}
+
+/// How generated passwords are written.
+#[derive(Clone, Debug)]
+pub struct OutputOptions {
+    pub format: Format,
+    /// Write a header row, for formats that have one.
+    pub header: bool,
+    /// The string written after each password in plain output, `"\n"` by default.
+    pub terminator: String,
+    /// Write the terminator after the last password too. When false, the terminator only
+    /// separates passwords.
+    pub final_terminator: bool,
+    /// The number of columns in plain output.
+    pub columns: Columns,
+    /// The line width that [`Columns::Auto`] fills, usually the terminal width.
+    pub width: usize,
+    /// Prefix each password in plain output with its 1-based index.
+    pub numbered: bool,
+    /// Annotate each password with its entropy in bits.
+    pub show_entropy: bool,
+    /// Annotate each password with its zxcvbn strength score and estimated guesses.
+    pub show_strength: bool,
+    /// Also render each password as a QR code on stderr.
+    pub qr: bool,
+    /// Also spell each password out on stderr, e.g. `capital X-ray, kilo, three`.
+    pub phonetic: bool,
+    /// Split each password in plain output into groups of this many characters.
+    pub group: Option<usize>,
+    /// The string placed between groups.
+    pub group_separator: String,
+    /// Color digits and symbols in plain output with ANSI escape codes.
+    pub color: bool,
+    /// Write a digest of each password after it, a tab apart in plain output. Requires the
+    /// `hash` cargo feature.
+    pub hash: Option<HashAlgorithm>,
+    /// Write a salted hash of each password, for storing instead of the password.
+    pub password_hash: Option<Hashing>,
+    /// Pair the passwords with these labels, in order.
+    pub labels: Option<Vec<String>>,
+    /// Show a progress bar on stderr while the passwords are written.
+    pub progress: bool,
+    /// Write this line for each password in plain output instead of the password.
+    pub template: Option<Template>,
+}
+
+/// What a formatter needs to know about the passwords it will write.
+#[derive(Clone, Debug)]
+pub struct Batch {
+    /// The character categories that were enabled, e.g. `lowercase` or `hex`.
+    pub categories: Vec<String>,
+    /// The number of passwords that will be written.
+    pub count: usize,
+    /// The theoretical entropy of each password.
+    pub entropy: Entropy,
+}
+
+/// The theoretical entropy of the passwords in a batch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Entropy {
+    /// Every password has this many bits.
+    Fixed(f64),
+    /// The passwords vary in length: `bits[i]` is the entropy of a password of
+    /// `min_length + i` characters.
+    ByLength { min_length: usize, bits: Vec<f64> },
+}
+
+impl Entropy {
+    /// The entropy in bits of `password`.
+    pub fn bits(&self, password: &str) -> f64 {
+        match self {
+            Entropy::Fixed(bits) => *bits,
+            Entropy::ByLength { min_length, bits } => {
+                let i = password.chars().count().saturating_sub(*min_length);
+                bits[i.min(bits.len() - 1)]
+            }
+        }
+    }
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            format: Format::Plain,
+            header: true,
+            terminator: "\n".to_string(),
+            final_terminator: true,
+            columns: Columns::Fixed(1),
+            width: 80,
+            numbered: false,
+            show_entropy: false,
+            show_strength: false,
+            qr: false,
+            phonetic: false,
+            group: None,
+            group_separator: "-".to_string(),
+            color: false,
+            hash: None,
+            password_hash: None,
+            labels: None,
+            progress: false,
+            template: None,
+        }
+    }
+}
+
+/// A password on its way through the formatters.
+#[derive(Clone, Debug)]
+pub struct Record {
+    /// The generated password, unchanged.
+    pub password: String,
+    /// The password as shown in plain output, e.g. grouped or colored.
+    pub display: String,
+    /// Text shown before the password, such as its index.
+    pub prefix: String,
+    /// Notes shown after the password, such as its entropy.
+    pub annotations: Vec<String>,
+    /// Named values shown after the annotations, each following a tab, such as a digest.
+    /// The other formats write them as fields.
+    pub fields: Vec<(&'static str, String)>,
+    /// The label paired with the password, shown before it and a tab.
+    pub label: Option<String>,
+}
+
+// A record holds the password in several forms, which are wiped once it is written.
+impl Drop for Record {
+    fn drop(&mut self) {
+        self.password.zeroize();
+        self.display.zeroize();
+        self.prefix.zeroize();
+        self.annotations.zeroize();
+        for (_, value) in &mut self.fields {
+            value.zeroize();
+        }
+        self.label.zeroize();
+    }
+}
+
+impl Record {
+    pub fn new(password: String) -> Self {
+        Self {
+            display: password.clone(),
+            password,
+            prefix: String::new(),
+            annotations: Vec::new(),
+            fields: Vec::new(),
+            label: None,
+        }
+    }
+
+    /// The full line shown in plain output.
+    pub fn line(&self) -> String {
+        let mut line = match &self.label {
+            Some(label) => format!("{}\t{}{}", label, self.prefix, self.display),
+            None => format!("{}{}", self.prefix, self.display),
+        };
+        for annotation in &self.annotations {
+            line.push_str(&format!("  ({})", annotation));
+        }
+        for (_, value) in &self.fields {
+            line.push('\t');
+            line.push_str(value);
+        }
+        line
+    }
+}
+
+/// Writes generated passwords in a particular format.
+pub trait Formatter {
+    /// Write anything that precedes the first record.
+    fn begin(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Write a single password.
+    fn write_record(&mut self, record: Record) -> io::Result<()>;
+
+    /// Write anything that follows the last record and flush the output.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Create the formatter described by `options`, writing to `out`.
+///
+/// Password hashes are salted from a generator seeded from `rng`, which is only drawn from
+/// when `options` asks for them.
+pub fn formatter<'a, R: SecureRng + ?Sized>(
+    options: &OutputOptions,
+    out: &'a mut dyn Write,
+    batch: &Batch,
+    rng: &mut R,
+) -> Box<dyn Formatter + 'a> {
+    let mut formatter = base_formatter(options, out, batch);
+
+    // The decorators read the unchanged password and only touch their own part of the
+    // record. Plain output shows the annotations inline; the other formats record them
+    // as fields.
+    if let Some(labels) = &options.labels {
+        formatter = Box::new(LabelFormatter::new(formatter, labels.clone()));
+    }
+    if let Some(hashing) = &options.password_hash {
+        formatter = Box::new(PasswordHashFormatter::new(
+            formatter,
+            hashing.clone(),
+            StdRng::from_seed(rng.r#gen()),
+        ));
+    }
+    #[cfg(feature = "hash")]
+    if let Some(algorithm) = options.hash {
+        formatter = Box::new(HashFormatter::new(formatter, algorithm));
+    }
+    if options.format == Format::Plain {
+        if let Some(template) = &options.template {
+            formatter = Box::new(TemplateFormatter::new(
+                formatter,
+                template.clone(),
+                batch.entropy.clone(),
+            ));
+        }
+        if options.show_strength {
+            formatter = Box::new(StrengthFormatter::new(formatter));
+        }
+        if options.show_entropy {
+            formatter = Box::new(EntropyFormatter::new(formatter, batch.entropy.clone()));
+        }
+        if options.numbered {
+            formatter = Box::new(NumberedFormatter::new(formatter, batch.count));
+        }
+        if let Some(size) = options.group {
+            formatter = Box::new(GroupFormatter::new(
+                formatter,
+                size,
+                options.group_separator.clone(),
+            ));
+        }
+        if options.color {
+            formatter = Box::new(ColorFormatter::new(formatter));
+        }
+    }
+
+    if options.phonetic {
+        formatter = Box::new(PhoneticFormatter::new(formatter, Box::new(io::stderr())));
+    }
+    if options.qr {
+        formatter = Box::new(QrFormatter::new(formatter, Box::new(io::stderr())));
+    }
+    if options.progress {
+        formatter = Box::new(ProgressFormatter::new(
+            formatter,
+            Box::new(io::stderr()),
+            batch.count,
+        ));
+    }
+    formatter
+}
+
+fn base_formatter<'a>(
+    options: &OutputOptions,
+    out: &'a mut dyn Write,
+    batch: &Batch,
+) -> Box<dyn Formatter + 'a> {
+    let fields = ExtraFields {
+        entropy: Some(batch.entropy.clone()).filter(|_| options.show_entropy),
+        strength: options.show_strength,
+        label: options.labels.is_some(),
+        // In the order the decorators add them.
+        names: options
+            .hash
+            .map(HashAlgorithm::name)
+            .into_iter()
+            .chain(
+                options
+                    .password_hash
+                    .as_ref()
+                    .filter(|hashing| !hashing.hash_only)
+                    .map(|_| PASSWORD_HASH_FIELD),
+            )
+            .collect(),
+    };
+
+    match options.format {
+        Format::Plain if options.columns != Columns::Fixed(1) => {
+            Box::new(ColumnFormatter::new(out, options.columns, options.width))
+        }
+        Format::Plain => Box::new(PlainFormatter::new(
+            out,
+            options.terminator.clone(),
+            options.final_terminator,
+        )),
+        Format::Json => Box::new(JsonFormatter::new(out, &batch.categories, fields)),
+        Format::Csv => Box::new(CsvFormatter::new(out, options.header, fields)),
+    }
+}
+
+// Set by `set_quiet`, which silences the warnings and notes on stderr.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Silence [`note`], as `--quiet` does.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Print a warning or note to stderr, unless [`set_quiet`] silenced them.
+pub fn note(message: impl fmt::Display) {
+    if !QUIET.load(Ordering::Relaxed) {
+        eprintln!("{}", message);
+    }
+}
+
+/// Write every password from `passwords` through `formatter`, stopping at the first error.
+pub fn write_all<E: From<io::Error>>(
+    passwords: impl Iterator<Item = Result<String, E>>,
+    formatter: &mut dyn Formatter,
+) -> Result<(), E> {
+    formatter.begin()?;
+    for password in passwords {
+        formatter.write_record(Record::new(password?))?;
+    }
+    Ok(formatter.finish()?)
+}
+
+/// Each password followed by a terminator, one per line by default.
+pub struct PlainFormatter<'a> {
+    out: &'a mut dyn Write,
+    terminator: String,
+    final_terminator: bool,
+    records: usize,
+}
+
+impl<'a> PlainFormatter<'a> {
+    pub fn new(out: &'a mut dyn Write, terminator: String, final_terminator: bool) -> Self {
+        Self {
+            out,
+            terminator,
+            final_terminator,
+            records: 0,
+        }
+    }
+}
+
+impl Formatter for PlainFormatter<'_> {
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        self.records += 1;
+        let mut line = record.line();
+
+        let result = if self.final_terminator {
+            write!(self.out, "{}{}", line, self.terminator)
+        } else if self.records == 1 {
+            write!(self.out, "{}", line)
+        } else {
+            // Without a final terminator, the terminator goes before every password but the first.
+            write!(self.out, "{}{}", self.terminator, line)
+        };
+        line.zeroize();
+        result
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Passwords laid out in a grid of fixed-width columns, like the classic pwgen.
+///
+/// The passwords are buffered so the column width can fit the longest one.
+pub struct ColumnFormatter<'a> {
+    out: &'a mut dyn Write,
+    columns: Columns,
+    width: usize,
+    passwords: Vec<String>,
+}
+
+impl<'a> ColumnFormatter<'a> {
+    pub fn new(out: &'a mut dyn Write, columns: Columns, width: usize) -> Self {
+        Self {
+            out,
+            columns,
+            width,
+            passwords: Vec::new(),
+        }
+    }
+}
+
+impl Formatter for ColumnFormatter<'_> {
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        self.passwords.push(record.line());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        const GAP: usize = 1;
+
+        let cell_width = self
+            .passwords
+            .iter()
+            .map(|password| display_width(password))
+            .max()
+            .unwrap_or(0);
+
+        // A password wider than the line still gets a line of its own.
+        let columns = match self.columns {
+            Columns::Auto => ((self.width + GAP) / (cell_width + GAP)).max(1),
+            Columns::Fixed(n) => n.max(1),
+        };
+
+        for row in self.passwords.chunks(columns) {
+            let last = row.len() - 1;
+            for (i, password) in row.iter().enumerate() {
+                if i == last {
+                    writeln!(self.out, "{}", password)?;
+                } else {
+                    // Pad by hand, since color codes take no space on the terminal.
+                    let padding = cell_width + GAP - display_width(password);
+                    write!(self.out, "{}{}", password, " ".repeat(padding))?;
+                }
+            }
+        }
+        self.passwords.zeroize();
+        self.out.flush()
+    }
+}
+
+/// Pairs each password with the next label before passing it on to another formatter.
+///
+/// Passwords beyond the last label are passed on unlabeled.
+pub struct LabelFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    labels: std::vec::IntoIter<String>,
+}
+
+impl<'a> LabelFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, labels: Vec<String>) -> Self {
+        Self {
+            inner,
+            labels: labels.into_iter(),
+        }
+    }
+}
+
+impl Formatter for LabelFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        record.label = self.labels.next();
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Prefixes each password with its 1-based index before passing it on to another formatter.
+///
+/// The index is right-aligned to the width of the largest index, so the passwords line up.
+pub struct NumberedFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    width: usize,
+    records: usize,
+}
+
+impl<'a> NumberedFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, count: usize) -> Self {
+        Self {
+            inner,
+            width: count.max(1).to_string().len(),
+            records: 0,
+        }
+    }
+}
+
+impl Formatter for NumberedFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        self.records += 1;
+
+        record.prefix = format!(
+            "{:>width$}: {}",
+            self.records,
+            record.prefix,
+            width = self.width
+        );
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Inserts a separator every `size` characters of each password before passing it on to
+/// another formatter, to make long passwords easier to transcribe.
+pub struct GroupFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    size: usize,
+    separator: String,
+}
+
+impl<'a> GroupFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, size: usize, separator: String) -> Self {
+        Self {
+            inner,
+            size: size.max(1),
+            separator,
+        }
+    }
+}
+
+impl Formatter for GroupFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        let grouped = visible_chars(&record.display)
+            .chunks(self.size)
+            .map(|group| group.concat())
+            .collect::<Vec<_>>()
+            .join(&self.separator);
+        std::mem::replace(&mut record.display, grouped).zeroize();
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Colors each digit and symbol of a password with ANSI escape codes before passing it on to
+/// another formatter. Letters are left plain.
+pub struct ColorFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+}
+
+impl<'a> ColorFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Formatter for ColorFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        let mut colored = String::with_capacity(record.display.len() * 4);
+        for c in record.display.chars() {
+            let color = if c.is_ascii_digit() {
+                Some(DIGIT_COLOR)
+            } else if c.is_alphabetic() {
+                None
+            } else {
+                Some(SYMBOL_COLOR)
+            };
+
+            match color {
+                Some(color) => {
+                    colored.push_str(color);
+                    colored.push(c);
+                    colored.push_str(RESET);
+                }
+                None => colored.push(c),
+            }
+        }
+        std::mem::replace(&mut record.display, colored).zeroize();
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+const DIGIT_COLOR: &str = "\x1b[34m";
+const SYMBOL_COLOR: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+// Split a string into the pieces that each show one character on the terminal. A color code
+// stays with the character it colors and a reset with the character before it.
+fn visible_chars(s: &str) -> Vec<&str> {
+    // The byte offset where each piece ends.
+    let mut ends: Vec<usize> = Vec::new();
+    let mut i = 0;
+
+    while i < s.len() {
+        let rest = &s[i..];
+        if rest.starts_with('\x1b') {
+            let len = rest.find('m').map_or(rest.len(), |end| end + 1);
+            if rest[..len] == *RESET
+                && let Some(end) = ends.last_mut()
+                && *end == i
+            {
+                *end += len;
+            }
+            i += len;
+        } else {
+            i += rest.chars().next().unwrap().len_utf8();
+            ends.push(i);
+        }
+    }
+
+    // Trailing codes without a character of their own stay with the last piece.
+    match ends.last_mut() {
+        Some(end) => *end = s.len(),
+        None if !s.is_empty() => ends.push(s.len()),
+        None => {}
+    }
+
+    let mut start = 0;
+    ends.iter()
+        .map(|&end| {
+            let piece = &s[start..end];
+            start = end;
+            piece
+        })
+        .collect()
+}
+
+// The terminal cells `s` takes. Emoji take two, and color codes none.
+fn display_width(s: &str) -> usize {
+    visible_chars(s)
+        .iter()
+        .map(|piece| {
+            if piece
+                .chars()
+                .any(|c| ('\u{1F300}'..='\u{1FAFF}').contains(&c))
+            {
+                2
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+/// Notes the entropy in bits of each password before passing it on to another formatter.
+pub struct EntropyFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    entropy: Entropy,
+}
+
+impl<'a> EntropyFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, entropy: Entropy) -> Self {
+        Self { inner, entropy }
+    }
+}
+
+impl Formatter for EntropyFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        record
+            .annotations
+            .push(format!("{:.1} bits", self.entropy.bits(&record.password)));
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Adds a hex digest of each password before passing it on to another formatter.
+#[cfg(feature = "hash")]
+pub struct HashFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    algorithm: HashAlgorithm,
+}
+
+#[cfg(feature = "hash")]
+impl<'a> HashFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, algorithm: HashAlgorithm) -> Self {
+        Self { inner, algorithm }
+    }
+}
+
+#[cfg(feature = "hash")]
+impl Formatter for HashFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        // The digest covers the password as generated, not as grouped or colored.
+        let digest = self.algorithm.digest(record.password.as_bytes());
+        record.fields.push((self.algorithm.name(), digest));
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// The field that holds the password hash in JSON and CSV output.
+const PASSWORD_HASH_FIELD: &str = "password_hash";
+
+/// Adds a salted hash of each password before passing it on to another formatter, or puts
+/// the hash in place of the password.
+///
+/// Hashing is slow by design, so with more than one job the records are held back and
+/// hashed a batch at a time on separate threads. The salts are drawn in record order, so
+/// the output does not depend on the number of jobs.
+pub struct PasswordHashFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    hashing: Hashing,
+    rng: StdRng,
+    pending: Vec<(Record, Vec<u8>)>,
+}
+
+impl<'a> PasswordHashFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, hashing: Hashing, rng: StdRng) -> Self {
+        Self {
+            inner,
+            hashing,
+            rng,
+            pending: Vec::new(),
+        }
+    }
+
+    fn write_pending(&mut self) -> io::Result<()> {
+        let algorithm = self.hashing.algorithm;
+        let chunk_len = self.pending.len().div_ceil(self.hashing.jobs.max(1)).max(1);
+        let hashes: Vec<String> = thread::scope(|scope| {
+            let workers: Vec<_> = self
+                .pending
+                .chunks(chunk_len)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(record, salt)| algorithm.hash(record.password.as_bytes(), salt))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("hash worker panicked"))
+                .collect()
+        });
+
+        for ((mut record, _), hash) in self.pending.drain(..).zip(hashes) {
+            if self.hashing.hash_only {
+                record.display.zeroize();
+                record.display = hash;
+            } else {
+                record.fields.push((PASSWORD_HASH_FIELD, hash));
+            }
+            self.inner.write_record(record)?;
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for PasswordHashFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        let mut salt = vec![0u8; self.hashing.algorithm.salt_len()];
+        self.rng.fill(&mut salt[..]);
+        self.pending.push((record, salt));
+        if self.pending.len() >= self.hashing.jobs {
+            self.write_pending()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.write_pending()?;
+        self.inner.finish()
+    }
+}
+
+/// Renders each password as a QR code on a separate stream before passing it on to another
+/// formatter, so the password output itself stays unchanged.
+pub struct QrFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    qr_out: Box<dyn Write + 'a>,
+    records: usize,
+}
+
+impl<'a> QrFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, qr_out: Box<dyn Write + 'a>) -> Self {
+        Self {
+            inner,
+            qr_out,
+            records: 0,
+        }
+    }
+}
+
+impl Formatter for QrFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        let code = qr_code(&record.password).map_err(io::Error::other)?;
+        // Light modules on a dark background, which scans well on typical dark terminals.
+        let image = code
+            .render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build();
+
+        // Codes are separated by a blank line.
+        if self.records > 0 {
+            writeln!(self.qr_out)?;
+        }
+        self.records += 1;
+        writeln!(self.qr_out, "{}", image)?;
+        self.qr_out.flush()?;
+
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Replaces each password in plain output with its [`Template`] filled in. The password is
+/// inserted verbatim, so quoting it for a shell or config file is up to the template.
+pub struct TemplateFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    template: Template,
+    entropy: Entropy,
+    records: usize,
+}
+
+impl<'a> TemplateFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, template: Template, entropy: Entropy) -> Self {
+        Self {
+            inner,
+            template,
+            entropy,
+            records: 0,
+        }
+    }
+}
+
+impl Formatter for TemplateFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        self.records += 1;
+        let mut line = String::new();
+        for part in &self.template.0 {
+            match part {
+                TemplatePart::Text(text) => line.push_str(text),
+                TemplatePart::Password => line.push_str(&record.password),
+                TemplatePart::Index => line.push_str(&self.records.to_string()),
+                TemplatePart::Length => line.push_str(&record.password.chars().count().to_string()),
+                TemplatePart::Entropy => {
+                    line.push_str(&format!("{:.1}", self.entropy.bits(&record.password)))
+                }
+            }
+        }
+        record.display.zeroize();
+        record.display = line;
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Spells each password out on a line of its own, indented, after passing it on to another
+/// formatter. The lines go to their own writer, so the output keeps only the passwords.
+pub struct PhoneticFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    spelled_out: Box<dyn Write + 'a>,
+}
+
+impl<'a> PhoneticFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>, spelled_out: Box<dyn Write + 'a>) -> Self {
+        Self { inner, spelled_out }
+    }
+}
+
+impl Formatter for PhoneticFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        let mut spelled = phonetic::spell_password(&record.password);
+        self.inner.write_record(record)?;
+        let written = writeln!(self.spelled_out, "  {}", spelled);
+        spelled.zeroize();
+        written?;
+        self.spelled_out.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+// The QR code of `password`. Anything beyond ASCII is marked as UTF-8, which scanners would
+// otherwise read as Latin-1.
+fn qr_code(password: &str) -> qrcode::types::QrResult<QrCode> {
+    use qrcode::bits::Bits;
+    use qrcode::types::{EcLevel, QrError, Version};
+
+    if password.is_ascii() {
+        return QrCode::new(password.as_bytes());
+    }
+    // The smallest version that fits, as `QrCode::new` picks.
+    for version in 1..=40 {
+        let mut bits = Bits::new(Version::Normal(version));
+        // ECI 26 is UTF-8.
+        if bits.push_eci_designator(26).is_ok()
+            && bits.push_byte_data(password.as_bytes()).is_ok()
+            && bits.push_terminator(EcLevel::M).is_ok()
+        {
+            return QrCode::with_bits(bits, EcLevel::M);
+        }
+    }
+    Err(QrError::DataTooLong)
+}
+
+/// Shows the passwords written so far, their rate, and the time left on a terminal line,
+/// after passing each on to another formatter.
+///
+/// The line is redrawn at most every [`ProgressFormatter::INTERVAL`], and cleared once the
+/// batch is done or abandoned.
+pub struct ProgressFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+    progress_out: Box<dyn Write + 'a>,
+    total: usize,
+    records: usize,
+    start: Instant,
+    last_draw: Instant,
+    drawn: bool,
+}
+
+impl<'a> ProgressFormatter<'a> {
+    /// The shortest time between redraws.
+    pub const INTERVAL: Duration = Duration::from_millis(100);
+
+    // Reading the clock for every password would slow down fast batches.
+    const CHECK_EVERY: usize = 64;
+
+    pub fn new(
+        inner: Box<dyn Formatter + 'a>,
+        progress_out: Box<dyn Write + 'a>,
+        total: usize,
+    ) -> Self {
+        let start = Instant::now();
+        Self {
+            inner,
+            progress_out,
+            total,
+            records: 0,
+            start,
+            last_draw: start,
+            drawn: false,
+        }
+    }
+
+    fn draw(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.start).as_secs_f64();
+        let rate = self.records as f64 / elapsed.max(f64::EPSILON);
+        let left = self.total.saturating_sub(self.records) as f64 / rate.max(f64::EPSILON);
+        // Rounded down, so 100% means done.
+        let percent = self.records * 100 / self.total.max(1);
+        // The progress is only a courtesy, so a failed write is not an error.
+        let _ = write!(
+            self.progress_out,
+            "\r{}/{} ({}%), {:.0}/s, ETA {}\x1b[K",
+            self.records,
+            self.total,
+            percent,
+            rate,
+            format_duration(left)
+        )
+        .and_then(|()| self.progress_out.flush());
+        self.last_draw = now;
+        self.drawn = true;
+    }
+
+    fn clear(&mut self) {
+        if self.drawn {
+            let _ = write!(self.progress_out, "\r\x1b[K").and_then(|()| self.progress_out.flush());
+            self.drawn = false;
+        }
+    }
+}
+
+impl Formatter for ProgressFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        self.inner.write_record(record)?;
+        self.records += 1;
+        if self.records.is_multiple_of(Self::CHECK_EVERY) {
+            let now = Instant::now();
+            if now.duration_since(self.last_draw) >= Self::INTERVAL {
+                self.draw(now);
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        // Cleared first, so nothing written after the batch lands on the progress line.
+        self.clear();
+        self.inner.finish()
+    }
+}
+
+impl Drop for ProgressFormatter<'_> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+// Seconds as `M:SS`, or `H:MM:SS` from an hour on.
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round().min(u64::MAX as f64) as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Notes the zxcvbn strength score and estimated guesses of each password before passing
+/// it on to another formatter.
+pub struct StrengthFormatter<'a> {
+    inner: Box<dyn Formatter + 'a>,
+}
+
+impl<'a> StrengthFormatter<'a> {
+    pub fn new(inner: Box<dyn Formatter + 'a>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Formatter for StrengthFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        self.inner.begin()
+    }
+
+    fn write_record(&mut self, mut record: Record) -> io::Result<()> {
+        let strength = strength::estimate(&record.password);
+        record.annotations.push(format!(
+            "strength {}/{}, 10^{:.1} guesses",
+            strength.score,
+            strength::MAX_SCORE,
+            strength.guesses_log10
+        ));
+        self.inner.write_record(record)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// Optional per-password fields recorded by the structured formats.
+#[derive(Clone, Debug, Default)]
+pub struct ExtraFields {
+    /// Record this entropy in bits as `entropy_bits`.
+    pub entropy: Option<Entropy>,
+    /// Record the zxcvbn `score` and `guesses_log10`.
+    pub strength: bool,
+    /// Record the label of each password as `label`.
+    pub label: bool,
+    /// The names of the fields that the decorators add to each record, e.g. `sha256`.
+    pub names: Vec<&'static str>,
+}
+
+/// A JSON array with one object per password.
+pub struct JsonFormatter<'a> {
+    out: &'a mut dyn Write,
+    categories: String,
+    fields: ExtraFields,
+    records: usize,
+}
+
+impl<'a> JsonFormatter<'a> {
+    pub fn new(out: &'a mut dyn Write, categories: &[String], fields: ExtraFields) -> Self {
+        let categories = categories
+            .iter()
+            .map(|category| format!("\"{}\"", escape_json(category)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Self {
+            out,
+            categories,
+            fields,
+            records: 0,
+        }
+    }
+}
+
+impl Formatter for JsonFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        write!(self.out, "[")
+    }
+
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        let password = &record.password;
+        let separator = if self.records == 0 { "" } else { "," };
+        self.records += 1;
+
+        write!(self.out, "{}\n  {{", separator)?;
+        if let Some(label) = &record.label {
+            write!(self.out, "\"label\": \"{}\", ", escape_json(label))?;
+        }
+        let mut escaped = escape_json(password);
+        let result = write!(
+            self.out,
+            "\"password\": \"{}\", \"length\": {}, \"categories\": [{}]",
+            escaped,
+            password.chars().count(),
+            self.categories
+        );
+        escaped.zeroize();
+        result?;
+        if let Some(entropy) = &self.fields.entropy {
+            write!(
+                self.out,
+                ", \"entropy_bits\": {:.1}",
+                entropy.bits(password)
+            )?;
+        }
+        if self.fields.strength {
+            let strength = strength::estimate(password);
+            write!(
+                self.out,
+                ", \"score\": {}, \"guesses_log10\": {:.1}",
+                strength.score, strength.guesses_log10
+            )?;
+        }
+        for (name, value) in &record.fields {
+            write!(self.out, ", \"{}\": \"{}\"", name, escape_json(value))?;
+        }
+        write!(self.out, "}}")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.records == 0 {
+            writeln!(self.out, "]")?;
+        } else {
+            writeln!(self.out, "\n]")?;
+        }
+        self.out.flush()
+    }
+}
+
+/// CSV rows of `index,password,length`, optionally preceded by a header row. Labeled
+/// passwords have a `label` column before the password.
+pub struct CsvFormatter<'a> {
+    out: &'a mut dyn Write,
+    header: bool,
+    fields: ExtraFields,
+    records: usize,
+}
+
+impl<'a> CsvFormatter<'a> {
+    pub fn new(out: &'a mut dyn Write, header: bool, fields: ExtraFields) -> Self {
+        Self {
+            out,
+            header,
+            fields,
+            records: 0,
+        }
+    }
+}
+
+impl Formatter for CsvFormatter<'_> {
+    fn begin(&mut self) -> io::Result<()> {
+        if self.header {
+            if self.fields.label {
+                write!(self.out, "index,label,password,length")?;
+            } else {
+                write!(self.out, "index,password,length")?;
+            }
+            if self.fields.entropy.is_some() {
+                write!(self.out, ",entropy_bits")?;
+            }
+            if self.fields.strength {
+                write!(self.out, ",score,guesses_log10")?;
+            }
+            for name in &self.fields.names {
+                write!(self.out, ",{}", name)?;
+            }
+            write!(self.out, "\r\n")?;
+        }
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: Record) -> io::Result<()> {
+        let password = &record.password;
+        self.records += 1;
+
+        write!(self.out, "{},", self.records)?;
+        if let Some(label) = &record.label {
+            write!(self.out, "{},", quote_csv(label))?;
+        }
+        let mut quoted = quote_csv(password);
+        let result = write!(self.out, "{},{}", quoted, password.chars().count());
+        quoted.zeroize();
+        result?;
+        if let Some(entropy) = &self.fields.entropy {
+            write!(self.out, ",{:.1}", entropy.bits(password))?;
+        }
+        if self.fields.strength {
+            let strength = strength::estimate(password);
+            write!(
+                self.out,
+                ",{},{:.1}",
+                strength.score, strength.guesses_log10
+            )?;
+        }
+        for (_, value) in &record.fields {
+            write!(self.out, ",{}", quote_csv(value))?;
+        }
+        write!(self.out, "\r\n")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+// Quote a CSV field if it contains a delimiter, quote, or line break, doubling inner quotes.
+fn quote_csv(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// Escape a string for use inside a JSON string literal.
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A buffered writer that wipes its buffer whenever it is flushed and when it is dropped,
+/// so the passwords it held do not linger in freed memory. A capacity of 0 writes through.
+pub struct ZeroizingWriter<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+    capacity: usize,
+}
+
+impl<W: Write> ZeroizingWriter<W> {
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner,
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// The underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let result = self.inner.write_all(&self.buffer);
+        // This keeps the allocation, so the buffer never moves to memory that is not wiped.
+        self.buffer.zeroize();
+        result
+    }
+}
+
+impl<W: Write> Write for ZeroizingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() + buf.len() > self.capacity {
+            self.flush_buffer()?;
+        }
+        if buf.len() >= self.capacity {
+            return self.inner.write(buf);
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer()?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for ZeroizingWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush_buffer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records each write it receives, so tests can see what was buffered and what went through.
+    #[derive(Default)]
+    struct Writes(Vec<Vec<u8>>);
+
+    impl Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn zeroizing_writer_buffers_until_flushed_and_keeps_its_allocation() {
+        let mut writer = ZeroizingWriter::with_capacity(16, Writes::default());
+        writer.write_all(b"abc\n").unwrap();
+        writer.write_all(b"def\n").unwrap();
+        assert!(writer.get_ref().0.is_empty());
+
+        let allocation = writer.buffer.as_ptr();
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref().0, [b"abc\ndef\n".to_vec()]);
+        assert!(writer.buffer.is_empty());
+        assert_eq!(writer.buffer.as_ptr(), allocation);
+        assert_eq!(writer.buffer.capacity(), 16);
+    }
+
+    #[test]
+    fn zeroizing_writer_flushes_before_writes_that_do_not_fit() {
+        let mut writer = ZeroizingWriter::with_capacity(8, Writes::default());
+        writer.write_all(b"abcde").unwrap();
+        writer.write_all(b"fghij").unwrap();
+        assert_eq!(writer.get_ref().0, [b"abcde".to_vec()]);
+
+        writer.write_all(b"0123456789").unwrap();
+        assert_eq!(
+            writer.get_ref().0,
+            [b"abcde".to_vec(), b"fghij".to_vec(), b"0123456789".to_vec()]
+        );
+        assert!(writer.buffer.is_empty());
+    }
+
+    #[test]
+    fn zeroizing_writer_with_no_capacity_writes_through() {
+        let mut writer = ZeroizingWriter::with_capacity(0, Writes::default());
+        writer.write_all(b"a").unwrap();
+        writer.write_all(b"bc").unwrap();
+        assert_eq!(writer.get_ref().0, [b"a".to_vec(), b"bc".to_vec()]);
+    }
+
+    #[test]
+    fn zeroizing_writer_writes_what_it_holds_when_dropped() {
+        let mut out = Vec::new();
+        {
+            let mut writer = ZeroizingWriter::with_capacity(64, &mut out);
+            writer.write_all(b"secret\n").unwrap();
+        }
+        assert_eq!(out, b"secret\n");
+    }
+
+    #[test]
+    fn records_written_through_a_zeroizing_writer_arrive_intact() {
+        let mut out = Vec::new();
+        {
+            let mut writer = ZeroizingWriter::with_capacity(8, &mut out);
+            let mut formatter = PlainFormatter::new(&mut writer, "\n".to_string(), true);
+            let passwords = ["one", "two words", "three"].map(|p| Ok::<_, io::Error>(p.into()));
+            write_all(passwords.into_iter(), &mut formatter).unwrap();
+        }
+        assert_eq!(out, b"one\ntwo words\nthree\n");
+    }
+
+    #[test]
+    fn generated_passwords_can_be_held_and_wiped_in_zeroizing() {
+        let config = crate::generator::GeneratorConfig::builder()
+            .use_all()
+            .length(12)
+            .count(3)
+            .build()
+            .unwrap();
+        let mut passwords = zeroize::Zeroizing::new(crate::generator::generate(&config).unwrap());
+        assert_eq!(passwords.len(), 3);
+        assert!(passwords.iter().all(|password| password.len() == 12));
+        passwords.zeroize();
+        assert!(passwords.is_empty());
+    }
+}
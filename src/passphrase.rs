@@ -0,0 +1,525 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+
+use clap::ValueEnum;
+use rand::Rng;
+use rand::seq::{SliceRandom, index};
+
+use crate::generator::{self, DIGITS, Error, SYMBOLS, SecureRng, log2_binomial};
+use crate::output::{self, OutputOptions};
+
+/// The EFF long wordlist (7776 words), one word per line.
+const EFF_LONG_WORDLIST: &str = include_str!("wordlists/eff_long.txt");
+
+/// The EFF short wordlist (1296 words), one word per line.
+#[cfg(feature = "wordlist-eff-short")]
+const EFF_SHORT_WORDLIST: &str = include_str!("wordlists/eff_short.txt");
+
+/// The BIP-39 English wordlist (2048 words), one word per line.
+#[cfg(feature = "wordlist-bip39")]
+pub(crate) const BIP39_WORDLIST: &str = include_str!("wordlists/bip39_english.txt");
+
+/// The fewest unique words a custom wordlist may contain (10 bits of entropy per word).
+const MIN_WORDLIST_SIZE: usize = 1024;
+
+/// The wordlists that can be embedded in the binary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Wordlist {
+    /// The EFF long wordlist: 7776 words, 12.9 bits per word.
+    #[default]
+    EffLong,
+    /// The EFF short wordlist: 1296 shorter words, 10.3 bits per word.
+    EffShort,
+    /// The BIP-39 English wordlist: 2048 words, 11 bits per word, each identified by its
+    /// first four letters.
+    Bip39,
+}
+
+impl Wordlist {
+    /// Every wordlist, including those left out of this build.
+    pub const ALL: [Wordlist; 3] = [Wordlist::EffLong, Wordlist::EffShort, Wordlist::Bip39];
+
+    /// The name used by `--wordlist-name`, e.g. `eff-long`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Wordlist::EffLong => "eff-long",
+            Wordlist::EffShort => "eff-short",
+            Wordlist::Bip39 => "bip39",
+        }
+    }
+
+    /// The cargo feature that embeds the list, if it is optional.
+    pub fn feature(self) -> Option<&'static str> {
+        match self {
+            Wordlist::EffLong => None,
+            Wordlist::EffShort => Some("wordlist-eff-short"),
+            Wordlist::Bip39 => Some("wordlist-bip39"),
+        }
+    }
+
+    /// The words of the list, or `None` if it was left out of this build.
+    pub fn words(self) -> Option<Vec<String>> {
+        let content = match self {
+            Wordlist::EffLong => Some(EFF_LONG_WORDLIST),
+            #[cfg(feature = "wordlist-eff-short")]
+            Wordlist::EffShort => Some(EFF_SHORT_WORDLIST),
+            #[cfg(feature = "wordlist-bip39")]
+            Wordlist::Bip39 => Some(BIP39_WORDLIST),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+        content.map(|content| content.lines().map(String::from).collect())
+    }
+}
+
+/// Which words of a passphrase start with a capital letter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Capitalize {
+    /// Leave the words as they are in the wordlist.
+    #[default]
+    None,
+    /// Capitalize the first word.
+    First,
+    /// Capitalize one word chosen at random.
+    Random,
+    /// Capitalize every word.
+    All,
+}
+
+/// How `--random-case` cases each passphrase word, chosen independently per word.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum RandomCase {
+    /// Lowercase or Capitalized: 1 bit per word.
+    Capital,
+    /// Lowercase, Capitalized, or ALL-CAPS: about 1.58 bits per word.
+    #[default]
+    Word,
+    /// Every letter upper or lowercase: 1 bit per letter.
+    Letter,
+}
+
+/// Configure information for passphrase generate
+#[derive(Clone, Debug)]
+pub struct PassphraseConfig {
+    pub words: usize,
+    pub count: usize,
+    /// Custom words, which replace the embedded list.
+    pub wordlist: Option<Vec<String>>,
+    /// The embedded list used without custom words.
+    pub wordlist_name: Wordlist,
+    pub separator: String,
+    /// Reject the configuration if its entropy is below this many bits.
+    pub min_entropy: Option<f64>,
+    pub capitalize: Capitalize,
+    /// Case each word at random, after lowercasing the wordlist. Replaces `capitalize`.
+    pub random_case: Option<RandomCase>,
+    /// The number of random digits added at random word boundaries.
+    pub add_digits: usize,
+    /// The number of random symbols added at random word boundaries. Symbols that appear in
+    /// the separator are left out, so the words stay apart.
+    pub add_symbols: usize,
+}
+
+struct PassphraseGenerator {
+    wordlist: Vec<String>,
+    words: usize,
+    count: usize,
+    separator: String,
+    capitalize: Capitalize,
+    random_case: Option<RandomCase>,
+    // The average bits that the random case adds to a word of the list.
+    case_bits: f64,
+    add_digits: usize,
+    add_symbols: usize,
+    symbols: Vec<char>,
+}
+
+impl PassphraseGenerator {
+    fn new(config: &PassphraseConfig) -> Result<Self, Error> {
+        if config.words == 0 {
+            return Err(Error::InvalidConfig(
+                "A passphrase must contain at least one word.".to_string(),
+            ));
+        }
+
+        let wordlist = match &config.wordlist {
+            Some(words) => words.clone(),
+            None => config.wordlist_name.words().ok_or_else(|| {
+                Error::InvalidConfig(format!(
+                    "The {} wordlist requires building with the '{}' feature.",
+                    config.wordlist_name.name(),
+                    config.wordlist_name.feature().unwrap_or_default()
+                ))
+            })?,
+        };
+        if config.random_case.is_some() && config.capitalize != Capitalize::None {
+            return Err(Error::InvalidConfig(
+                "Random case replaces capitalizing words.".to_string(),
+            ));
+        }
+        // Words that differ only in case would otherwise be cased twice and counted twice.
+        let wordlist = match config.random_case {
+            Some(_) => {
+                let mut seen = HashSet::new();
+                wordlist
+                    .into_iter()
+                    .map(|word| word.to_lowercase())
+                    .filter(|word| seen.insert(word.clone()))
+                    .collect()
+            }
+            None => wordlist,
+        };
+        let case_bits = match config.random_case {
+            Some(case) if !wordlist.is_empty() => {
+                wordlist
+                    .iter()
+                    .map(|word| case_bits(word, case))
+                    .sum::<f64>()
+                    / wordlist.len() as f64
+            }
+            _ => 0.0,
+        };
+
+        let generator = Self {
+            wordlist,
+            words: config.words,
+            count: config.count,
+            separator: config.separator.clone(),
+            capitalize: config.capitalize,
+            random_case: config.random_case,
+            case_bits,
+            add_digits: config.add_digits,
+            add_symbols: config.add_symbols,
+            symbols: SYMBOLS
+                .chars()
+                .filter(|c| !config.separator.contains(*c))
+                .collect(),
+        };
+        if generator.add_symbols > 0 && generator.symbols.is_empty() {
+            return Err(Error::InvalidConfig(
+                "The separator leaves no symbols to add.".to_string(),
+            ));
+        }
+
+        if let Some(required) = config.min_entropy {
+            let bits = generator.entropy_bits();
+            if bits < required {
+                // The words alone reach the target, and the added characters may need fewer.
+                let words = generator::suggested_length(required, generator.wordlist.len())
+                    .and_then(|max| {
+                        (1..=max).find(|words| generator.entropy_bits_with(*words) >= required)
+                    })
+                    .unwrap_or(generator.words);
+                return Err(Error::InsufficientEntropy {
+                    bits,
+                    required,
+                    suggestion: format!("Use --words {} or more.", words),
+                });
+            }
+        }
+        Ok(generator)
+    }
+
+    // Each word is chosen independently and uniformly from the wordlist. A random capital adds the
+    // choice of its word, and a random case the bits of casing each word, which keeps words apart
+    // since the list is lowercase. The added characters add their own choices, which kinds come in
+    // which order, and how they are spread over the word boundaries, each uniform.
+    fn entropy_bits(&self) -> f64 {
+        self.entropy_bits_with(self.words)
+    }
+
+    // The entropy of the passphrases if they had `words` words.
+    fn entropy_bits_with(&self, words: usize) -> f64 {
+        let capital = match self.capitalize {
+            Capitalize::Random => (words as f64).log2(),
+            Capitalize::None | Capitalize::First | Capitalize::All => 0.0,
+        };
+
+        let added = self.add_digits + self.add_symbols;
+        let boundaries = words + 1;
+        let chars = self.add_digits as f64 * (DIGITS.len() as f64).log2()
+            + self.add_symbols as f64 * (self.symbols.len() as f64).log2();
+        let order = log2_binomial(added, self.add_digits);
+        let placement = log2_binomial(boundaries + added - 1, added);
+        words as f64 * ((self.wordlist.len() as f64).log2() + self.case_bits)
+            + capital
+            + chars
+            + order
+            + placement
+    }
+
+    fn create_one_passphrase(&self, rng: &mut (impl Rng + ?Sized)) -> String {
+        let mut words: Vec<String> = (0..self.words)
+            .map(|_| self.wordlist.choose(rng).unwrap().clone())
+            .collect();
+
+        let capitalized: Vec<usize> = match self.capitalize {
+            Capitalize::None => Vec::new(),
+            Capitalize::First => vec![0],
+            Capitalize::Random => vec![rng.gen_range(0..self.words)],
+            Capitalize::All => (0..self.words).collect(),
+        };
+        for i in capitalized {
+            words[i] = capitalize(&words[i]);
+        }
+        if let Some(case) = self.random_case {
+            for word in words.iter_mut() {
+                *word = random_case(word, case, rng);
+            }
+        }
+
+        if self.add_digits + self.add_symbols == 0 {
+            return words.join(&self.separator);
+        }
+
+        // The added characters in random order.
+        let digits: Vec<char> = DIGITS.chars().collect();
+        let mut added: Vec<char> = Vec::with_capacity(self.add_digits + self.add_symbols);
+        for _ in 0..self.add_digits {
+            added.push(*digits.choose(rng).unwrap());
+        }
+        for _ in 0..self.add_symbols {
+            added.push(*self.symbols.choose(rng).unwrap());
+        }
+        added.shuffle(rng);
+
+        // Spread them over the word boundaries, every split being equally likely: in a row of
+        // the added characters and one bar per word, the bars mark where each boundary ends.
+        let slots = self.words + added.len();
+        let mut is_char = vec![false; slots];
+        for i in index::sample(rng, slots, added.len()) {
+            is_char[i] = true;
+        }
+        let mut boundaries = vec![String::new(); self.words + 1];
+        let mut boundary = 0;
+        let mut added = added.into_iter();
+        for is_char in is_char {
+            match is_char {
+                true => boundaries[boundary].push(added.next().unwrap()),
+                false => boundary += 1,
+            }
+        }
+
+        // Characters between two words follow the first of them, before the separator.
+        let mut passphrase = boundaries[0].clone();
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                passphrase.push_str(&self.separator);
+            }
+            passphrase.push_str(word);
+            passphrase.push_str(&boundaries[i + 1]);
+        }
+        passphrase
+    }
+}
+
+// `word` with its first letter in uppercase.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// `word` in a casing chosen by `case`.
+fn random_case(word: &str, case: RandomCase, rng: &mut (impl Rng + ?Sized)) -> String {
+    match case {
+        RandomCase::Capital if rng.r#gen() => capitalize(word),
+        RandomCase::Capital => word.to_string(),
+        RandomCase::Word => match rng.gen_range(0..3) {
+            0 => word.to_string(),
+            1 => capitalize(word),
+            _ => word.to_uppercase(),
+        },
+        RandomCase::Letter => word
+            .chars()
+            .map(|c| match rng.r#gen() {
+                true => c.to_uppercase().collect::<String>(),
+                false => c.to_lowercase().collect(),
+            })
+            .collect(),
+    }
+}
+
+// The bits `case` adds to `word`. Casings that give the same string, as for a word of one
+// letter, count once.
+fn case_bits(word: &str, case: RandomCase) -> f64 {
+    let forms = match case {
+        RandomCase::Capital => vec![word.to_string(), capitalize(word)],
+        RandomCase::Word => vec![word.to_string(), capitalize(word), word.to_uppercase()],
+        RandomCase::Letter => {
+            return word
+                .chars()
+                .filter(|c| c.to_uppercase().ne(c.to_lowercase()))
+                .count() as f64;
+        }
+    };
+    forms
+        .iter()
+        .enumerate()
+        .filter(|(i, form)| !forms[..*i].contains(form))
+        .map(|(_, form)| {
+            let p = forms.iter().filter(|other| *other == form).count() as f64 / forms.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Read a custom wordlist, one word per line.
+///
+/// Blank lines and lines starting with `#` are skipped and duplicate words are removed.
+pub fn read_wordlist(path: &str) -> Result<Vec<String>, Error> {
+    let content = fs::read_to_string(path).map_err(|err| {
+        Error::InvalidConfig(format!("Failed to read wordlist '{}': {}", path, err))
+    })?;
+
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let word = line.trim();
+        if word.is_empty() || word.starts_with('#') {
+            continue;
+        }
+
+        if word.chars().any(|c| c.is_whitespace()) {
+            return Err(Error::InvalidConfig(format!(
+                "The wordlist '{}' contains whitespace inside a word on line {}.",
+                path,
+                i + 1
+            )));
+        }
+
+        if seen.insert(word) {
+            words.push(word.to_string());
+        }
+    }
+
+    if words.len() < MIN_WORDLIST_SIZE {
+        return Err(Error::InvalidConfig(format!(
+            "The wordlist '{}' contains only {} unique words, but at least {} are required.",
+            path,
+            words.len(),
+            MIN_WORDLIST_SIZE
+        )));
+    }
+    Ok(words)
+}
+
+/// Generate passphrases with the given random number generator and write them to `output`
+/// in the format described by `options`.
+pub fn generate_passphrases<R: SecureRng + ?Sized>(
+    config: &PassphraseConfig,
+    rng: &mut R,
+    options: &OutputOptions,
+    output: &mut dyn Write,
+) -> Result<(), Error> {
+    let generator = PassphraseGenerator::new(config)?;
+    let batch = output::Batch {
+        categories: vec!["words".to_string()],
+        count: generator.count,
+        entropy: output::Entropy::Fixed(generator.entropy_bits()),
+    };
+    let mut formatter = output::formatter(options, output, &batch, rng);
+
+    let passphrases = (0..generator.count).map(|_| Ok(generator.create_one_passphrase(rng)));
+    output::write_all(passphrases, &mut *formatter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    // Two-letter words, which split back out of a passphrase even without a separator.
+    fn words() -> Vec<String> {
+        let letters = 'a'..='z';
+        letters
+            .clone()
+            .flat_map(|a| letters.clone().map(move |b| format!("{a}{b}")))
+            .collect()
+    }
+
+    // `count` five-word passphrases joined by `separator`, from a seeded generator.
+    fn passphrases(separator: &str, count: usize) -> Vec<String> {
+        let config = PassphraseConfig {
+            words: 5,
+            count,
+            wordlist: Some(words()),
+            wordlist_name: Wordlist::default(),
+            separator: separator.to_string(),
+            min_entropy: None,
+            capitalize: Capitalize::None,
+            random_case: None,
+            add_digits: 0,
+            add_symbols: 0,
+        };
+        let mut buffer = Vec::new();
+        generate_passphrases(
+            &config,
+            &mut StdRng::seed_from_u64(8),
+            &OutputOptions::default(),
+            &mut buffer,
+        )
+        .unwrap();
+        String::from_utf8(buffer)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn an_empty_separator_joins_the_words_directly() {
+        let words = words();
+        for passphrase in passphrases("", 50) {
+            assert_eq!(passphrase.len(), 10);
+            for i in (0..10).step_by(2) {
+                assert!(
+                    words.contains(&passphrase[i..i + 2].to_string()),
+                    "{passphrase}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn multi_character_separators_sit_between_every_pair_of_words() {
+        let words = words();
+        for separator in ["<->", " + ", "–"] {
+            for passphrase in passphrases(separator, 50) {
+                let parts: Vec<&str> = passphrase.split(separator).collect();
+                assert_eq!(parts.len(), 5, "{passphrase}");
+                assert!(parts.iter().all(|part| words.contains(&part.to_string())));
+            }
+        }
+    }
+
+    #[test]
+    fn every_counted_passphrase_uses_the_separator() {
+        for count in [1, 7, 200] {
+            let lines = passphrases("::", count);
+            assert_eq!(lines.len(), count);
+            assert!(lines.iter().all(|line| line.matches("::").count() == 4));
+        }
+        // The same seed gives the same words, whatever joins them.
+        let joined: Vec<String> = passphrases("", 20);
+        let split: Vec<String> = passphrases("::", 20)
+            .iter()
+            .map(|line| line.replace("::", ""))
+            .collect();
+        assert_eq!(joined, split);
+    }
+
+    #[test]
+    fn separators_cannot_break_lines() {
+        assert_eq!(validation::parse_separator(""), Ok(String::new()));
+        assert_eq!(validation::parse_separator("<->"), Ok("<->".to_string()));
+        assert!(validation::parse_separator("a\nb").is_err());
+        assert!(validation::parse_separator("\r").is_err());
+    }
+}
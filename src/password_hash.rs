@@ -0,0 +1,165 @@
+//! Password hashes in the formats that login systems store, for seeding user tables with
+//! `--hash-password` and writing htpasswd files instead of plaintext.
+//!
+//! The strings are the standard encodings, `$2b$...` for bcrypt, PHC strings for Argon2id,
+//! `$6$...` for SHA-512 crypt, and the htpasswd forms of the rest, so any library that
+//! verifies them accepts the output.
+
+pub mod apr1;
+pub mod argon2;
+pub mod bcrypt;
+#[cfg(feature = "crypt")]
+pub mod sha_crypt;
+
+use clap::ValueEnum;
+
+/// The hash schemes `--hash-password` offers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Scheme {
+    Bcrypt,
+    Argon2id,
+}
+
+/// The crypt(3) schemes `--crypt` offers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CryptScheme {
+    Sha512,
+}
+
+/// A hash scheme with its work factors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// bcrypt at a cost between 4 and 31, the log2 of its rounds.
+    Bcrypt {
+        cost: u32,
+        version: bcrypt::Version,
+    },
+    Argon2id(argon2::Params),
+    /// Apache's MD5-based `$apr1$` crypt.
+    Apr1,
+    /// The unsalted `{SHA}` hash of htpasswd files: base64 of the SHA-1 digest.
+    #[cfg(feature = "hash")]
+    Sha1,
+    /// SHA-512 crypt with this many rounds.
+    #[cfg(feature = "crypt")]
+    Sha512Crypt {
+        rounds: u32,
+    },
+}
+
+impl Algorithm {
+    /// Check that the work factors are in range for the scheme.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Algorithm::Bcrypt { cost, .. } if !bcrypt::COSTS.contains(cost) => Err(format!(
+                "The bcrypt cost must be between {} and {}, not {}.",
+                bcrypt::COSTS.start(),
+                bcrypt::COSTS.end(),
+                cost
+            )),
+            Algorithm::Argon2id(params) => params.validate(),
+            #[cfg(feature = "crypt")]
+            Algorithm::Sha512Crypt { rounds } if !sha_crypt::ROUNDS.contains(rounds) => {
+                Err(format!(
+                    "SHA-512 crypt needs between {} and {} rounds, not {}.",
+                    sha_crypt::ROUNDS.start(),
+                    sha_crypt::ROUNDS.end(),
+                    rounds
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The length of the random salt in bytes.
+    pub fn salt_len(&self) -> usize {
+        match self {
+            Algorithm::Bcrypt { .. } => bcrypt::SALT_LEN,
+            Algorithm::Argon2id(_) => argon2::SALT_LEN,
+            Algorithm::Apr1 => apr1::SALT_LEN,
+            #[cfg(feature = "hash")]
+            Algorithm::Sha1 => 0,
+            #[cfg(feature = "crypt")]
+            Algorithm::Sha512Crypt { .. } => sha_crypt::SALT_LEN,
+        }
+    }
+
+    /// Hash `password` with `salt`, which is [`salt_len`](Self::salt_len) bytes long.
+    pub fn hash(&self, password: &[u8], salt: &[u8]) -> String {
+        match self {
+            Algorithm::Bcrypt { cost, version } => {
+                bcrypt::hash(password, salt_array(salt), *cost, *version)
+            }
+            Algorithm::Argon2id(params) => argon2::hash(password, salt_array(salt), *params),
+            Algorithm::Apr1 => apr1::hash(password, salt_array(salt)),
+            #[cfg(feature = "hash")]
+            Algorithm::Sha1 => {
+                let digest = sha1_smol::Sha1::from(password).digest().bytes();
+                let encoded = base64(&digest);
+                let padding = "=".repeat((3 - digest.len() % 3) % 3);
+                format!("{{SHA}}{}{}", encoded, padding)
+            }
+            #[cfg(feature = "crypt")]
+            Algorithm::Sha512Crypt { rounds } => {
+                sha_crypt::hash(password, salt_array(salt), *rounds)
+            }
+        }
+    }
+}
+
+fn salt_array<const N: usize>(salt: &[u8]) -> &[u8; N] {
+    salt.try_into().expect("salt of the wrong length")
+}
+
+/// How generated passwords are hashed on their way out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hashing {
+    pub algorithm: Algorithm,
+    /// Write the hash in place of the password rather than after it.
+    pub hash_only: bool,
+    /// Hash this many passwords at once on separate threads.
+    pub jobs: usize,
+}
+
+// Standard base64 (RFC 4648) without padding, as PHC strings use it.
+fn base64(bytes: &[u8]) -> String {
+    encode_base64(
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+        bytes,
+    )
+}
+
+// Base64 without padding in the given alphabet, most significant bits first.
+fn encode_base64(alphabet: &[u8; 64], bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            encoded.push(alphabet[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+// The base64 alphabet of crypt(3), ordered unlike the standard one.
+const CRYPT_ALPHABET: &[u8; 64] =
+    b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// A crypt(3) salt with one character per random byte.
+fn crypt_salt(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| CRYPT_ALPHABET[usize::from(byte & 0x3f)] as char)
+        .collect()
+}
+
+// Append the low `chars` * 6 bits of `group` in the crypt(3) alphabet, least significant
+// first.
+fn push_crypt_base64(encoded: &mut String, mut group: u32, chars: usize) {
+    for _ in 0..chars {
+        encoded.push(CRYPT_ALPHABET[(group & 0x3f) as usize] as char);
+        group >>= 6;
+    }
+}
@@ -0,0 +1,154 @@
+//! Apache's `$apr1$` variant of the MD5-based crypt, which every htpasswd reader
+//! understands. MD5 is long broken as a digest; it is offered for old servers only.
+
+use zeroize::Zeroize;
+
+use super::{crypt_salt, push_crypt_base64};
+
+/// The salt length in characters.
+pub const SALT_LEN: usize = 8;
+
+const MAGIC: &str = "$apr1$";
+
+/// Hash `password`, e.g. `$apr1$` followed by the salt and the encoded hash. Each random
+/// byte of `salt` picks one salt character.
+pub fn hash(password: &[u8], salt: &[u8; SALT_LEN]) -> String {
+    let salt = crypt_salt(salt);
+
+    let mut alternate = md5(&[password, salt.as_bytes(), password].concat());
+    let mut input = [password, MAGIC.as_bytes(), salt.as_bytes()].concat();
+    for chunk in password.chunks(alternate.len()) {
+        input.extend_from_slice(&alternate[..chunk.len()]);
+    }
+    // One byte per bit of the password length: NUL for set bits, the first password byte for
+    // clear ones.
+    let mut length = password.len();
+    while length > 0 {
+        input.push(if length & 1 == 1 {
+            0
+        } else {
+            password.first().copied().unwrap_or(0)
+        });
+        length >>= 1;
+    }
+    let mut digest = md5(&input);
+    input.zeroize();
+    alternate.zeroize();
+
+    // A thousand rounds to slow down guessing, by the standards of 1994.
+    for round in 0..1000 {
+        let mut input = Vec::new();
+        if round % 2 == 1 {
+            input.extend_from_slice(password);
+        } else {
+            input.extend_from_slice(&digest);
+        }
+        if round % 3 != 0 {
+            input.extend_from_slice(salt.as_bytes());
+        }
+        if round % 7 != 0 {
+            input.extend_from_slice(password);
+        }
+        if round % 2 == 1 {
+            input.extend_from_slice(&digest);
+        } else {
+            input.extend_from_slice(password);
+        }
+        digest = md5(&input);
+        input.zeroize();
+    }
+
+    let mut encoded = String::new();
+    for [a, b, c] in [[0, 6, 12], [1, 7, 13], [2, 8, 14], [3, 9, 15], [4, 10, 5]] {
+        let group = u32::from(digest[a]) << 16 | u32::from(digest[b]) << 8 | u32::from(digest[c]);
+        push_crypt_base64(&mut encoded, group, 4);
+    }
+    push_crypt_base64(&mut encoded, u32::from(digest[11]), 2);
+    digest.zeroize();
+
+    format!("{}{}${}", MAGIC, salt, encoded)
+}
+
+// MD5 (RFC 1321) of a whole message.
+fn md5(message: &[u8]) -> [u8; 16] {
+    let mut state: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&((message.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    for block in padded.chunks(64) {
+        let m: [u32; 16] = std::array::from_fn(|i| {
+            u32::from_le_bytes(block[4 * i..4 * i + 4].try_into().unwrap())
+        });
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(K[i])
+                .wrapping_add(m[g])
+                .rotate_left(SHIFTS[i / 16][i % 4]);
+            (a, b, c, d) = (d, b.wrapping_add(rotated), b, c);
+        }
+        for (word, added) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(added);
+        }
+    }
+    padded.zeroize();
+
+    let mut digest = [0u8; 16];
+    for (chunk, word) in digest.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+const SHIFTS: [[u32; 4]; 4] = [
+    [7, 12, 17, 22],
+    [5, 9, 14, 20],
+    [4, 11, 16, 23],
+    [6, 10, 15, 21],
+];
+
+// The integer parts of 2^32 * |sin(i + 1)|.
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "saltsalt" in the crypt(3) alphabet.
+    const SALT: [u8; SALT_LEN] = [56, 38, 49, 57, 56, 38, 49, 57];
+
+    // The expected hashes are those of 'openssl passwd -apr1 -salt saltsalt'.
+    #[test]
+    fn hash_matches_openssl() {
+        assert_eq!(
+            hash(b"Hello world!", &SALT),
+            "$apr1$saltsalt$6BwcdpRros16.J9J/tHRr/"
+        );
+        assert_eq!(hash(b"", &SALT), "$apr1$saltsalt$a8ml/vK5HEjiZ5oypDWA7/");
+        assert_eq!(
+            hash(b"a password longer than sixteen bytes", &SALT),
+            "$apr1$saltsalt$oN/R3H1T.1bODoLY26a8G/"
+        );
+    }
+}
@@ -0,0 +1,535 @@
+//! Argon2id (RFC 9106), version 1.3, encoded as a PHC string.
+
+use zeroize::Zeroize;
+
+use super::base64;
+
+/// The salt length in bytes.
+pub const SALT_LEN: usize = 16;
+
+/// The hash length in bytes.
+const HASH_LEN: usize = 32;
+
+const VERSION: u32 = 0x13;
+
+// The type number of Argon2id, which is part of the hashed parameters.
+const ARGON2ID: u32 = 2;
+
+// The slices each pass over a lane is split into. Lanes synchronize between slices.
+const SLICES: usize = 4;
+
+// A block of memory, 1 KiB.
+type Block = [u64; 128];
+
+/// The cost parameters of an Argon2id hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    /// The memory to fill, in KiB. At least 8 per lane.
+    pub memory: u32,
+    /// The passes over the memory; at least 1.
+    pub iterations: u32,
+    /// The lanes filled independently, from 1 to 2^24 - 1.
+    pub parallelism: u32,
+}
+
+impl Params {
+    /// Check that Argon2id accepts these parameters.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..1 << 24).contains(&self.parallelism) {
+            return Err(format!(
+                "Argon2 parallelism must be between 1 and {}, not {}.",
+                (1 << 24) - 1,
+                self.parallelism
+            ));
+        }
+        if self.iterations == 0 {
+            return Err("Argon2 needs at least 1 iteration.".to_string());
+        }
+        if u64::from(self.memory) < 8 * u64::from(self.parallelism) {
+            return Err(format!(
+                "Argon2 needs at least 8 KiB of memory per lane, so {} KiB with a parallelism of {}.",
+                8 * u64::from(self.parallelism),
+                self.parallelism
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Hash `password` with `salt`, e.g. `$argon2id$v=19$m=19456,t=2,p=1$` followed by the
+/// encoded salt and hash. The parameters must pass [`Params::validate`].
+pub fn hash(password: &[u8], salt: &[u8; SALT_LEN], params: Params) -> String {
+    let mut tag = [0u8; HASH_LEN];
+    hash_raw(password, salt, &[], &[], params, &mut tag);
+    let encoded = format!(
+        "$argon2id$v={}$m={},t={},p={}${}${}",
+        VERSION,
+        params.memory,
+        params.iterations,
+        params.parallelism,
+        base64(salt),
+        base64(&tag)
+    );
+    tag.zeroize();
+    encoded
+}
+
+// Fill `tag` with the Argon2id hash of `password`. `hash` leaves out the optional secret key
+// and associated data.
+fn hash_raw(
+    password: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    data: &[u8],
+    params: Params,
+    tag: &mut [u8],
+) {
+    let lanes = params.parallelism as usize;
+    // The memory is rounded down to a whole number of segments in every lane.
+    let lane_len = params.memory as usize / (SLICES * lanes) * SLICES;
+    let segment_len = lane_len / SLICES;
+
+    let mut h0 = [0u8; 64];
+    let mut hasher = Blake2b::new(h0.len());
+    for value in [
+        params.parallelism,
+        tag.len() as u32,
+        params.memory,
+        params.iterations,
+        VERSION,
+        ARGON2ID,
+    ] {
+        hasher.update(&value.to_le_bytes());
+    }
+    for input in [password, salt, secret, data] {
+        hasher.update(&(input.len() as u32).to_le_bytes());
+        hasher.update(input);
+    }
+    hasher.finalize(&mut h0);
+
+    let mut memory: Vec<Block> = vec![[0; 128]; lanes * lane_len];
+    let mut bytes = [0u8; 1024];
+    for lane in 0..lanes {
+        for column in 0..2 {
+            h_prime(
+                &[
+                    &h0,
+                    &(column as u32).to_le_bytes(),
+                    &(lane as u32).to_le_bytes(),
+                ],
+                &mut bytes,
+            );
+            memory[lane * lane_len + column] = block_from_bytes(&bytes);
+        }
+    }
+    h0.zeroize();
+
+    let fill = Fill {
+        lanes,
+        lane_len,
+        segment_len,
+        iterations: params.iterations,
+    };
+    for pass in 0..params.iterations {
+        for slice in 0..SLICES {
+            for lane in 0..lanes {
+                fill.segment(&mut memory, pass, slice, lane);
+            }
+        }
+    }
+
+    // The last block of every lane, combined.
+    let mut last = [0u64; 128];
+    for lane in 0..lanes {
+        xor(&mut last, &memory[lane * lane_len + lane_len - 1]);
+    }
+    for (chunk, word) in bytes.chunks_mut(8).zip(last) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    h_prime(&[&bytes], tag);
+
+    last.zeroize();
+    bytes.zeroize();
+    memory.zeroize();
+}
+
+// The memory layout, shared by every segment.
+struct Fill {
+    lanes: usize,
+    lane_len: usize,
+    segment_len: usize,
+    iterations: u32,
+}
+
+impl Fill {
+    fn segment(&self, memory: &mut [Block], pass: u32, slice: usize, lane: usize) {
+        // Argon2id picks reference blocks independently of the password in the first half
+        // of the first pass, which resists side channels, and from the memory afterwards,
+        // which resists trading memory for time.
+        let independent = pass == 0 && slice < SLICES / 2;
+        let mut input = [0u64; 128];
+        let mut addresses = [0u64; 128];
+        if independent {
+            input[..6].copy_from_slice(&[
+                u64::from(pass),
+                lane as u64,
+                slice as u64,
+                memory.len() as u64,
+                u64::from(self.iterations),
+                u64::from(ARGON2ID),
+            ]);
+        }
+
+        // The first two blocks of each lane come from the initial hash.
+        let start = if pass == 0 && slice == 0 { 2 } else { 0 };
+        if independent && start != 0 {
+            next_addresses(&mut input, &mut addresses);
+        }
+
+        for index in start..self.segment_len {
+            let column = slice * self.segment_len + index;
+            let previous = lane * self.lane_len
+                + if column == 0 {
+                    self.lane_len - 1
+                } else {
+                    column - 1
+                };
+
+            let random = if independent {
+                if index % addresses.len() == 0 {
+                    next_addresses(&mut input, &mut addresses);
+                }
+                addresses[index % addresses.len()]
+            } else {
+                memory[previous][0]
+            };
+
+            let reference_lane = if pass == 0 && slice == 0 {
+                lane
+            } else {
+                (random >> 32) as usize % self.lanes
+            };
+            let reference = reference_lane * self.lane_len
+                + self.reference_column(pass, slice, index, random, reference_lane == lane);
+
+            let mut block = compress(&memory[previous], &memory[reference]);
+            // Later passes fold the new block into the old one.
+            if pass > 0 {
+                xor(&mut block, &memory[lane * self.lane_len + column]);
+            }
+            memory[lane * self.lane_len + column] = block;
+            block.zeroize();
+        }
+    }
+
+    // The column of the reference block for the block at `index` of a segment, chosen
+    // from the blocks already finished, skewed towards the most recent ones.
+    fn reference_column(
+        &self,
+        pass: u32,
+        slice: usize,
+        index: usize,
+        random: u64,
+        same_lane: bool,
+    ) -> usize {
+        let finished = if pass == 0 {
+            slice * self.segment_len
+        } else {
+            self.lane_len - self.segment_len
+        };
+        // The block being computed and the one before it are excluded. Another lane's
+        // last finished block is still being overwritten at the start of a segment.
+        let area = if same_lane {
+            finished + index - 1
+        } else {
+            finished - usize::from(index == 0)
+        };
+
+        let x = (random & 0xffff_ffff).pow(2) >> 32;
+        let relative = area - 1 - ((area as u64 * x) >> 32) as usize;
+        let start = if pass == 0 || slice == SLICES - 1 {
+            0
+        } else {
+            (slice + 1) * self.segment_len
+        };
+        (start + relative) % self.lane_len
+    }
+}
+
+fn next_addresses(input: &mut Block, addresses: &mut Block) {
+    input[6] += 1;
+    let zero = [0u64; 128];
+    *addresses = compress(&zero, &compress(&zero, input));
+}
+
+// The compression function G: a BLAKE2b-like permutation of the two blocks combined,
+// applied to the rows and then the columns of the block seen as 8x8 pairs of words.
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = *x;
+    xor(&mut r, y);
+    let mut z = r;
+    for row in 0..8 {
+        permute(&mut z, std::array::from_fn(|i| 16 * row + i));
+    }
+    for column in 0..8 {
+        permute(
+            &mut z,
+            std::array::from_fn(|i| 16 * (i / 2) + 2 * column + i % 2),
+        );
+    }
+    xor(&mut z, &r);
+    r.zeroize();
+    z
+}
+
+// The words that each round of BLAKE2b mixes: the columns of its 4x4 state, then the
+// diagonals.
+const MIXES: [[usize; 4]; 8] = [
+    [0, 4, 8, 12],
+    [1, 5, 9, 13],
+    [2, 6, 10, 14],
+    [3, 7, 11, 15],
+    [0, 5, 10, 15],
+    [1, 6, 11, 12],
+    [2, 7, 8, 13],
+    [3, 4, 9, 14],
+];
+
+fn permute(block: &mut Block, words: [usize; 16]) {
+    let mut v = words.map(|i| block[i]);
+    for [a, b, c, d] in MIXES {
+        // BLAKE2b's mixing with a multiplication added to each sum.
+        let mix = |x: u64, y: u64| {
+            x.wrapping_add(y).wrapping_add(
+                2u64.wrapping_mul(x & 0xffff_ffff)
+                    .wrapping_mul(y & 0xffff_ffff),
+            )
+        };
+        v[a] = mix(v[a], v[b]);
+        v[d] = (v[d] ^ v[a]).rotate_right(32);
+        v[c] = mix(v[c], v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(24);
+        v[a] = mix(v[a], v[b]);
+        v[d] = (v[d] ^ v[a]).rotate_right(16);
+        v[c] = mix(v[c], v[d]);
+        v[b] = (v[b] ^ v[c]).rotate_right(63);
+    }
+    for (i, word) in words.into_iter().zip(v) {
+        block[i] = word;
+    }
+    v.zeroize();
+}
+
+fn xor(block: &mut Block, other: &Block) {
+    for (word, other) in block.iter_mut().zip(other) {
+        *word ^= other;
+    }
+}
+
+fn block_from_bytes(bytes: &[u8; 1024]) -> Block {
+    std::array::from_fn(|i| u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap()))
+}
+
+// The variable-length hash H', which chains BLAKE2b for outputs longer than 64 bytes.
+fn h_prime(inputs: &[&[u8]], out: &mut [u8]) {
+    let mut hasher = Blake2b::new(out.len().min(64));
+    hasher.update(&(out.len() as u32).to_le_bytes());
+    for input in inputs {
+        hasher.update(input);
+    }
+    if out.len() <= 64 {
+        hasher.finalize(out);
+        return;
+    }
+
+    let mut v = [0u8; 64];
+    hasher.finalize(&mut v);
+    out[..32].copy_from_slice(&v[..32]);
+    let mut position = 32;
+    while out.len() - position > 64 {
+        let mut hasher = Blake2b::new(64);
+        hasher.update(&v);
+        hasher.finalize(&mut v);
+        out[position..position + 32].copy_from_slice(&v[..32]);
+        position += 32;
+    }
+    let mut hasher = Blake2b::new(out.len() - position);
+    hasher.update(&v);
+    hasher.finalize(&mut out[position..]);
+    v.zeroize();
+}
+
+// BLAKE2b (RFC 7693) without a key, for outputs of 1 to 64 bytes.
+struct Blake2b {
+    h: [u64; 8],
+    buffer: [u8; 128],
+    buffered: usize,
+    length: u128,
+}
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+impl Blake2b {
+    fn new(out_len: usize) -> Self {
+        let mut h = IV;
+        h[0] ^= 0x0101_0000 ^ out_len as u64;
+        Self {
+            h,
+            buffer: [0; 128],
+            buffered: 0,
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // The last block is held back, since it is compressed differently.
+            if self.buffered == self.buffer.len() {
+                self.length += self.buffer.len() as u128;
+                self.compress(false);
+                self.buffered = 0;
+            }
+            let n = data.len().min(self.buffer.len() - self.buffered);
+            self.buffer[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+        }
+    }
+
+    fn finalize(mut self, out: &mut [u8]) {
+        self.length += self.buffered as u128;
+        self.buffer[self.buffered..].fill(0);
+        self.compress(true);
+        let bytes: Vec<u8> = self.h.iter().flat_map(|word| word.to_le_bytes()).collect();
+        out.copy_from_slice(&bytes[..out.len()]);
+        self.h.zeroize();
+        self.buffer.zeroize();
+    }
+
+    fn compress(&mut self, last: bool) {
+        let m: [u64; 16] = std::array::from_fn(|i| {
+            u64::from_le_bytes(self.buffer[8 * i..8 * i + 8].try_into().unwrap())
+        });
+        let mut v = [0u64; 16];
+        v[..8].copy_from_slice(&self.h);
+        v[8..].copy_from_slice(&IV);
+        v[12] ^= self.length as u64;
+        v[13] ^= (self.length >> 64) as u64;
+        if last {
+            v[14] = !v[14];
+        }
+
+        for round in 0..12 {
+            let s = &SIGMA[round % SIGMA.len()];
+            for (i, [a, b, c, d]) in MIXES.into_iter().enumerate() {
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(m[s[2 * i]]);
+                v[d] = (v[d] ^ v[a]).rotate_right(32);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right(24);
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(m[s[2 * i + 1]]);
+                v[d] = (v[d] ^ v[a]).rotate_right(16);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right(63);
+            }
+        }
+
+        for i in 0..8 {
+            self.h[i] ^= v[i] ^ v[i + 8];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The Argon2id test vector of RFC 9106, section 5.3.
+    #[test]
+    fn hash_raw_matches_rfc_9106() {
+        let params = Params {
+            memory: 32,
+            iterations: 3,
+            parallelism: 4,
+        };
+        let mut tag = [0u8; HASH_LEN];
+        hash_raw(&[1; 32], &[2; 16], &[3; 8], &[4; 12], params, &mut tag);
+        let hex: String = tag.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(
+            hex,
+            "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659"
+        );
+    }
+
+    #[test]
+    fn hash_encodes_a_phc_string() {
+        let params = Params {
+            memory: 32,
+            iterations: 3,
+            parallelism: 4,
+        };
+        let mut tag = [0u8; HASH_LEN];
+        hash_raw(&[1; 32], &[2; 16], &[], &[], params, &mut tag);
+        assert_eq!(
+            hash(&[1; 32], &[2; 16], params),
+            format!(
+                "$argon2id$v=19$m=32,t=3,p=4$AgICAgICAgICAgICAgICAg${}",
+                base64(&tag)
+            )
+        );
+    }
+
+    #[test]
+    fn argon2_crate_verifies_hashes() {
+        use ::argon2::{Argon2, PasswordHash, PasswordVerifier};
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(82);
+        for (memory, iterations, parallelism) in [(8, 1, 1), (64, 2, 1), (256, 3, 4), (1024, 1, 2)]
+        {
+            let params = Params {
+                memory,
+                iterations,
+                parallelism,
+            };
+            let mut salt = [0u8; SALT_LEN];
+            rng.fill(&mut salt);
+            let password: Vec<u8> = (0..rng.gen_range(0..64)).map(|_| rng.r#gen()).collect();
+
+            let hashed = hash(&password, &salt, params);
+            let parsed = PasswordHash::new(&hashed).unwrap();
+            assert!(
+                Argon2::default()
+                    .verify_password(&password, &parsed)
+                    .is_ok(),
+                "{hashed}"
+            );
+            assert!(
+                Argon2::default()
+                    .verify_password(b"wrong password", &parsed)
+                    .is_err()
+            );
+        }
+    }
+}
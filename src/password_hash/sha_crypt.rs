@@ -0,0 +1,164 @@
+//! SHA-512 crypt, the `$6$` hashes of `/etc/shadow` that Linux installers and configuration
+//! tools expect.
+
+use sha2::{Digest, Sha512};
+use zeroize::Zeroize;
+
+use super::{crypt_salt, push_crypt_base64};
+
+/// The salt length in characters, the longest the scheme uses.
+pub const SALT_LEN: usize = 16;
+
+/// The rounds used when a hash does not name them.
+pub const DEFAULT_ROUNDS: u32 = 5000;
+
+/// The rounds a hash may name.
+pub const ROUNDS: std::ops::RangeInclusive<u32> = 1000..=999_999_999;
+
+// The order in which the digest bytes are encoded, three at a time.
+const ENCODING: [[usize; 3]; 21] = [
+    [0, 21, 42],
+    [22, 43, 1],
+    [44, 2, 23],
+    [3, 24, 45],
+    [25, 46, 4],
+    [47, 5, 26],
+    [6, 27, 48],
+    [28, 49, 7],
+    [50, 8, 29],
+    [9, 30, 51],
+    [31, 52, 10],
+    [53, 11, 32],
+    [12, 33, 54],
+    [34, 55, 13],
+    [56, 14, 35],
+    [15, 36, 57],
+    [37, 58, 16],
+    [59, 17, 38],
+    [18, 39, 60],
+    [40, 61, 19],
+    [62, 20, 41],
+];
+
+/// Hash `password` with `rounds`, e.g. `$6$` followed by the salt and the encoded hash.
+/// Rounds other than [`DEFAULT_ROUNDS`] are recorded as `rounds=N$` before the salt. Each
+/// random byte of `salt` picks one salt character.
+pub fn hash(password: &[u8], salt: &[u8; SALT_LEN], rounds: u32) -> String {
+    hash_with_salt(password, &crypt_salt(salt), rounds)
+}
+
+// Hash `password` with a salt of at most `SALT_LEN` crypt(3) characters.
+fn hash_with_salt(password: &[u8], salt: &str, rounds: u32) -> String {
+    debug_assert!(ROUNDS.contains(&rounds));
+    debug_assert!(salt.len() <= SALT_LEN);
+    let salt_bytes = salt.as_bytes();
+
+    let mut alternate = Sha512::new();
+    alternate.update(password);
+    alternate.update(salt_bytes);
+    alternate.update(password);
+    let mut alternate: [u8; 64] = alternate.finalize().into();
+
+    let mut digest = Sha512::new();
+    digest.update(password);
+    digest.update(salt_bytes);
+    for chunk in password.chunks(alternate.len()) {
+        digest.update(&alternate[..chunk.len()]);
+    }
+    // One block per bit of the password length: the alternate digest for set bits, the
+    // password for clear ones.
+    let mut length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            digest.update(alternate);
+        } else {
+            digest.update(password);
+        }
+        length >>= 1;
+    }
+    let mut digest: [u8; 64] = digest.finalize().into();
+    alternate.zeroize();
+
+    // The password and salt mixed into every round are repeated digests of themselves.
+    let mut password_hash = Sha512::new();
+    for _ in 0..password.len() {
+        password_hash.update(password);
+    }
+    let mut p = repeat(&password_hash.finalize(), password.len());
+    let mut salt_hash = Sha512::new();
+    for _ in 0..16 + usize::from(digest[0]) {
+        salt_hash.update(salt_bytes);
+    }
+    let s = repeat(&salt_hash.finalize(), salt_bytes.len());
+
+    for round in 0..rounds {
+        let mut next = Sha512::new();
+        if round % 2 == 1 {
+            next.update(&p);
+        } else {
+            next.update(digest);
+        }
+        if round % 3 != 0 {
+            next.update(&s);
+        }
+        if round % 7 != 0 {
+            next.update(&p);
+        }
+        if round % 2 == 1 {
+            next.update(digest);
+        } else {
+            next.update(&p);
+        }
+        digest = next.finalize().into();
+    }
+    p.zeroize();
+
+    let mut encoded = String::new();
+    for [a, b, c] in ENCODING {
+        let group = u32::from(digest[a]) << 16 | u32::from(digest[b]) << 8 | u32::from(digest[c]);
+        push_crypt_base64(&mut encoded, group, 4);
+    }
+    push_crypt_base64(&mut encoded, u32::from(digest[63]), 2);
+    digest.zeroize();
+
+    let rounds = if rounds == DEFAULT_ROUNDS {
+        String::new()
+    } else {
+        format!("rounds={}$", rounds)
+    };
+    format!("$6${}{}${}", rounds, salt, encoded)
+}
+
+// `digest` repeated and cut to `len` bytes.
+fn repeat(digest: &[u8], len: usize) -> Vec<u8> {
+    digest.iter().copied().cycle().take(len).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The SHA-512 vectors of "Unix crypt using SHA-256 and SHA-512" by Ulrich Drepper.
+    #[test]
+    fn hash_matches_the_specification() {
+        assert_eq!(
+            hash_with_salt(b"Hello world!", "saltstring", DEFAULT_ROUNDS),
+            "$6$saltstring$svn8UoSVapNtMuq1ukKS4tPQd8iKwSMHWjl/O817G3uBnIFNjnQJuesI68u4OTLiBFdcbYEdFCoEOfaS35inz1"
+        );
+        assert_eq!(
+            hash_with_salt(b"Hello world!", "saltstringsaltst", 10000),
+            "$6$rounds=10000$saltstringsaltst$OW1/O6BYHV6BcXZu8QVeXbDWra3Oeqh0sbHbbMCVNSnCM/UrjmM0Dp8vOuZeHBy/YTBmSK6H9qs/y3RnOaw5v."
+        );
+    }
+
+    #[test]
+    fn hash_maps_each_salt_byte_to_a_character() {
+        let salt = [0, 1, 63, 64, 255, 12, 37, 38, 0, 0, 0, 0, 0, 0, 0, 0];
+        let hashed = hash(b"password", &salt, DEFAULT_ROUNDS);
+        assert!(hashed.starts_with("$6$./z.zAZa........$"));
+        assert_eq!(
+            hashed,
+            hash_with_salt(b"password", "./z.zAZa........", DEFAULT_ROUNDS)
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Spelling passwords out for `--phonetic`, to read them aloud without mix-ups: the NATO
+//! alphabet for letters, names for digits, symbols, and the built-in emoji.
+
+/// The spoken name of `c`, e.g. `capital X-ray`, `x-ray`, `three`, or `dollar sign`.
+///
+/// `None` for characters outside the built-in sets, such as custom Unicode characters.
+pub fn spell(c: char) -> Option<&'static str> {
+    let name = match c {
+        'a' => "alfa",
+        'b' => "bravo",
+        'c' => "charlie",
+        'd' => "delta",
+        'e' => "echo",
+        'f' => "foxtrot",
+        'g' => "golf",
+        'h' => "hotel",
+        'i' => "india",
+        'j' => "juliett",
+        'k' => "kilo",
+        'l' => "lima",
+        'm' => "mike",
+        'n' => "november",
+        'o' => "oscar",
+        'p' => "papa",
+        'q' => "quebec",
+        'r' => "romeo",
+        's' => "sierra",
+        't' => "tango",
+        'u' => "uniform",
+        'v' => "victor",
+        'w' => "whiskey",
+        'x' => "x-ray",
+        'y' => "yankee",
+        'z' => "zulu",
+        'A' => "capital Alfa",
+        'B' => "capital Bravo",
+        'C' => "capital Charlie",
+        'D' => "capital Delta",
+        'E' => "capital Echo",
+        'F' => "capital Foxtrot",
+        'G' => "capital Golf",
+        'H' => "capital Hotel",
+        'I' => "capital India",
+        'J' => "capital Juliett",
+        'K' => "capital Kilo",
+        'L' => "capital Lima",
+        'M' => "capital Mike",
+        'N' => "capital November",
+        'O' => "capital Oscar",
+        'P' => "capital Papa",
+        'Q' => "capital Quebec",
+        'R' => "capital Romeo",
+        'S' => "capital Sierra",
+        'T' => "capital Tango",
+        'U' => "capital Uniform",
+        'V' => "capital Victor",
+        'W' => "capital Whiskey",
+        'X' => "capital X-ray",
+        'Y' => "capital Yankee",
+        'Z' => "capital Zulu",
+        '0' => "zero",
+        '1' => "one",
+        '2' => "two",
+        '3' => "three",
+        '4' => "four",
+        '5' => "five",
+        '6' => "six",
+        '7' => "seven",
+        '8' => "eight",
+        '9' => "nine",
+        '!' => "exclamation mark",
+        '"' => "quotation mark",
+        '#' => "number sign",
+        '$' => "dollar sign",
+        '%' => "percent sign",
+        '&' => "ampersand",
+        '\'' => "apostrophe",
+        '(' => "left parenthesis",
+        ')' => "right parenthesis",
+        '*' => "asterisk",
+        '+' => "plus sign",
+        ',' => "comma",
+        '-' => "hyphen",
+        '.' => "period",
+        '/' => "slash",
+        ':' => "colon",
+        ';' => "semicolon",
+        '<' => "less-than sign",
+        '=' => "equals sign",
+        '>' => "greater-than sign",
+        '?' => "question mark",
+        '@' => "at sign",
+        '[' => "left bracket",
+        '\\' => "backslash",
+        ']' => "right bracket",
+        '^' => "caret",
+        '_' => "underscore",
+        '`' => "backtick",
+        '{' => "left brace",
+        '|' => "vertical bar",
+        '}' => "right brace",
+        '~' => "tilde",
+        ' ' => "space",
+        '🐌' => "snail emoji",
+        '🐍' => "snake emoji",
+        '🐎' => "horse emoji",
+        '🐘' => "elephant emoji",
+        '🐙' => "octopus emoji",
+        '🐚' => "spiral shell emoji",
+        '🐛' => "bug emoji",
+        '🐜' => "ant emoji",
+        '🐝' => "honeybee emoji",
+        '🐞' => "lady beetle emoji",
+        '🐟' => "fish emoji",
+        '🐢' => "turtle emoji",
+        '🐧' => "penguin emoji",
+        '🐨' => "koala emoji",
+        '🐬' => "dolphin emoji",
+        '🐳' => "spouting whale emoji",
+        '🐸' => "frog emoji",
+        '🐼' => "panda emoji",
+        '🐷' => "pig face emoji",
+        '🐯' => "tiger face emoji",
+        '🐰' => "rabbit face emoji",
+        '🐻' => "bear emoji",
+        '🐔' => "chicken emoji",
+        '🍄' => "mushroom emoji",
+        '🍅' => "tomato emoji",
+        '🍆' => "eggplant emoji",
+        '🍇' => "grapes emoji",
+        '🍉' => "watermelon emoji",
+        '🍋' => "lemon emoji",
+        '🍌' => "banana emoji",
+        '🍍' => "pineapple emoji",
+        '🍎' => "red apple emoji",
+        '🍒' => "cherries emoji",
+        '🍓' => "strawberry emoji",
+        '🍔' => "hamburger emoji",
+        '🍕' => "pizza emoji",
+        '🍩' => "doughnut emoji",
+        '🍪' => "cookie emoji",
+        '🍰' => "shortcake emoji",
+        '🍿' => "popcorn emoji",
+        '🌽' => "ear of corn emoji",
+        '🌵' => "cactus emoji",
+        '🌻' => "sunflower emoji",
+        '🍀' => "four leaf clover emoji",
+        '🍁' => "maple leaf emoji",
+        '🎈' => "balloon emoji",
+        '🎁' => "wrapped gift emoji",
+        '🎸' => "guitar emoji",
+        '🎲' => "game die emoji",
+        '🎯' => "bullseye emoji",
+        '🏀' => "basketball emoji",
+        '🎩' => "top hat emoji",
+        '🎃' => "jack-o-lantern emoji",
+        '🔑' => "key emoji",
+        '🔔' => "bell emoji",
+        '💎' => "gem stone emoji",
+        '💡' => "light bulb emoji",
+        '📚' => "books emoji",
+        '🚀' => "rocket emoji",
+        '🚲' => "bicycle emoji",
+        '🚗' => "automobile emoji",
+        '🌈' => "rainbow emoji",
+        '🌙' => "crescent moon emoji",
+        '🔥' => "fire emoji",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// `password` spelled out character by character, separated by commas. Characters without
+/// a name are quoted as they are.
+pub fn spell_password(password: &str) -> String {
+    password
+        .chars()
+        .map(|c| match spell(c) {
+            Some(name) => name.to_string(),
+            None => format!("'{}'", c),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{self, CharClass, Charset};
+    use crate::preset::Presets;
+
+    #[test]
+    fn every_built_in_character_has_a_name() {
+        let mut charsets: Vec<String> = CharClass::ALL
+            .iter()
+            .map(|class| class.chars().to_string())
+            .collect();
+        charsets.extend(
+            [
+                Charset::hex(false),
+                Charset::hex(true),
+                Charset::base58(),
+                Charset::base62(),
+                Charset::emoji(),
+            ]
+            .map(|charset| charset.chars),
+        );
+        charsets.extend(Presets::builtin().iter().map(|(_, preset)| preset.chars()));
+        charsets.push(generator::LEET_MAP.replace([',', '='], ""));
+
+        for c in charsets.iter().flat_map(|chars| chars.chars()) {
+            assert!(spell(c).is_some(), "{c:?} has no name");
+        }
+    }
+
+    #[test]
+    fn spell_password_quotes_unnamed_characters() {
+        assert_eq!(spell_password("aB3"), "alfa, capital Bravo, three");
+        assert_eq!(spell_password("ß!"), "'ß', exclamation mark");
+    }
+}
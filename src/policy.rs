@@ -0,0 +1,117 @@
+//! Named password requirements for `--policy`.
+//!
+//! A policy is data: the same [`check::Policy`] constraints that `pwgen check` tests, so
+//! generating with a policy and checking against it agree.
+
+use std::collections::BTreeMap;
+
+use crate::check;
+use crate::generator::CharClass;
+
+/// A policy and where its rules come from.
+#[derive(Clone, Debug)]
+pub struct NamedPolicy {
+    /// A short note on the source of the rules, e.g. `NIST SP 800-63B`.
+    pub source: String,
+    pub rules: check::Policy,
+}
+
+impl NamedPolicy {
+    /// The minimum number of characters of `class`, counting a required category as one.
+    pub fn min_count(&self, class: CharClass) -> usize {
+        let min = match class {
+            CharClass::Lower => self.rules.min_lower,
+            CharClass::Upper => self.rules.min_upper,
+            CharClass::Digit => self.rules.min_digits,
+            CharClass::Symbol => self.rules.min_symbols,
+        };
+        min.max(usize::from(self.rules.required.contains(&class)))
+    }
+
+    /// The rules in words, e.g. `at least 12 characters; requires digits, symbols`.
+    pub fn describe(&self) -> String {
+        let mut rules = Vec::new();
+        if let Some(min_length) = self.rules.min_length {
+            rules.push(format!("at least {} characters", min_length));
+        }
+        // A category needed once is simply required; larger minimums are spelled out.
+        let required: Vec<&str> = CharClass::ALL
+            .into_iter()
+            .filter(|class| self.min_count(*class) == 1)
+            .map(CharClass::name)
+            .collect();
+        if !required.is_empty() {
+            rules.push(format!("requires {}", required.join(", ")));
+        }
+        for class in CharClass::ALL {
+            let min = self.min_count(class);
+            if min > 1 {
+                rules.push(format!("at least {} {}", min, class.name()));
+            }
+        }
+        if CharClass::ALL
+            .into_iter()
+            .all(|class| self.min_count(class) == 0)
+        {
+            rules.push("no composition rules".to_string());
+        }
+        if self.rules.avoid_ambiguous {
+            rules.push("no ambiguous characters".to_string());
+        }
+        if let Some(exclude) = &self.rules.exclude_chars {
+            rules.push(format!("none of {}", exclude));
+        }
+        rules.join("; ")
+    }
+}
+
+/// The policies available by name.
+#[derive(Clone, Debug)]
+pub struct Policies {
+    policies: BTreeMap<String, NamedPolicy>,
+}
+
+impl Policies {
+    /// The built-in policies.
+    pub fn builtin() -> Self {
+        let policy = |name: &str, source: &str, min_length: usize, required: &[CharClass]| {
+            (
+                name.to_string(),
+                NamedPolicy {
+                    source: source.to_string(),
+                    rules: check::Policy {
+                        min_length: Some(min_length),
+                        required: required.to_vec(),
+                        ..check::Policy::default()
+                    },
+                },
+            )
+        };
+
+        Self {
+            policies: BTreeMap::from([
+                policy("legacy8", "older systems", 8, &CharClass::ALL),
+                policy("nist", "NIST SP 800-63B", 15, &[]),
+                policy("pci", "PCI DSS", 12, &CharClass::ALL),
+            ]),
+        }
+    }
+
+    /// The policy called `name`.
+    pub fn get(&self, name: &str) -> Result<&NamedPolicy, String> {
+        self.policies.get(name).ok_or_else(|| {
+            format!(
+                "Unknown policy '{}'. Valid policies are: {}.",
+                name,
+                self.policies.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+
+    /// The policies in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &NamedPolicy)> {
+        self.policies
+            .iter()
+            .map(|(name, policy)| (name.as_str(), policy))
+    }
+}
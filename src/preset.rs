@@ -0,0 +1,96 @@
+//! Named character sets for `--preset`.
+
+use std::collections::BTreeMap;
+
+use crate::generator::{BASE58, BASE62, CharClass, Charset};
+use crate::validation;
+
+/// The RFC 4648 base32 alphabet.
+pub const BASE32: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// What a preset selects.
+#[derive(Clone, Debug)]
+pub enum Preset {
+    /// Built-in categories, each of which appears at least once.
+    Categories(Vec<CharClass>),
+    /// A complete charset without guarantees.
+    Charset(Charset),
+}
+
+impl Preset {
+    /// Every character the preset may produce.
+    pub fn chars(&self) -> String {
+        match self {
+            Preset::Categories(classes) => classes.iter().map(|class| class.chars()).collect(),
+            Preset::Charset(charset) => charset.chars.clone(),
+        }
+    }
+}
+
+/// The presets available by name: the built-in ones and any user-defined ones.
+#[derive(Clone, Debug)]
+pub struct Presets {
+    presets: BTreeMap<String, Preset>,
+}
+
+impl Presets {
+    /// The built-in presets.
+    pub fn builtin() -> Self {
+        let charset = |name: &str, chars: String| {
+            (
+                name.to_string(),
+                Preset::Charset(Charset {
+                    name: name.to_string(),
+                    chars,
+                }),
+            )
+        };
+
+        Self {
+            presets: BTreeMap::from([
+                (
+                    "alnum".to_string(),
+                    Preset::Categories(vec![CharClass::Lower, CharClass::Upper, CharClass::Digit]),
+                ),
+                charset("base32", BASE32.to_string()),
+                charset("base58", BASE58.to_string()),
+                charset("base62", BASE62.to_string()),
+                charset("hex", Charset::hex(false).chars),
+                charset("printable", ('!'..='~').collect()),
+            ]),
+        }
+    }
+
+    /// Add a preset drawing uniformly from `chars`, written with the syntax of `--chars`.
+    /// A user-defined preset replaces a built-in one of the same name.
+    pub fn define(&mut self, name: &str, chars: &str) -> Result<(), String> {
+        let chars = validation::parse_chars(chars)
+            .map_err(|err| format!("The preset '{}' is invalid: {}", name, err))?;
+        self.presets.insert(
+            name.to_string(),
+            Preset::Charset(Charset {
+                name: name.to_string(),
+                chars,
+            }),
+        );
+        Ok(())
+    }
+
+    /// The preset called `name`.
+    pub fn get(&self, name: &str) -> Result<&Preset, String> {
+        self.presets.get(name).ok_or_else(|| {
+            format!(
+                "Unknown preset '{}'. Valid presets are: {}.",
+                name,
+                self.presets.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+
+    /// The presets in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Preset)> {
+        self.presets
+            .iter()
+            .map(|(name, preset)| (name.as_str(), preset))
+    }
+}
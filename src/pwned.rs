@@ -0,0 +1,243 @@
+//! Breach checks against the Have I Been Pwned range API.
+//!
+//! Only the first five hex digits of a password's SHA-1 hash are sent. The API answers with
+//! the suffixes of every breached hash sharing that prefix, and the comparison happens
+//! locally. The HTTP client is only built with the `pwned` cargo feature.
+
+use clap::ValueEnum;
+
+/// What to do when the API cannot be reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Fail the generation.
+    Strict,
+    /// Warn once and keep generating without the check.
+    BestEffort,
+}
+
+/// The range API endpoint. The hash prefix is appended to it.
+pub const DEFAULT_API_URL: &str = "https://api.pwnedpasswords.com/range/";
+
+/// Whether a range API response lists `suffix`, the last 35 hex digits of a SHA-1 hash.
+///
+/// Each line has the form `SUFFIX:COUNT`. Padding entries have a count of 0 and are ignored.
+pub fn contains_suffix(response: &str, suffix: &str) -> bool {
+    response.lines().any(|line| {
+        line.trim()
+            .split_once(':')
+            .is_some_and(|(candidate, count)| {
+                candidate.eq_ignore_ascii_case(suffix) && count.trim() != "0"
+            })
+    })
+}
+
+#[cfg(feature = "pwned")]
+pub use client::Checker;
+
+#[cfg(feature = "pwned")]
+mod client {
+    use std::io;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use super::{DEFAULT_API_URL, Mode, contains_suffix};
+    use crate::output;
+
+    /// How long a single request may take.
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// A client for the range API.
+    pub struct Checker {
+        mode: Mode,
+        api_url: String,
+        agent: ureq::Agent,
+        // Set after a best-effort failure, so an offline machine only waits for one timeout.
+        unavailable: AtomicBool,
+    }
+
+    impl Checker {
+        /// A checker using the public API.
+        pub fn new(mode: Mode) -> Self {
+            Self::with_api_url(mode, DEFAULT_API_URL)
+        }
+
+        /// A checker using another server implementing the range API, e.g. a local mirror.
+        pub fn with_api_url(mode: Mode, api_url: &str) -> Self {
+            Self {
+                mode,
+                api_url: api_url.to_string(),
+                agent: ureq::AgentBuilder::new()
+                    .timeout(TIMEOUT)
+                    .user_agent(concat!("pwgen-command/", env!("CARGO_PKG_VERSION")))
+                    .build(),
+                unavailable: AtomicBool::new(false),
+            }
+        }
+
+        /// Whether `password` appears in a known breach.
+        ///
+        /// In [`Mode::BestEffort`], a failed request prints a warning with [`output::note`],
+        /// and this and every later call report the password as not found.
+        pub fn is_pwned(&self, password: &str) -> io::Result<bool> {
+            if self.unavailable.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+
+            match self.lookup(password) {
+                Ok(found) => Ok(found),
+                Err(err) if self.mode == Mode::BestEffort => {
+                    output::note(format_args!(
+                        "Warning: Could not check passwords against Have I Been Pwned ({}). Continuing without the check.",
+                        err
+                    ));
+                    self.unavailable.store(true, Ordering::Relaxed);
+                    Ok(false)
+                }
+                Err(err) => Err(err),
+            }
+        }
+
+        fn lookup(&self, password: &str) -> io::Result<bool> {
+            let hash = sha1_smol::Sha1::from(password)
+                .digest()
+                .to_string()
+                .to_uppercase();
+            let (prefix, suffix) = hash.split_at(5);
+
+            let response = self
+                .agent
+                .get(&format!("{}{}", self.api_url, prefix))
+                .set("Add-Padding", "true")
+                .call()
+                .map_err(io::Error::other)?
+                .into_string()?;
+            Ok(contains_suffix(&response, suffix))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_suffix_skips_padding() {
+        let response = "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n\
+                        00D4F6E8FA6EECAD2A3AA415EEC418D38EC:0\r\n";
+        assert!(contains_suffix(
+            response,
+            "0018a45c4d1def81644b54ab7f969b88d65"
+        ));
+        assert!(!contains_suffix(
+            response,
+            "00D4F6E8FA6EECAD2A3AA415EEC418D38EC"
+        ));
+        assert!(!contains_suffix(
+            response,
+            "011053FD0102E94D6AE2F8B83D76FAF94F6"
+        ));
+    }
+
+    #[cfg(feature = "pwned")]
+    mod client {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+        use std::sync::mpsc;
+        use std::thread;
+
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        use super::super::{Checker, Mode};
+        use crate::generator::{GeneratorConfig, generate_with_rng};
+
+        // Answer every request with `body`, sending each request line to the receiver.
+        fn serve(body: String) -> (String, mpsc::Receiver<String>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/range/", listener.local_addr().unwrap());
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut request = String::new();
+                    reader.read_line(&mut request).unwrap();
+                    let mut header = String::new();
+                    while reader.read_line(&mut header).unwrap() > 2 {
+                        header.clear();
+                    }
+                    sender.send(request.trim_end().to_string()).unwrap();
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .unwrap();
+                }
+            });
+            (url, receiver)
+        }
+
+        fn sha1_hex(password: &str) -> String {
+            sha1_smol::Sha1::from(password)
+                .digest()
+                .to_string()
+                .to_uppercase()
+        }
+
+        #[test]
+        fn breached_passwords_are_regenerated() {
+            let config = GeneratorConfig::builder()
+                .use_all()
+                .length(12)
+                .build()
+                .unwrap();
+            let first = generate_with_rng(&config, &mut StdRng::seed_from_u64(40)).unwrap();
+            let hash = sha1_hex(&first[0]);
+            let (prefix, suffix) = hash.split_at(5);
+
+            let (url, requests) = serve(format!("{}:0\r\n{}:12\r\n", "0".repeat(35), suffix));
+            let config = GeneratorConfig {
+                check_pwned: Some(Mode::Strict),
+                pwned_api_url: Some(url),
+                ..config
+            };
+            let passwords = generate_with_rng(&config, &mut StdRng::seed_from_u64(40)).unwrap();
+            assert_ne!(passwords, first);
+
+            let requests: Vec<String> = requests.try_iter().collect();
+            assert_eq!(requests.len(), 2);
+            assert_eq!(requests[0], format!("GET /range/{} HTTP/1.1", prefix));
+            assert_eq!(
+                requests[1],
+                format!("GET /range/{} HTTP/1.1", &sha1_hex(&passwords[0])[..5])
+            );
+            for request in &requests {
+                let path = request
+                    .strip_prefix("GET /range/")
+                    .and_then(|rest| rest.strip_suffix(" HTTP/1.1"))
+                    .unwrap();
+                assert_eq!(path.len(), 5);
+                assert!(path.bytes().all(|b| b.is_ascii_hexdigit()));
+            }
+        }
+
+        #[test]
+        fn unreachable_servers_fail_only_strict_checks() {
+            // Nothing listens on the port once the listener is dropped.
+            let url = {
+                let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                format!("http://{}/range/", listener.local_addr().unwrap())
+            };
+            assert!(
+                Checker::with_api_url(Mode::Strict, &url)
+                    .is_pwned("password")
+                    .is_err()
+            );
+            let checker = Checker::with_api_url(Mode::BestEffort, &url);
+            assert!(!checker.is_pwned("password").unwrap());
+            assert!(!checker.is_pwned("password").unwrap());
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Detection of predictable runs such as `abc`, `987`, or `qwe`.
+
+use std::ops::Range;
+
+/// The shortest run [`find_run`] reports unless told otherwise.
+pub const DEFAULT_MIN_LENGTH: usize = 3;
+
+/// Character orders in which neighbors form a run: the alphabet, the digits, and the letter
+/// rows of a QWERTY keyboard.
+const SEQUENCES: [&str; 5] = [
+    "abcdefghijklmnopqrstuvwxyz",
+    "0123456789",
+    "qwertyuiop",
+    "asdfghjkl",
+    "zxcvbnm",
+];
+
+/// The span, in characters, of the first run of at least `min_length` characters that step
+/// through one of the sequences in a single direction, ignoring case.
+pub fn find_run(password: &str, min_length: usize) -> Option<Range<usize>> {
+    let chars: Vec<char> = password.chars().map(|c| c.to_ascii_lowercase()).collect();
+    if min_length < 2 || chars.len() < min_length {
+        return None;
+    }
+
+    SEQUENCES
+        .iter()
+        .filter_map(|sequence| find_run_in(&chars, sequence, min_length))
+        .min_by_key(|span| span.start)
+}
+
+fn find_run_in(chars: &[char], sequence: &str, min_length: usize) -> Option<Range<usize>> {
+    let position = |c: char| sequence.find(c).map(|i| i as isize);
+
+    let mut start = 0;
+    let mut direction = 0;
+    for i in 1..=chars.len() {
+        let step = match (
+            chars.get(i - 1).and_then(|c| position(*c)),
+            chars.get(i).and_then(|c| position(*c)),
+        ) {
+            (Some(a), Some(b)) if (b - a).abs() == 1 => b - a,
+            _ => 0,
+        };
+        if step != 0 && step == direction {
+            continue;
+        }
+
+        // The run before `i` has ended.
+        if direction != 0 && i - start >= min_length {
+            return Some(start..i);
+        }
+        // A new run starts at the previous character when this pair still steps.
+        start = if step == 0 { i } else { i - 1 };
+        direction = step;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_run_spans_alphabetic_runs() {
+        assert_eq!(find_run("xabcx", 3), Some(1..4));
+        assert_eq!(find_run("x1ABCDEF", 3), Some(2..8));
+        assert_eq!(find_run("QzYx", 3), Some(1..4));
+        assert_eq!(find_run("abcd", 5), None);
+    }
+
+    #[test]
+    fn find_run_spans_numeric_runs() {
+        assert_eq!(find_run("pw987z", 3), Some(2..5));
+        assert_eq!(find_run("0123", 4), Some(0..4));
+        // The span counts characters, not bytes.
+        assert_eq!(find_run("éé345", 3), Some(2..5));
+    }
+
+    #[test]
+    fn find_run_spans_keyboard_runs() {
+        assert_eq!(find_run("Qwe!", 3), Some(0..3));
+        assert_eq!(find_run("9poiu9", 3), Some(1..5));
+        assert_eq!(find_run("1zxcv", 4), Some(1..5));
+    }
+
+    #[test]
+    fn find_run_reports_the_earliest_run() {
+        assert_eq!(find_run("x789abc", 3), Some(1..4));
+        assert_eq!(find_run("xyz!123", 3), Some(0..3));
+    }
+
+    #[test]
+    fn find_run_ignores_broken_and_short_runs() {
+        assert_eq!(find_run("acegik", 3), None);
+        assert_eq!(find_run("abab", 3), None);
+        assert_eq!(find_run("abcba", 4), None);
+        assert_eq!(find_run("q1w2e3", 3), None);
+        assert_eq!(find_run("ab", 3), None);
+        assert_eq!(find_run("", 3), None);
+        assert_eq!(find_run("abc", 1), None);
+    }
+}
@@ -0,0 +1,26 @@
+//! Password strength estimation with the zxcvbn algorithm.
+
+/// The highest zxcvbn score.
+pub const MAX_SCORE: u8 = 4;
+
+/// How hard a password is to guess, as estimated by zxcvbn.
+#[derive(Clone, Copy, Debug)]
+pub struct Strength {
+    /// 0 (trivially guessable) to 4 (more than 10^10 guesses).
+    pub score: u8,
+    /// The estimated number of guesses, as a power of ten.
+    pub guesses_log10: f64,
+}
+
+/// Estimate the strength of `password`.
+///
+/// Unlike the theoretical entropy, this accounts for dictionary words, keyboard patterns,
+/// repeats, and sequences that happen to appear in the password.
+pub fn estimate(password: &str) -> Strength {
+    let entropy = zxcvbn::zxcvbn(password, &[]);
+
+    Strength {
+        score: entropy.score().into(),
+        guesses_log10: entropy.guesses_log10(),
+    }
+}
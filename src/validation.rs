@@ -0,0 +1,770 @@
+use crate::generator::{CharClass, LeetMap, LengthRange, Pattern, PatternClass};
+use crate::output::{Columns, Template, TemplatePart};
+use crate::strength;
+
+/// The shortest password length accepted by [`parse_length`].
+pub const MIN_LENGTH: usize = 6;
+
+/// The longest password length accepted by [`parse_length`]. It is far beyond any real
+/// password, so a longer one is most likely a typo.
+pub const MAX_LENGTH: usize = 1024;
+
+/// Validate a password length, `N` or `MIN-MAX`, from [`MIN_LENGTH`] to [`MAX_LENGTH`].
+pub fn parse_length(s: &str) -> Result<LengthRange, String> {
+    parse_length_within(s, MIN_LENGTH, MAX_LENGTH)
+}
+
+/// Validate a password length like [`parse_length`], from `min_length` to `max_length`.
+///
+/// The lengths are plain digits, so signs and whitespace are rejected.
+pub fn parse_length_within(
+    s: &str,
+    min_length: usize,
+    max_length: usize,
+) -> Result<LengthRange, String> {
+    let parse = |part: &str| -> Result<usize, String> {
+        if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("'{}' is not a valid number or range.", s));
+        }
+        part.parse()
+            .map_err(|_| format!("The length '{}' is too large.", part))
+    };
+    let (min, max) = match s.split_once('-') {
+        Some((min, max)) => (parse(min)?, parse(max)?),
+        None => {
+            let length = parse(s)?;
+            (length, length)
+        }
+    };
+
+    if min < min_length {
+        Err(format!(
+            "The password length must be at least {}.",
+            min_length
+        ))
+    } else if min > max {
+        Err(format!(
+            "The length range {}-{} is empty. Put the shorter length first.",
+            min, max
+        ))
+    } else if max > max_length {
+        Err(format!(
+            "The password length {} is above the maximum of {}.",
+            max, max_length
+        ))
+    } else {
+        Ok(LengthRange { min, max })
+    }
+}
+
+/// The largest `--count` accepted unless `--max-count` raises it.
+pub const DEFAULT_MAX_COUNT: usize = 10_000_000;
+
+/// Validate a password count: a number with an optional `k` (thousand) or `m` (million)
+/// suffix, e.g. `10k`. 0 is accepted, for a run that only checks the options.
+pub fn parse_count(s: &str) -> Result<usize, String> {
+    let invalid = || format!("'{}' is not a valid count, such as 20, 10k, or 2m.", s);
+    let (number, scale) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 1_000),
+        Some((i, 'm' | 'M')) => (&s[..i], 1_000_000),
+        _ => (s, 1),
+    };
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+        .ok_or_else(|| format!("The count '{}' is too large.", s))
+}
+
+/// Validate a PIN length of at least one digit.
+pub fn parse_pin(s: &str) -> Result<usize, String> {
+    let length: usize = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number.", s))?;
+
+    if length == 0 {
+        Err("The PIN length must be at least 1.".to_string())
+    } else {
+        Ok(length)
+    }
+}
+
+/// Validate a column count: `auto` or a positive number.
+pub fn parse_columns(s: &str) -> Result<Columns, String> {
+    if s == "auto" {
+        return Ok(Columns::Auto);
+    }
+
+    match s.parse() {
+        Ok(0) => Err("The number of columns must be at least 1.".to_string()),
+        Ok(n) => Ok(Columns::Fixed(n)),
+        Err(_) => Err(format!("'{}' is not 'auto' or a valid number.", s)),
+    }
+}
+
+/// Validate a group size of at least one character.
+pub fn parse_group(s: &str) -> Result<usize, String> {
+    let size: usize = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number.", s))?;
+
+    if size == 0 {
+        Err("The group size must be at least 1.".to_string())
+    } else {
+        Ok(size)
+    }
+}
+
+/// Validate a number of emoji of at least one.
+pub fn parse_emoji(s: &str) -> Result<usize, String> {
+    let count: usize = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number.", s))?;
+
+    if count == 0 {
+        Err("At least one emoji is required. Leave out --emoji instead.".to_string())
+    } else {
+        Ok(count)
+    }
+}
+
+/// Validate a number of attempts per password of at least one.
+pub fn parse_attempts(s: &str) -> Result<usize, String> {
+    let attempts: usize = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number.", s))?;
+
+    if attempts == 0 {
+        Err("At least one attempt per password is required.".to_string())
+    } else {
+        Ok(attempts)
+    }
+}
+
+/// Validate a positive number of bits of entropy.
+pub fn parse_bits(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(bits) if bits.is_finite() && bits > 0.0 => Ok(bits),
+        _ => Err(format!("'{}' is not a positive number of bits.", s)),
+    }
+}
+
+/// Validate the shortest word length for `--no-words`, at least 3 letters.
+pub fn parse_word_length(s: &str) -> Result<usize, String> {
+    let length: usize = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number.", s))?;
+
+    if length < 3 {
+        Err("The shortest word length must be at least 3.".to_string())
+    } else {
+        Ok(length)
+    }
+}
+
+/// Validate the shortest run length for `--no-sequential`, at least 2 characters.
+pub fn parse_run_length(s: &str) -> Result<usize, String> {
+    let length: usize = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number.", s))?;
+
+    if length < 2 {
+        Err("The shortest run length must be at least 2.".to_string())
+    } else {
+        Ok(length)
+    }
+}
+
+/// Validate a zxcvbn strength score from 0 to 4.
+pub fn parse_score(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(score) if score <= strength::MAX_SCORE => Ok(score),
+        _ => Err(format!(
+            "'{}' is not a valid score. Use a number from 0 to {}.",
+            s,
+            strength::MAX_SCORE
+        )),
+    }
+}
+
+/// Validate a custom character set, expanding escapes and ranges such as `a-f0-9`.
+///
+/// The escapes are `\xNN` for an ASCII character in hex, `\s` for a space, `\\` for a
+/// backslash, and `\-` for a hyphen. A hyphen is also literal at the start or end of the set.
+/// Raw whitespace is rejected, so a space or tab must be written as an escape.
+pub fn parse_chars(s: &str) -> Result<String, String> {
+    expand_ranges(&unescape(&validate_char_set(s, "custom character set")?)?)
+}
+
+// A character of a custom set, with its 1-based position and whether it was escaped.
+type Token = (usize, char, bool);
+
+// Resolve the escapes of a custom set.
+fn unescape(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().enumerate();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            tokens.push((i + 1, c, false));
+            continue;
+        }
+
+        let c = match chars.next() {
+            Some((_, 's')) => ' ',
+            Some((_, c @ ('\\' | '-'))) => c,
+            Some((_, 'x')) => {
+                let digits: String = chars.by_ref().take(2).map(|(_, c)| c).collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) if digits.len() == 2 && byte.is_ascii() => {
+                        let c = char::from(byte);
+                        if c.is_control() && c != '\t' {
+                            return Err(format!(
+                                "The escape '\\x{}' at position {} is a control character. Only \\x09 (tab) is allowed.",
+                                digits,
+                                i + 1
+                            ));
+                        }
+                        c
+                    }
+                    _ => {
+                        return Err(format!(
+                            "The escape '\\x{}' at position {} needs two hex digits for an ASCII character (00-7F).",
+                            digits,
+                            i + 1
+                        ));
+                    }
+                }
+            }
+            Some((_, other)) => {
+                return Err(format!(
+                    "Invalid escape '\\{}' at position {}. Use \\xNN, \\s, \\\\, or \\-.",
+                    other,
+                    i + 1
+                ));
+            }
+            None => {
+                return Err(
+                    "The character set cannot end with an unfinished escape '\\'.".to_string(),
+                );
+            }
+        };
+        tokens.push((i + 1, c, true));
+    }
+    Ok(tokens)
+}
+
+// Expand `X-Y` ranges into the characters they cover.
+fn expand_ranges(tokens: &[Token]) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let (start_at, start, _) = tokens[i];
+        match (tokens.get(i + 1), tokens.get(i + 2)) {
+            (Some((_, '-', false)), Some(&(end_at, end, _))) => {
+                let span = format!("'{}-{}' at positions {}-{}", start, end, start_at, end_at);
+                if !start.is_ascii() || !end.is_ascii() {
+                    return Err(format!(
+                        "The range {} must be between ASCII characters.",
+                        span
+                    ));
+                }
+                if start > end {
+                    return Err(format!(
+                        "The range {} is descending. Write it as '{}-{}'.",
+                        span, end, start
+                    ));
+                }
+                if (start..=end).any(|c| c.is_control()) {
+                    return Err(format!("The range {} includes control characters.", span));
+                }
+                expanded.extend(start..=end);
+                i += 3;
+            }
+            _ => {
+                expanded.push(start);
+                i += 1;
+            }
+        }
+    }
+    Ok(expanded)
+}
+
+/// Validate a set of characters to exclude.
+pub fn parse_exclude(s: &str) -> Result<String, String> {
+    validate_char_set(s, "excluded character set")
+}
+
+// Shared validation for options that take a set of characters
+fn validate_char_set(s: &str, name: &str) -> Result<String, String> {
+    if s.is_empty() {
+        return Err(format!(
+            "The {} cannot be empty. Please provide at least one character.",
+            name
+        ));
+    }
+
+    if s.chars().any(|c| c.is_whitespace()) {
+        return Err(format!(
+            "The {} cannot contain whitespace characters.",
+            name
+        ));
+    }
+
+    if s.chars().any(|c| c.is_control()) {
+        return Err(format!("The {} cannot contain control characters.", name));
+    }
+
+    // Non-ASCII characters are accepted here and require --unicode, but marks that combine
+    // with a neighbor never stand alone as a password character.
+    if let Some(c) = s.chars().find(|c| is_combining(*c)) {
+        return Err(format!(
+            "The {} cannot contain combining or zero-width characters (U+{:04X}).",
+            name, c as u32
+        ));
+    }
+
+    Ok(s.to_string())
+}
+
+/// Validate a passphrase word separator.
+pub fn parse_separator(s: &str) -> Result<String, String> {
+    if s.contains(['\n', '\r']) {
+        return Err("The separator cannot contain newline characters.".to_string());
+    }
+    Ok(s.to_string())
+}
+
+/// Validate a file name template for `--output-dir`.
+pub fn parse_filename_template(s: &str) -> Result<String, String> {
+    if s.is_empty() {
+        return Err("The file name template cannot be empty.".to_string());
+    }
+    if s.contains(['/', '\\']) {
+        return Err("The file name template cannot contain path separators.".to_string());
+    }
+    if s == "." || s == ".." {
+        return Err(format!("'{}' is not a valid file name.", s));
+    }
+    Ok(s.to_string())
+}
+
+/// Parse an output template such as `export APP_PASS_{n}="{password}"`. The placeholders
+/// are `{password}`, `{n}` or `{index}`, `{length}`, and `{entropy}`, and `{{` and `}}` are
+/// literal braces.
+pub fn parse_template(s: &str) -> Result<Template, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed {
+                    return Err(
+                        "The template has a '{' without a closing '}'. Write '{{' for a literal brace."
+                            .to_string(),
+                    );
+                }
+                let part = match name.as_str() {
+                    "password" => TemplatePart::Password,
+                    // clap shows '{n}' in help as a line break, so the help names '{index}'.
+                    "n" | "index" => TemplatePart::Index,
+                    "length" => TemplatePart::Length,
+                    "entropy" => TemplatePart::Entropy,
+                    _ => {
+                        return Err(format!(
+                            "Unknown placeholder '{{{}}}'. Valid placeholders are: {{password}}, {{n}}, {{index}}, {{length}}, {{entropy}}.",
+                            name
+                        ));
+                    }
+                };
+                if !text.is_empty() {
+                    parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(part);
+            }
+            '}' => {
+                return Err(
+                    "The template has a '}' without an opening '{'. Write '}}' for a literal brace."
+                        .to_string(),
+                );
+            }
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(TemplatePart::Text(text));
+    }
+    Ok(Template(parts))
+}
+
+/// Parse leet substitutions like `a=4@,e=3`: comma-separated letters, each with the
+/// characters that may replace it.
+pub fn parse_leet_map(s: &str) -> Result<LeetMap, String> {
+    let mut map: Vec<(char, Vec<char>)> = Vec::new();
+    for entry in s.split(',') {
+        let (letter, substitutes) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' is not a substitution like 'a=4@'.", entry))?;
+        let mut letters = letter.chars();
+        let letter = match (letters.next(), letters.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => c.to_ascii_lowercase(),
+            _ => return Err(format!("'{}' is not a single ASCII letter.", letter)),
+        };
+        if substitutes.is_empty() {
+            return Err(format!("The letter '{}' has no substitutes.", letter));
+        }
+        if map.iter().any(|(l, _)| *l == letter) {
+            return Err(format!("The letter '{}' is listed twice.", letter));
+        }
+        map.push((letter, substitutes.chars().collect()));
+    }
+    Ok(LeetMap(map))
+}
+
+/// Parse a category weight like `symbols=0.2`: a category name and a positive factor.
+pub fn parse_weight(s: &str) -> Result<(CharClass, f64), String> {
+    let (name, factor) = s
+        .split_once('=')
+        .ok_or_else(|| format!("'{}' is not a weight like 'symbols=0.2'.", s))?;
+    let class = CharClass::ALL
+        .into_iter()
+        .find(|class| class.name() == name)
+        .ok_or_else(|| {
+            format!(
+                "Unknown category '{}'. Valid categories are: {}.",
+                name,
+                CharClass::ALL.map(CharClass::name).join(", ")
+            )
+        })?;
+    match factor.parse::<f64>() {
+        Ok(0.0) => Err(format!(
+            "A weight of 0 would never use {}. Leave the category out instead.",
+            name
+        )),
+        Ok(factor) if factor.is_finite() && factor > 0.0 => Ok((class, factor)),
+        _ => Err(format!("'{}' is not a positive factor.", factor)),
+    }
+}
+
+/// Validate the number of words of a BIP-39 mnemonic: 12, 15, 18, 21, or 24.
+pub fn parse_mnemonic_words(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(words) if (12..=24).contains(&words) && words % 3 == 0 => Ok(words),
+        _ => Err(format!(
+            "'{}' is not a BIP-39 mnemonic length. Use 12, 15, 18, 21, or 24.",
+            s
+        )),
+    }
+}
+
+/// Validate a probability from 0 to 1.
+pub fn parse_probability(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(format!("'{}' is not a probability from 0 to 1.", s)),
+    }
+}
+
+/// Parse a password pattern mask into its character classes.
+pub fn parse_pattern(s: &str) -> Result<Pattern, String> {
+    if s.is_empty() {
+        return Err("The pattern cannot be empty.".to_string());
+    }
+
+    let mut classes = Vec::new();
+    let mut chars = s.chars().enumerate();
+
+    while let Some((i, c)) = chars.next() {
+        let class = match c {
+            'l' => PatternClass::Lower,
+            'u' => PatternClass::Upper,
+            'd' => PatternClass::Digit,
+            's' => PatternClass::Symbol,
+            'a' => PatternClass::Alnum,
+            'x' => PatternClass::Any,
+            '\\' => match chars.next() {
+                Some((_, literal)) if !literal.is_whitespace() && !literal.is_control() => {
+                    PatternClass::Literal(literal)
+                }
+                Some((_, _)) => {
+                    return Err(format!(
+                        "The escaped character at position {} cannot be whitespace or a control character.",
+                        i + 2
+                    ));
+                }
+                None => {
+                    return Err(
+                        "The pattern cannot end with an unfinished escape '\\'.".to_string()
+                    );
+                }
+            },
+            other => {
+                return Err(format!(
+                    "Invalid pattern character '{}' at position {}. Allowed codes are: l, u, d, s, a, x, and '\\' followed by a literal character.",
+                    other,
+                    i + 1
+                ));
+            }
+        };
+        classes.push(class);
+    }
+
+    Ok(Pattern(classes))
+}
+
+// Whether `c` attaches to a neighboring character instead of standing alone: a combining
+// mark, a variation selector, or a zero-width character.
+fn is_combining(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{FEFF}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_length_accepts_lengths_and_ranges() {
+        assert_eq!(parse_length("12"), Ok(LengthRange { min: 12, max: 12 }));
+        assert_eq!(parse_length("12-16"), Ok(LengthRange { min: 12, max: 16 }));
+        assert_eq!(
+            parse_length_within("2000", MIN_LENGTH, 4096),
+            Ok(LengthRange {
+                min: 2000,
+                max: 2000
+            })
+        );
+    }
+
+    #[test]
+    fn parse_length_rejects_signs() {
+        for s in ["+12", "-12", "12--16", "12-+16"] {
+            assert!(parse_length(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_length_rejects_whitespace() {
+        for s in [" 12", "12 ", "12 -16", "12- 16"] {
+            assert!(parse_length(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_length_rejects_malformed_input() {
+        for s in ["", "-", "12-", "abc", "12-16-20"] {
+            assert!(parse_length(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_length_rejects_empty_ranges() {
+        assert_eq!(
+            parse_length("10-5"),
+            Err("The length range 10-5 is empty. Put the shorter length first.".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_count_accepts_thousand_and_million_suffixes() {
+        assert_eq!(parse_count("0"), Ok(0));
+        assert_eq!(parse_count("20"), Ok(20));
+        assert_eq!(parse_count("10k"), Ok(10_000));
+        assert_eq!(parse_count("10K"), Ok(10_000));
+        assert_eq!(parse_count("2m"), Ok(2_000_000));
+        assert_eq!(parse_count("2M"), Ok(2_000_000));
+        assert_eq!(parse_count("0k"), Ok(0));
+    }
+
+    #[test]
+    fn parse_count_leaves_the_cap_to_max_count() {
+        assert_eq!(parse_count("10m"), Ok(DEFAULT_MAX_COUNT));
+        assert_eq!(parse_count("10000001"), Ok(DEFAULT_MAX_COUNT + 1));
+    }
+
+    #[test]
+    fn parse_count_rejects_overflow() {
+        let max = usize::MAX.to_string();
+        assert_eq!(parse_count(&max), Ok(usize::MAX));
+        for s in [
+            format!("{}0", max),
+            format!("{}k", usize::MAX / 1_000 + 1),
+            format!("{}m", usize::MAX / 1_000_000 + 1),
+        ] {
+            assert_eq!(
+                parse_count(&s),
+                Err(format!("The count '{}' is too large.", s))
+            );
+        }
+        assert_eq!(
+            parse_count(&format!("{}k", usize::MAX / 1_000)),
+            Ok(usize::MAX / 1_000 * 1_000)
+        );
+    }
+
+    #[test]
+    fn parse_count_rejects_malformed_input() {
+        for s in [
+            "", "k", "m", "-1", "+1", " 1", "1 ", "1.5k", "1km", "1g", "10kk", "1_000",
+        ] {
+            assert_eq!(
+                parse_count(s),
+                Err(format!(
+                    "'{}' is not a valid count, such as 20, 10k, or 2m.",
+                    s
+                )),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn parsed_ranges_bound_the_generated_lengths() {
+        use crate::generator::{GeneratorConfig, generate_with_rng};
+        use rand::SeedableRng;
+
+        assert!(parse_length("16-12").is_err());
+
+        let range = parse_length("12-16").unwrap();
+        let config = GeneratorConfig {
+            length: range.min,
+            length_max: Some(range.max),
+            count: 2000,
+            use_lower: true,
+            use_digits: true,
+            ..Default::default()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(52);
+        let lengths: std::collections::BTreeSet<usize> = generate_with_rng(&config, &mut rng)
+            .unwrap()
+            .iter()
+            .map(|password| password.chars().count())
+            .collect();
+        assert_eq!(lengths, (12..=16).collect());
+    }
+
+    #[test]
+    fn parse_length_rejects_short_lengths() {
+        assert!(parse_length("5").is_err());
+        assert!(parse_length("5-10").is_err());
+        assert!(parse_length_within("1", 1, MAX_LENGTH).is_ok());
+    }
+
+    #[test]
+    fn parse_length_rejects_lengths_above_the_maximum() {
+        assert_eq!(
+            parse_length("1025"),
+            Err("The password length 1025 is above the maximum of 1024.".to_string())
+        );
+        assert!(parse_length("12-1025").is_err());
+        assert!(parse_length_within("21", MIN_LENGTH, 20).is_err());
+        assert!(parse_length("99999999999999999999999").is_err());
+    }
+
+    #[test]
+    fn fragments_with_shared_characters_merge_into_one_custom_set() {
+        use crate::generator::{GeneratorConfig, PasswordGenerator};
+
+        let fragments: Vec<String> = ["abc", "b-d", "cx\\-", "x"]
+            .iter()
+            .map(|fragment| parse_chars(fragment).unwrap())
+            .collect();
+        assert_eq!(fragments, ["abc", "bcd", "cx-", "x"]);
+        let config = GeneratorConfig::builder()
+            .custom_chars(fragments.concat())
+            .length(12)
+            .build()
+            .unwrap();
+        let generator = PasswordGenerator::new(&config).unwrap();
+        // The guarantee covers the union once, not each fragment, with each character once in
+        // the order it first appeared.
+        let custom: Vec<&[char]> = generator
+            .required_sets()
+            .iter()
+            .map(|set| set.chars())
+            .collect();
+        assert_eq!(custom, [&['a', 'b', 'c', 'd', 'x', '-'][..]]);
+        assert_eq!(generator.charset(), ['-', 'a', 'b', 'c', 'd', 'x']);
+    }
+
+    #[test]
+    fn parse_chars_keeps_a_hyphen_at_the_start_or_end() {
+        assert_eq!(parse_chars("-ab"), Ok("-ab".to_string()));
+        assert_eq!(parse_chars("ab-"), Ok("ab-".to_string()));
+        assert_eq!(parse_chars("-"), Ok("-".to_string()));
+        assert_eq!(parse_chars("-a-c"), Ok("-abc".to_string()));
+        assert_eq!(parse_chars("a-c-"), Ok("abc-".to_string()));
+    }
+
+    #[test]
+    fn parse_chars_keeps_an_escaped_hyphen() {
+        assert_eq!(parse_chars("a\\-z"), Ok("a-z".to_string()));
+        assert_eq!(parse_chars("\\-\\-"), Ok("--".to_string()));
+        // An escaped hyphen can still end a range.
+        assert_eq!(parse_chars("+-\\-"), Ok("+,-".to_string()));
+    }
+
+    #[test]
+    fn parse_chars_expands_ranges() {
+        assert_eq!(parse_chars("a-f0-9_"), Ok("abcdef0123456789_".to_string()));
+        assert_eq!(parse_chars("a-a"), Ok("a".to_string()));
+        // After a range, a hyphen starts over as a character of its own.
+        assert_eq!(parse_chars("a-c-e"), Ok("abc-e".to_string()));
+        assert_eq!(parse_chars("!--"), Ok("!\"#$%&'()*+,-".to_string()));
+    }
+
+    #[test]
+    fn parse_chars_rejects_bad_ranges() {
+        assert_eq!(
+            parse_chars("abz-a"),
+            Err("The range 'z-a' at positions 3-5 is descending. Write it as 'a-z'.".to_string())
+        );
+        assert_eq!(
+            parse_chars("a-é"),
+            Err("The range 'a-é' at positions 1-3 must be between ASCII characters.".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_chars_counts_positions_in_characters() {
+        assert_eq!(parse_chars("€😀a-c"), Ok("€😀abc".to_string()));
+        assert_eq!(
+            parse_chars("€😀z-a"),
+            Err("The range 'z-a' at positions 3-5 is descending. Write it as 'a-z'.".to_string())
+        );
+        assert_eq!(
+            parse_chars("€\\q"),
+            Err("Invalid escape '\\q' at position 2. Use \\xNN, \\s, \\\\, or \\-.".to_string())
+        );
+    }
+}
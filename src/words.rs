@@ -0,0 +1,77 @@
+//! Detection of common English words hidden in passwords.
+//!
+//! The embedded list holds about 21,000 frequent words, names, and password terms taken from
+//! the zxcvbn frequency lists. It is only built with the `common-words` cargo feature.
+
+use std::collections::HashSet;
+
+/// Frequent lowercase words of at least 3 letters, one per line.
+const COMMON_WORDS: &str = include_str!("wordlists/common_words.txt");
+
+/// The shortest words [`WordFilter`] looks for unless told otherwise.
+pub const DEFAULT_MIN_LENGTH: usize = 4;
+
+/// Finds embedded words, ignoring case and undoing simple leetspeak such as `p4ss`.
+pub struct WordFilter {
+    words: HashSet<&'static str>,
+    min_length: usize,
+    max_length: usize,
+}
+
+impl WordFilter {
+    /// A filter for the embedded words of at least `min_length` letters.
+    pub fn new(min_length: usize) -> Self {
+        let words: HashSet<&'static str> = COMMON_WORDS
+            .lines()
+            .filter(|word| word.len() >= min_length)
+            .collect();
+        let max_length = words.iter().map(|word| word.len()).max().unwrap_or(0);
+
+        Self {
+            words,
+            min_length,
+            max_length,
+        }
+    }
+
+    /// The first word found in `password`, if any.
+    pub fn find(&self, password: &str) -> Option<&'static str> {
+        // '1' stands for 'i' as often as for 'l', so both readings are tried.
+        [normalize(password, 'i'), normalize(password, 'l')]
+            .iter()
+            .find_map(|candidate| self.find_in(candidate))
+    }
+
+    fn find_in(&self, text: &[u8]) -> Option<&'static str> {
+        for start in 0..text.len() {
+            let longest = self.max_length.min(text.len() - start);
+            for length in self.min_length..=longest {
+                // Normalized text is ASCII, and anything else was replaced by a space.
+                let slice = std::str::from_utf8(&text[start..start + length]).ok()?;
+                if let Some(word) = self.words.get(slice) {
+                    return Some(word);
+                }
+            }
+        }
+        None
+    }
+}
+
+// Lowercase `password` and map common leetspeak digits and symbols back to letters, reading
+// '1' as `one`. Every other character becomes a space, which no word contains.
+fn normalize(password: &str, one: char) -> Vec<u8> {
+    password
+        .chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            '0' => 'o',
+            '1' => one,
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            '!' | '|' => 'i',
+            c if c.is_ascii_lowercase() => c,
+            _ => ' ',
+        } as u8)
+        .collect()
+}
@@ -0,0 +1,89 @@
+//! Coloring digits and symbols with `--color`.
+
+use std::process::Command;
+
+const DIGIT: &str = "\x1b[34m";
+const SYMBOL: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+// The stdout of pwgen, ignoring any config file and `PWGEN_*` variables. Stdout is a pipe.
+fn pwgen(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen"))
+        .arg("--no-config")
+        .args(args)
+        .env_clear()
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// The characters of a colored line, each with the escape sequence it is wrapped in, if any.
+fn colored_chars(line: &str) -> Vec<(Option<&str>, char)> {
+    let mut chars = Vec::new();
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let color = [DIGIT, SYMBOL]
+            .into_iter()
+            .find(|color| rest.starts_with(color));
+        match color {
+            Some(color) => {
+                let c = rest[color.len()..].chars().next().unwrap();
+                let end = color.len() + c.len_utf8();
+                assert_eq!(&rest[end..end + RESET.len()], RESET, "{line:?}");
+                chars.push((Some(color), c));
+                rest = &rest[end + RESET.len()..];
+            }
+            None => {
+                chars.push((None, c));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    chars
+}
+
+#[test]
+fn always_wraps_digits_and_symbols_in_their_colors() {
+    assert_eq!(
+        pwgen(&["--seed", "42", "-C", "2", "--color", "always"]),
+        format!(
+            "{SYMBOL}[{RESET}N{SYMBOL}]{RESET}{SYMBOL}{{{RESET}{SYMBOL}}}{RESET}{DIGIT}2{RESET}rd\n\
+             {DIGIT}7{RESET}Wln{DIGIT}7{RESET}{SYMBOL}${RESET}nR\n"
+        )
+    );
+
+    let stdout = pwgen(&["--seed", "37", "-L", "24", "-C", "200", "--color", "always"]);
+    let plain = pwgen(&["--seed", "37", "-L", "24", "-C", "200", "--color", "never"]);
+    assert_eq!(stdout.lines().count(), 200);
+    for (line, plain) in stdout.lines().zip(plain.lines()) {
+        let chars = colored_chars(line);
+        assert_eq!(chars.iter().map(|(_, c)| c).collect::<String>(), plain);
+        for (color, c) in chars {
+            let expected = if c.is_ascii_digit() {
+                Some(DIGIT)
+            } else if c.is_ascii_alphabetic() {
+                None
+            } else {
+                Some(SYMBOL)
+            };
+            assert_eq!(color, expected, "{c:?} in {line:?}");
+        }
+    }
+}
+
+#[test]
+fn color_stays_out_of_pipes_and_files_by_default() {
+    for args in [&["--seed", "42"][..], &["--seed", "42", "--color", "never"]] {
+        let stdout = pwgen(args);
+        assert!(!stdout.contains('\x1b'), "{stdout:?}");
+    }
+
+    let path = std::env::temp_dir().join(format!("pwgen-color-{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    pwgen(&["--seed", "42", "-C", "5", "-o", path.to_str().unwrap()]);
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(content.lines().count(), 5);
+    assert!(!content.contains('\x1b'));
+}
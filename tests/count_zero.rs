@@ -0,0 +1,79 @@
+//! `--count 0`, which checks the options without generating anything.
+
+use std::fs;
+use std::process::{Command, Output};
+
+// Run pwgen, ignoring any config file and `PWGEN_*` variables.
+fn pwgen(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pwgen"))
+        .arg("--no-config")
+        .args(args)
+        .env_clear()
+        .output()
+        .unwrap()
+}
+
+// The exit status and stderr of pwgen, after checking that it wrote nothing to stdout.
+fn dry_run(args: &[&str]) -> (bool, String) {
+    let output = pwgen(args);
+    assert!(output.stdout.is_empty(), "{:?}", output.stdout);
+    (
+        output.status.success(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn prints_the_plan_to_stderr_without_any_passwords() {
+    assert_eq!(
+        dry_run(&["-C", "0", "-L", "12", "-l"]),
+        (
+            true,
+            "The configuration is valid: passwords of 12 characters from 26, with 56.4 bits of entropy each.\n"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        dry_run(&["passphrase", "-C", "0"]),
+        (
+            true,
+            "The configuration is valid: passphrases of 6 words.\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn quiet_silences_the_plan() {
+    assert_eq!(
+        dry_run(&["-C", "0", "-L", "12", "--quiet"]),
+        (true, String::new())
+    );
+}
+
+#[test]
+fn fails_like_a_real_run_would() {
+    let (success, stderr) = dry_run(&["-C", "0", "-L", "4"]);
+    assert!(!success);
+    assert!(stderr.starts_with("Error: --length: "), "{stderr}");
+}
+
+#[test]
+fn creates_and_touches_no_output_file() {
+    let dir = std::env::temp_dir().join(format!("pwgen-count-zero-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let missing = dir.join("missing.txt");
+    let existing = dir.join("existing.txt");
+    fs::write(&existing, "kept\n").unwrap();
+
+    for (path, flag) in [
+        (&missing, "--force"),
+        (&existing, "--force"),
+        (&existing, "--append"),
+    ] {
+        let (success, _) = dry_run(&["-C", "0", "-o", path.to_str().unwrap(), flag]);
+        assert!(success);
+    }
+    assert!(!missing.exists());
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "kept\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
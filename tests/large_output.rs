@@ -0,0 +1,18 @@
+//! The refusal of batches over 1 GiB without `--force-large`.
+
+use std::process::Command;
+
+#[test]
+fn large_batches_are_refused_before_writing_anything() {
+    let output = Command::new(env!("CARGO_BIN_EXE_pwgen"))
+        .args(["--no-config", "-C", "2m", "-L", "1024"])
+        .env_clear()
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Error: The passwords would take up to 1.9 GiB (2000000 of up to 1024 characters). Use --force-large to write them anyway.\n"
+    );
+}
@@ -0,0 +1,35 @@
+//! The `--max-count` cap on `--count`.
+
+use std::process::{Command, Output};
+
+// Run pwgen, ignoring any config file and `PWGEN_*` variables.
+fn pwgen(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pwgen"))
+        .arg("--no-config")
+        .args(args)
+        .env_clear()
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn counts_above_the_default_cap_are_refused_before_generating() {
+    let output = pwgen(&["-C", "10000001"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Error: --count 10000001 is above the limit of 10000000. Use --max-count to raise it.\n"
+    );
+}
+
+#[test]
+fn max_count_moves_the_cap() {
+    let output = pwgen(&["-C", "2k", "--max-count", "1k"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = pwgen(&["-C", "3", "--max-count", "3"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 3);
+}
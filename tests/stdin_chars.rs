@@ -0,0 +1,58 @@
+//! Reading the custom charset from stdin with `--chars -`.
+
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Run pwgen with `stdin` piped in, ignoring any config file and `PWGEN_*` variables.
+fn pwgen(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pwgen"))
+        .arg("--no-config")
+        .args(args)
+        .env_clear()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // pwgen may exit on an error before reading, which closes the pipe.
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn a_piped_charset_makes_up_the_passwords() {
+    let output = pwgen(&["-c", "-", "-L", "12", "-C", "50"], "xyz\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let passwords: Vec<&str> = stdout.lines().collect();
+    assert_eq!(passwords.len(), 50);
+    assert!(passwords.iter().all(|password| password.len() == 12));
+    let seen: BTreeSet<char> = passwords
+        .iter()
+        .flat_map(|password| password.chars())
+        .collect();
+    assert_eq!(seen, BTreeSet::from(['x', 'y', 'z']));
+}
+
+#[test]
+fn a_piped_charset_uses_the_syntax_of_chars_on_every_line() {
+    let output = pwgen(
+        &["-c", "-", "-c", "_", "-L", "16", "-C", "50"],
+        "a-c\n0-2\n",
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let seen: BTreeSet<char> = stdout.lines().flat_map(str::chars).collect();
+    assert_eq!(seen, "abc012_".chars().collect());
+}
+
+#[test]
+fn only_one_option_can_read_stdin() {
+    let output = pwgen(&["-c", "-", "--chars-file", "-"], "abc\n");
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Error: Only one of '--chars -' and '--chars-file -' can read from stdin.\n"
+    );
+}